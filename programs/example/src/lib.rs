@@ -1,37 +1,100 @@
 use anchor_lang::prelude::*;
 use solana_program::pubkey::Pubkey;
 
-use zaffre_anchor::{verify_ownership_proof, NULLIFIER_SEED_PREFIX, ZAFFRE_SEED_PREFIX};
-use zaffre_core::{compute_action_hash, serialize_public_witness};
+use zaffre_anchor::{
+    close_state_account, pay_relayer, rotate_state_account, spend_nullifier,
+    verify_registered_ownership_proof, zaffre_action, VerifierEntry, VerifierRegistry,
+    ZaffreConfig, ZaffreError, NULLIFIER_SEED_PREFIX, ZAFFRE_SEED_PREFIX,
+};
+use zaffre_core::{serialize_public_witness, CloseAction, RotateAction, ZaffreAction};
 
 declare_id!("HDGiZbLeKG5XqixDtAQb9dzuCiCTVP7Yg6En3ZyDHXM1");
 
 pub const CONFIG_SEED: &[u8] = b"zaffre_config";
 
+/// the `set_value` action; clients hash the same struct when they build the proof
+#[derive(ZaffreAction)]
+#[zaffre(discriminator = b"setvalue")]
+pub struct SetValueAction {
+    pub value: u64,
+}
+
+/// the `set_value_relayed` action. its params are `value || relayer || fee_le`, the
+/// `encode_relayed_action_params` layout, hashed with the params digest
+#[derive(ZaffreAction)]
+#[zaffre(discriminator = b"relayset", params_digest)]
+pub struct SetValueRelayedAction {
+    pub value: u64,
+    pub relayer: [u8; 32],
+    pub fee: u64,
+}
+
 #[program]
 pub mod example {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, verifier_program: Pubkey) -> Result<()> {
-        ctx.accounts.config.authority = ctx.accounts.authority.key();
-        ctx.accounts.config.verifier_program = verifier_program;
+    /// registers `verifier_program`, which must be immutable; pass its programdata account
+    /// if it is an upgradeable program
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        circuit_name: String,
+        circuit_version: u32,
+        vk_hash: [u8; 32],
+        timelock_slots: u64,
+    ) -> Result<()> {
+        let accounts = ctx.accounts;
+        let entry = VerifierEntry::new(
+            accounts.verifier_program.key(),
+            &circuit_name,
+            circuit_version,
+            vk_hash,
+        )?;
+        accounts.config.registry = VerifierRegistry::new(
+            accounts.authority.key(),
+            entry,
+            &accounts.verifier_program,
+            accounts.verifier_program_data.as_deref(),
+            timelock_slots,
+        )?;
         Ok(())
     }
 
-    pub fn prepare(
-        _ctx: Context<Prepare>,
-        commitment: [u8; 32],
-        nullifier: [u8; 32],
+    /// schedules a verifier change; it takes effect via `apply_verifier_upgrade` once the
+    /// registry timelock has passed
+    pub fn propose_verifier_upgrade(
+        ctx: Context<ProposeVerifier>,
+        circuit_name: String,
+        circuit_version: u32,
+        vk_hash: [u8; 32],
     ) -> Result<()> {
-        let _ = (commitment, nullifier);
+        let accounts = ctx.accounts;
+        let entry = VerifierEntry::new(
+            accounts.verifier_program.key(),
+            &circuit_name,
+            circuit_version,
+            vk_hash,
+        )?;
+        let slot = Clock::get()?.slot;
+        let activates_at_slot = accounts.config.registry.propose_upgrade(
+            entry,
+            &accounts.verifier_program,
+            accounts.verifier_program_data.as_deref(),
+            slot,
+        )?;
+        msg!("Verifier upgrade activates at slot {}", activates_at_slot);
         Ok(())
     }
 
-    pub fn update_verifier(ctx: Context<UpdateVerifier>, verifier_program: Pubkey) -> Result<()> {
-        ctx.accounts.config.verifier_program = verifier_program;
+    pub fn apply_verifier_upgrade(ctx: Context<UpdateVerifier>) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        ctx.accounts.config.registry.apply_upgrade(slot)?;
         Ok(())
     }
 
+    pub fn cancel_verifier_upgrade(ctx: Context<UpdateVerifier>) -> Result<()> {
+        ctx.accounts.config.registry.cancel_upgrade()
+    }
+
     pub fn set_value(
         ctx: Context<SetValue>,
         commitment: [u8; 32],
@@ -40,37 +103,163 @@ pub mod example {
         value: u64,
         nonce: u64,
     ) -> Result<()> {
-        require!(
-            ctx.accounts.nullifier_state.spent_at_slot == 0,
-            ExampleError::NullifierAlreadySpent
-        );
-
-        let action_discriminator = *b"setvalue";
-        let action_params = value.to_le_bytes();
-        let action_hash = compute_action_hash(
-            &ctx.program_id,
-            &ctx.accounts.state.key(),
-            &action_discriminator,
-            &action_params,
+        ctx.accounts.verify_and_spend(
+            ctx.program_id,
+            &commitment,
+            &nullifier,
+            &proof,
+            &SetValueAction { value },
             nonce,
-        );
+        )?;
+        ctx.accounts.state.value = value;
+        ctx.accounts.state.bump = ctx.bumps.state;
+        ctx.accounts.state.commitment = commitment;
+        Ok(())
+    }
+
+    /// `set_value` submitted by a relayer, who signs and pays the transaction and is
+    /// reimbursed `fee` lamports from the state account. the proof binds the relayer and
+    /// fee, so the user's wallet never appears in the transaction.
+    pub fn set_value_relayed(
+        ctx: Context<SetValueRelayed>,
+        commitment: [u8; 32],
+        nullifier: [u8; 32],
+        proof: Vec<u8>,
+        value: u64,
+        fee: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        let state = ctx.accounts.state.to_account_info();
+        let relayer = ctx.accounts.relayer.to_account_info();
+        let action_hash = SetValueRelayedAction {
+            value,
+            relayer: relayer.key.to_bytes(),
+            fee,
+        }
+        .action_hash(ctx.program_id, state.key, nonce);
 
         let public_witness = serialize_public_witness(
             &commitment,
-            &ctx.accounts.state.key(),
-            &ctx.program_id,
+            state.key,
+            ctx.program_id,
             &action_hash,
             &nullifier,
         );
 
-        verify_ownership_proof(&ctx.accounts.verifier_program, &proof, &public_witness)?;
+        verify_registered_ownership_proof(
+            &ctx.accounts.config.registry,
+            &ctx.accounts.verifier_program,
+            &proof,
+            &public_witness,
+        )?;
 
         ctx.accounts.state.value = value;
-        ctx.accounts.state.bump = ctx.bumps.state;
-        ctx.accounts.state.commitment = commitment;
-        ctx.accounts.nullifier_state.spent_at_slot = Clock::get()?.slot;
+        spend_nullifier(
+            ctx.program_id,
+            &ctx.accounts.nullifier_state,
+            &nullifier,
+            &relayer,
+            &ctx.accounts.system_program,
+        )?;
+        pay_relayer(ctx.program_id, &state, &relayer, fee)
+    }
+
+    /// moves the state to the pda of `new_commitment`, authorized by an ownership
+    /// proof over `old_commitment` whose action params are `new_commitment`
+    pub fn rotate(
+        ctx: Context<Rotate>,
+        old_commitment: [u8; 32],
+        new_commitment: [u8; 32],
+        nullifier: [u8; 32],
+        proof: Vec<u8>,
+        nonce: u64,
+    ) -> Result<()> {
+        let old_state = ctx.accounts.old_state.to_account_info();
+        let action_hash =
+            RotateAction { new_commitment }.action_hash(ctx.program_id, old_state.key, nonce);
+
+        let public_witness = serialize_public_witness(
+            &old_commitment,
+            old_state.key,
+            ctx.program_id,
+            &action_hash,
+            &nullifier,
+        );
+
+        verify_registered_ownership_proof(
+            &ctx.accounts.config.registry,
+            &ctx.accounts.verifier_program,
+            &proof,
+            &public_witness,
+        )?;
+
+        let new_state = ctx.accounts.new_state.to_account_info();
+        let bump = rotate_state_account(
+            ctx.program_id,
+            &old_state,
+            &new_state,
+            &old_commitment,
+            &new_commitment,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        let mut data = new_state.try_borrow_mut_data()?;
+        let mut state = DemoState::try_deserialize(&mut &data[..])?;
+        state.bump = bump;
+        state.commitment = new_commitment;
+        state.try_serialize(&mut &mut data[..])?;
+        drop(data);
+
+        spend_nullifier(
+            ctx.program_id,
+            &ctx.accounts.nullifier_state,
+            &nullifier,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
         Ok(())
     }
+
+    /// closes the state account and sends its lamports to `destination`, authorized by
+    /// an ownership proof whose action params are the destination pubkey
+    pub fn close(
+        ctx: Context<Close>,
+        commitment: [u8; 32],
+        nullifier: [u8; 32],
+        proof: Vec<u8>,
+        nonce: u64,
+    ) -> Result<()> {
+        let state = ctx.accounts.state.to_account_info();
+        let destination = ctx.accounts.destination.to_account_info();
+        let action_hash = CloseAction {
+            destination: destination.key.to_bytes(),
+        }
+        .action_hash(ctx.program_id, state.key, nonce);
+
+        let public_witness = serialize_public_witness(
+            &commitment,
+            state.key,
+            ctx.program_id,
+            &action_hash,
+            &nullifier,
+        );
+
+        verify_registered_ownership_proof(
+            &ctx.accounts.config.registry,
+            &ctx.accounts.verifier_program,
+            &proof,
+            &public_witness,
+        )?;
+
+        spend_nullifier(
+            ctx.program_id,
+            &ctx.accounts.nullifier_state,
+            &nullifier,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+        close_state_account(ctx.program_id, &state, &commitment, &destination)
+    }
 }
 
 #[derive(Accounts)]
@@ -85,24 +274,45 @@ pub struct Initialize<'info> {
     pub config: Account<'info, Config>,
     #[account(mut)]
     pub authority: Signer<'info>,
+    /// CHECK: checked to be an immutable program by `VerifierRegistry::new`
+    pub verifier_program: UncheckedAccount<'info>,
+    /// CHECK: the verifier's programdata, checked by `VerifierRegistry::new`
+    pub verifier_program_data: Option<UncheckedAccount<'info>>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct ProposeVerifier<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.registry.authority == authority.key() @ ExampleError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub authority: Signer<'info>,
+    /// CHECK: checked to be an immutable program by `propose_upgrade`
+    pub verifier_program: UncheckedAccount<'info>,
+    /// CHECK: the verifier's programdata, checked by `propose_upgrade`
+    pub verifier_program_data: Option<UncheckedAccount<'info>>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateVerifier<'info> {
     #[account(
         mut,
         seeds = [CONFIG_SEED],
         bump,
-        has_one = authority
+        constraint = config.registry.authority == authority.key() @ ExampleError::Unauthorized
     )]
     pub config: Account<'info, Config>,
     pub authority: Signer<'info>,
 }
 
+/// `config`, `nullifier_state` and `verifier_program` come from `zaffre_action`
+#[zaffre_action(action = SetValueAction)]
 #[derive(Accounts)]
-#[instruction(commitment: [u8; 32], nullifier: [u8; 32])]
-pub struct Prepare<'info> {
+pub struct SetValue<'info> {
     #[account(
         init_if_needed,
         payer = payer,
@@ -111,46 +321,108 @@ pub struct Prepare<'info> {
         bump
     )]
     pub state: Account<'info, DemoState>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32], nullifier: [u8; 32])]
+pub struct SetValueRelayed<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// funded by the user ahead of time; pays the relayer fee
+    #[account(
+        mut,
+        seeds = [ZAFFRE_SEED_PREFIX, commitment.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, DemoState>,
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    /// CHECK: created by spend_nullifier, which fails if the nullifier was spent
     #[account(
-        init_if_needed,
-        payer = payer,
-        space = 8 + NullifierState::STATE_SIZE,
+        mut,
         seeds = [NULLIFIER_SEED_PREFIX, nullifier.as_ref()],
         bump
     )]
-    pub nullifier_state: Account<'info, NullifierState>,
+    pub nullifier_state: UncheckedAccount<'info>,
+    /// CHECK: must be the registry's active verifier, checked before the cpi
+    #[account(
+        constraint = config.registry.is_registered(verifier_program.key)
+            @ ZaffreError::UnregisteredVerifier
+    )]
+    pub verifier_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(old_commitment: [u8; 32], new_commitment: [u8; 32], nullifier: [u8; 32])]
+pub struct Rotate<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [ZAFFRE_SEED_PREFIX, old_commitment.as_ref()],
+        bump
+    )]
+    pub old_state: Account<'info, DemoState>,
+    /// CHECK: must be the empty pda of new_commitment, enforced by rotate_state_account
+    #[account(
+        mut,
+        seeds = [ZAFFRE_SEED_PREFIX, new_commitment.as_ref()],
+        bump
+    )]
+    pub new_state: UncheckedAccount<'info>,
+    /// CHECK: created by spend_nullifier, which fails if the nullifier was spent
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, nullifier.as_ref()],
+        bump
+    )]
+    pub nullifier_state: UncheckedAccount<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
+    /// CHECK: must be the registry's active verifier, checked before the cpi
+    #[account(
+        constraint = config.registry.is_registered(verifier_program.key)
+            @ ZaffreError::UnregisteredVerifier
+    )]
+    pub verifier_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(commitment: [u8; 32], nullifier: [u8; 32])]
-pub struct SetValue<'info> {
-    #[account(
-        seeds = [CONFIG_SEED],
-        bump,
-        has_one = verifier_program
-    )]
+pub struct Close<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
     pub config: Account<'info, Config>,
     #[account(
         mut,
         seeds = [ZAFFRE_SEED_PREFIX, commitment.as_ref()],
-        bump
+        bump = state.bump
     )]
     pub state: Account<'info, DemoState>,
+    /// CHECK: any account; bound by the close proof's action params
     #[account(mut)]
-    pub payer: Signer<'info>,
+    pub destination: UncheckedAccount<'info>,
+    /// CHECK: created by spend_nullifier, which fails if the nullifier was spent
     #[account(
         mut,
         seeds = [NULLIFIER_SEED_PREFIX, nullifier.as_ref()],
         bump
     )]
-    pub nullifier_state: Account<'info, NullifierState>,
-    /// CHECK: sunspot verifier program is stateless
+    pub nullifier_state: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: must be the registry's active verifier, checked before the cpi
+    #[account(
+        constraint = config.registry.is_registered(verifier_program.key)
+            @ ZaffreError::UnregisteredVerifier
+    )]
     pub verifier_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
-    pub clock: Sysvar<'info, Clock>,
 }
 
 #[account]
@@ -174,25 +446,23 @@ pub fn derive_config_pda(program_id: &Pubkey) -> (Pubkey, u8) {
 
 #[account]
 pub struct Config {
-    pub authority: Pubkey,
-    pub verifier_program: Pubkey,
+    pub registry: VerifierRegistry,
 }
 
 impl Config {
-    pub const STATE_SIZE: usize = 32 + 32;
+    pub const STATE_SIZE: usize = VerifierRegistry::SIZE;
 }
 
-#[account]
-pub struct NullifierState {
-    pub spent_at_slot: u64,
-}
+impl ZaffreConfig for Config {
+    const SEED: &'static [u8] = CONFIG_SEED;
 
-impl NullifierState {
-    pub const STATE_SIZE: usize = 8;
+    fn registry(&self) -> &VerifierRegistry {
+        &self.registry
+    }
 }
 
 #[error_code]
 pub enum ExampleError {
-    #[msg("Nullifier already spent")]
-    NullifierAlreadySpent,
+    #[msg("Signer is not the config authority")]
+    Unauthorized,
 }
@@ -4,14 +4,15 @@ use solana_program::entrypoint::ProgramResult;
 use solana_program::instruction::Instruction;
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
 use solana_program::system_program;
 use solana_program::sysvar;
 use solana_program_test::{processor, ProgramTest};
-use solana_sdk::signature::Signer;
+use solana_sdk::signature::{Keypair, Signer};
 use solana_sdk::transaction::Transaction;
 
 use example::DemoState;
-use zaffre_anchor::{GROTH16_PROOF_SIZE, NULLIFIER_SEED_PREFIX};
+use zaffre_anchor::{NullifierStateData, GROTH16_PROOF_SIZE, NULLIFIER_SEED_PREFIX};
 
 fn mock_verifier_process(
     _program_id: &Pubkey,
@@ -65,11 +66,16 @@ async fn test_set_value_updates_state() {
         accounts: example::accounts::Initialize {
             config: config_pda,
             authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
             system_program: system_program::ID,
         }
         .to_account_metas(None),
         data: example::instruction::Initialize {
-            verifier_program: verifier_program_id,
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
         }
         .data(),
     };
@@ -78,26 +84,6 @@ async fn test_set_value_updates_state() {
     init_tx.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(init_tx).await.unwrap();
 
-    let prepare_ix = Instruction {
-        program_id: example::id(),
-        accounts: example::accounts::Prepare {
-            state: state_pda,
-            nullifier_state: nullifier_pda,
-            payer: payer.pubkey(),
-            system_program: system_program::ID,
-        }
-        .to_account_metas(None),
-        data: example::instruction::Prepare {
-            commitment,
-            nullifier,
-        }
-        .data(),
-    };
-
-    let mut prepare_tx = Transaction::new_with_payer(&[prepare_ix], Some(&payer.pubkey()));
-    prepare_tx.sign(&[&payer], recent_blockhash);
-    banks_client.process_transaction(prepare_tx).await.unwrap();
-
     let accounts = example::accounts::SetValue {
         config: config_pda,
         state: state_pda,
@@ -165,11 +151,16 @@ async fn test_invalid_proof_length_fails() {
         accounts: example::accounts::Initialize {
             config: config_pda,
             authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
             system_program: system_program::ID,
         }
         .to_account_metas(None),
         data: example::instruction::Initialize {
-            verifier_program: verifier_program_id,
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
         }
         .data(),
     };
@@ -178,26 +169,6 @@ async fn test_invalid_proof_length_fails() {
     init_tx.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(init_tx).await.unwrap();
 
-    let prepare_ix = Instruction {
-        program_id: example::id(),
-        accounts: example::accounts::Prepare {
-            state: state_pda,
-            nullifier_state: nullifier_pda,
-            payer: payer.pubkey(),
-            system_program: system_program::ID,
-        }
-        .to_account_metas(None),
-        data: example::instruction::Prepare {
-            commitment,
-            nullifier,
-        }
-        .data(),
-    };
-
-    let mut prepare_tx = Transaction::new_with_payer(&[prepare_ix], Some(&payer.pubkey()));
-    prepare_tx.sign(&[&payer], recent_blockhash);
-    banks_client.process_transaction(prepare_tx).await.unwrap();
-
     let accounts = example::accounts::SetValue {
         config: config_pda,
         state: state_pda,
@@ -258,11 +229,16 @@ async fn test_nullifier_replay_fails() {
         accounts: example::accounts::Initialize {
             config: config_pda,
             authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
             system_program: system_program::ID,
         }
         .to_account_metas(None),
         data: example::instruction::Initialize {
-            verifier_program: verifier_program_id,
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
         }
         .data(),
     };
@@ -271,26 +247,6 @@ async fn test_nullifier_replay_fails() {
     init_tx.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(init_tx).await.unwrap();
 
-    let prepare_ix = Instruction {
-        program_id: example::id(),
-        accounts: example::accounts::Prepare {
-            state: state_pda,
-            nullifier_state: nullifier_pda,
-            payer: payer.pubkey(),
-            system_program: system_program::ID,
-        }
-        .to_account_metas(None),
-        data: example::instruction::Prepare {
-            commitment,
-            nullifier,
-        }
-        .data(),
-    };
-
-    let mut prepare_tx = Transaction::new_with_payer(&[prepare_ix], Some(&payer.pubkey()));
-    prepare_tx.sign(&[&payer], recent_blockhash);
-    banks_client.process_transaction(prepare_tx).await.unwrap();
-
     let accounts = example::accounts::SetValue {
         config: config_pda,
         state: state_pda,
@@ -336,3 +292,559 @@ async fn test_nullifier_replay_fails() {
     replay_tx.sign(&[&payer], recent_blockhash);
     assert!(banks_client.process_transaction(replay_tx).await.is_err());
 }
+
+#[tokio::test]
+async fn test_prefunded_nullifier_is_spent() {
+    let verifier_program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "example",
+        example::id(),
+        processor!(demo_processor),
+    );
+    program_test.add_program(
+        "mock_verifier",
+        verifier_program_id,
+        processor!(mock_verifier_process),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let commitment = [11u8; 32];
+    let (state_pda, _) = example::derive_state_pda(&example::id(), &commitment);
+    let nullifier = [12u8; 32];
+    let (nullifier_pda, _) =
+        Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &nullifier], &example::id());
+    let (config_pda, _) = example::derive_config_pda(&example::id());
+
+    let init_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::Initialize {
+            config: config_pda,
+            authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::Initialize {
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
+        }
+        .data(),
+    };
+    // anyone can send lamports to the nullifier address before it is spent
+    let prefund_ix = system_instruction::transfer(&payer.pubkey(), &nullifier_pda, 1_000);
+    let set_value_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::SetValue {
+            config: config_pda,
+            state: state_pda,
+            payer: payer.pubkey(),
+            nullifier_state: nullifier_pda,
+            verifier_program: verifier_program_id,
+            system_program: system_program::ID,
+            clock: sysvar::clock::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::SetValue {
+            commitment,
+            nullifier,
+            proof: vec![0u8; GROTH16_PROOF_SIZE],
+            value: 4,
+            nonce: 1,
+        }
+        .data(),
+    };
+
+    let mut tx =
+        Transaction::new_with_payer(&[init_ix, prefund_ix, set_value_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let nullifier_account = banks_client
+        .get_account(nullifier_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(nullifier_account.owner, example::id());
+    let state = NullifierStateData::from_bytes(nullifier_account.data[..].try_into().unwrap());
+    assert!(state.spent_at_slot > 0);
+}
+
+#[tokio::test]
+async fn test_set_value_relayed_pays_relayer() {
+    let verifier_program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "example",
+        example::id(),
+        processor!(demo_processor),
+    );
+    program_test.add_program(
+        "mock_verifier",
+        verifier_program_id,
+        processor!(mock_verifier_process),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let commitment = [13u8; 32];
+    let (state_pda, _) = example::derive_state_pda(&example::id(), &commitment);
+    let set_nullifier = [14u8; 32];
+    let relayed_nullifier = [15u8; 32];
+    let (set_nullifier_pda, _) =
+        Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &set_nullifier], &example::id());
+    let (relayed_nullifier_pda, _) = Pubkey::find_program_address(
+        &[NULLIFIER_SEED_PREFIX, &relayed_nullifier],
+        &example::id(),
+    );
+    let (config_pda, _) = example::derive_config_pda(&example::id());
+    let relayer = Keypair::new();
+    let fee = 50_000u64;
+
+    let init_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::Initialize {
+            config: config_pda,
+            authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::Initialize {
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
+        }
+        .data(),
+    };
+    let set_value_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::SetValue {
+            config: config_pda,
+            state: state_pda,
+            payer: payer.pubkey(),
+            nullifier_state: set_nullifier_pda,
+            verifier_program: verifier_program_id,
+            system_program: system_program::ID,
+            clock: sysvar::clock::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::SetValue {
+            commitment,
+            nullifier: set_nullifier,
+            proof: vec![0u8; GROTH16_PROOF_SIZE],
+            value: 1,
+            nonce: 1,
+        }
+        .data(),
+    };
+    // the user funds the state account; the relayer funds its own fee payer
+    let fund_state_ix = system_instruction::transfer(&payer.pubkey(), &state_pda, 1_000_000);
+    let fund_relayer_ix =
+        system_instruction::transfer(&payer.pubkey(), &relayer.pubkey(), 10_000_000);
+    let mut setup_tx = Transaction::new_with_payer(
+        &[init_ix, set_value_ix, fund_state_ix, fund_relayer_ix],
+        Some(&payer.pubkey()),
+    );
+    setup_tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(setup_tx).await.unwrap();
+
+    let state_before = banks_client.get_balance(state_pda).await.unwrap();
+    let relayed_ix = |fee: u64| Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::SetValueRelayed {
+            config: config_pda,
+            state: state_pda,
+            relayer: relayer.pubkey(),
+            nullifier_state: relayed_nullifier_pda,
+            verifier_program: verifier_program_id,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::SetValueRelayed {
+            commitment,
+            nullifier: relayed_nullifier,
+            proof: vec![0u8; GROTH16_PROOF_SIZE],
+            value: 2,
+            fee,
+            nonce: 2,
+        }
+        .data(),
+    };
+
+    // more than the state can pay while staying rent exempt
+    let mut tx = Transaction::new_with_payer(&[relayed_ix(state_before)], Some(&relayer.pubkey()));
+    tx.sign(&[&relayer], recent_blockhash);
+    assert!(banks_client.process_transaction(tx).await.is_err());
+
+    let mut tx = Transaction::new_with_payer(&[relayed_ix(fee)], Some(&relayer.pubkey()));
+    tx.sign(&[&relayer], recent_blockhash);
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let state_account = banks_client.get_account(state_pda).await.unwrap().unwrap();
+    assert_eq!(state_account.lamports, state_before - fee);
+    let mut data: &[u8] = &state_account.data;
+    assert_eq!(DemoState::try_deserialize(&mut data).unwrap().value, 2);
+}
+
+#[tokio::test]
+async fn test_rotate_moves_state() {
+    let verifier_program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "example",
+        example::id(),
+        processor!(demo_processor),
+    );
+    program_test.add_program(
+        "mock_verifier",
+        verifier_program_id,
+        processor!(mock_verifier_process),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let old_commitment = [4u8; 32];
+    let new_commitment = [5u8; 32];
+    let (old_pda, _) = example::derive_state_pda(&example::id(), &old_commitment);
+    let (new_pda, new_bump) = example::derive_state_pda(&example::id(), &new_commitment);
+    let set_nullifier = [6u8; 32];
+    let rotate_nullifier = [10u8; 32];
+    let (set_nullifier_pda, _) =
+        Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &set_nullifier], &example::id());
+    let (rotate_nullifier_pda, _) = Pubkey::find_program_address(
+        &[NULLIFIER_SEED_PREFIX, &rotate_nullifier],
+        &example::id(),
+    );
+    let (config_pda, _) = example::derive_config_pda(&example::id());
+    let proof = vec![0u8; GROTH16_PROOF_SIZE];
+    let value = 77u64;
+
+    let init_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::Initialize {
+            config: config_pda,
+            authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::Initialize {
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
+        }
+        .data(),
+    };
+
+    let set_value_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::SetValue {
+            config: config_pda,
+            state: old_pda,
+            payer: payer.pubkey(),
+            nullifier_state: set_nullifier_pda,
+            verifier_program: verifier_program_id,
+            system_program: system_program::ID,
+            clock: sysvar::clock::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::SetValue {
+            commitment: old_commitment,
+            nullifier: set_nullifier,
+            proof: proof.clone(),
+            value,
+            nonce: 1,
+        }
+        .data(),
+    };
+
+    let mut setup_tx =
+        Transaction::new_with_payer(&[init_ix, set_value_ix], Some(&payer.pubkey()));
+    setup_tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(setup_tx).await.unwrap();
+
+    let rotate_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::Rotate {
+            config: config_pda,
+            old_state: old_pda,
+            new_state: new_pda,
+            nullifier_state: rotate_nullifier_pda,
+            payer: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::Rotate {
+            old_commitment,
+            new_commitment,
+            nullifier: rotate_nullifier,
+            proof,
+            nonce: 2,
+        }
+        .data(),
+    };
+
+    let mut rotate_tx = Transaction::new_with_payer(&[rotate_ix.clone()], Some(&payer.pubkey()));
+    rotate_tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(rotate_tx).await.unwrap();
+
+    assert!(banks_client.get_account(old_pda).await.unwrap().is_none());
+    let new_account = banks_client.get_account(new_pda).await.unwrap().unwrap();
+    assert_eq!(new_account.owner, example::id());
+    let mut data: &[u8] = &new_account.data;
+    let state = DemoState::try_deserialize(&mut data).unwrap();
+    assert_eq!(state.value, value);
+    assert_eq!(state.commitment, new_commitment);
+    assert_eq!(state.bump, new_bump);
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut replay_tx = Transaction::new_with_payer(&[rotate_ix], Some(&payer.pubkey()));
+    replay_tx.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(replay_tx).await.is_err());
+}
+
+#[tokio::test]
+async fn test_close_returns_lamports_to_destination() {
+    let verifier_program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "example",
+        example::id(),
+        processor!(demo_processor),
+    );
+    program_test.add_program(
+        "mock_verifier",
+        verifier_program_id,
+        processor!(mock_verifier_process),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let commitment = [11u8; 32];
+    let (state_pda, _) = example::derive_state_pda(&example::id(), &commitment);
+    let set_nullifier = [12u8; 32];
+    let close_nullifier = [13u8; 32];
+    let (set_nullifier_pda, _) =
+        Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &set_nullifier], &example::id());
+    let (close_nullifier_pda, _) =
+        Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &close_nullifier], &example::id());
+    let (config_pda, _) = example::derive_config_pda(&example::id());
+    let destination = Pubkey::new_unique();
+    let proof = vec![0u8; GROTH16_PROOF_SIZE];
+
+    let init_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::Initialize {
+            config: config_pda,
+            authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::Initialize {
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
+        }
+        .data(),
+    };
+
+    let set_value_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::SetValue {
+            config: config_pda,
+            state: state_pda,
+            payer: payer.pubkey(),
+            nullifier_state: set_nullifier_pda,
+            verifier_program: verifier_program_id,
+            system_program: system_program::ID,
+            clock: sysvar::clock::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::SetValue {
+            commitment,
+            nullifier: set_nullifier,
+            proof: proof.clone(),
+            value: 5,
+            nonce: 1,
+        }
+        .data(),
+    };
+
+    let mut setup_tx =
+        Transaction::new_with_payer(&[init_ix, set_value_ix], Some(&payer.pubkey()));
+    setup_tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(setup_tx).await.unwrap();
+    let state_lamports = banks_client
+        .get_account(state_pda)
+        .await
+        .unwrap()
+        .unwrap()
+        .lamports;
+
+    let close_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::Close {
+            config: config_pda,
+            state: state_pda,
+            destination,
+            nullifier_state: close_nullifier_pda,
+            payer: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::Close {
+            commitment,
+            nullifier: close_nullifier,
+            proof,
+            nonce: 2,
+        }
+        .data(),
+    };
+
+    let mut close_tx = Transaction::new_with_payer(&[close_ix.clone()], Some(&payer.pubkey()));
+    close_tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(close_tx).await.unwrap();
+
+    assert!(banks_client.get_account(state_pda).await.unwrap().is_none());
+    let destination_account = banks_client
+        .get_account(destination)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(destination_account.lamports, state_lamports);
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut replay_tx = Transaction::new_with_payer(&[close_ix], Some(&payer.pubkey()));
+    replay_tx.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(replay_tx).await.is_err());
+}
+
+#[tokio::test]
+async fn test_unregistered_verifier_rejected() {
+    let verifier_program_id = Pubkey::new_unique();
+    let other_verifier_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "example",
+        example::id(),
+        processor!(demo_processor),
+    );
+    program_test.add_program(
+        "mock_verifier",
+        verifier_program_id,
+        processor!(mock_verifier_process),
+    );
+    program_test.add_program(
+        "other_mock_verifier",
+        other_verifier_id,
+        processor!(mock_verifier_process),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let commitment = [7u8; 32];
+    let (state_pda, _) = example::derive_state_pda(&example::id(), &commitment);
+    let nullifier = [8u8; 32];
+    let (nullifier_pda, _) =
+        Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &nullifier], &example::id());
+    let (config_pda, _) = example::derive_config_pda(&example::id());
+
+    let init_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::Initialize {
+            config: config_pda,
+            authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::Initialize {
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
+        }
+        .data(),
+    };
+    let mut setup_tx = Transaction::new_with_payer(&[init_ix], Some(&payer.pubkey()));
+    setup_tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(setup_tx).await.unwrap();
+
+    let set_value_ix = |verifier_program: Pubkey| Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::SetValue {
+            config: config_pda,
+            state: state_pda,
+            payer: payer.pubkey(),
+            nullifier_state: nullifier_pda,
+            verifier_program,
+            system_program: system_program::ID,
+            clock: sysvar::clock::ID,
+        }
+        .to_account_metas(None),
+        data: example::instruction::SetValue {
+            commitment,
+            nullifier,
+            proof: vec![0u8; GROTH16_PROOF_SIZE],
+            value: 3,
+            nonce: 1,
+        }
+        .data(),
+    };
+
+    let mut tx = Transaction::new_with_payer(
+        &[set_value_ix(other_verifier_id)],
+        Some(&payer.pubkey()),
+    );
+    tx.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(tx).await.is_err());
+
+    let update_accounts = example::accounts::UpdateVerifier {
+        config: config_pda,
+        authority: payer.pubkey(),
+    };
+    let propose_ix = Instruction {
+        program_id: example::id(),
+        accounts: example::accounts::ProposeVerifier {
+            config: config_pda,
+            authority: payer.pubkey(),
+            verifier_program: other_verifier_id,
+            verifier_program_data: None,
+        }
+        .to_account_metas(None),
+        data: example::instruction::ProposeVerifierUpgrade {
+            circuit_name: "ownership".to_string(),
+            circuit_version: 2,
+            vk_hash: [1u8; 32],
+        }
+        .data(),
+    };
+    let apply_ix = Instruction {
+        program_id: example::id(),
+        accounts: update_accounts.to_account_metas(None),
+        data: example::instruction::ApplyVerifierUpgrade {}.data(),
+    };
+    let mut tx = Transaction::new_with_payer(
+        &[propose_ix, apply_ix, set_value_ix(other_verifier_id)],
+        Some(&payer.pubkey()),
+    );
+    tx.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let config_account = banks_client.get_account(config_pda).await.unwrap().unwrap();
+    let mut data: &[u8] = &config_account.data;
+    let config = example::Config::try_deserialize(&mut data).unwrap();
+    assert_eq!(config.registry.active.program_id, other_verifier_id);
+    assert_eq!(config.registry.active.circuit_version, 2);
+    assert!(config.registry.pending.is_none());
+}
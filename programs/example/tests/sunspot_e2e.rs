@@ -11,10 +11,8 @@ use solana_program_test::{processor, ProgramTest};
 use solana_sdk::signature::Signer;
 use solana_sdk::transaction::Transaction;
 
-use zaffre_core::{
-    compute_action_hash, extract_public_inputs, PUBLIC_INPUT_BYTES, PUBLIC_WITNESS_SIZE,
-};
-use example::DemoState;
+use zaffre_core::{extract_public_inputs, ZaffreAction, PUBLIC_INPUT_BYTES, PUBLIC_WITNESS_SIZE};
+use example::{DemoState, SetValueAction};
 use zaffre_anchor::NULLIFIER_SEED_PREFIX;
 
 fn read_public_inputs(path: &PathBuf) -> [u8; PUBLIC_INPUT_BYTES] {
@@ -87,14 +85,8 @@ async fn test_set_value_with_real_sunspot_verifier() {
         "public witness PDA does not match commitment-derived PDA"
     );
 
-    let action_discriminator = *b"setvalue";
-    let expected_action_hash = compute_action_hash(
-        &program_id,
-        &expected_pda,
-        &action_discriminator,
-        &value.to_le_bytes(),
-        nonce,
-    );
+    let expected_action_hash =
+        SetValueAction { value }.action_hash(&program_id, &expected_pda, nonce);
     assert_eq!(
         action_hash, expected_action_hash,
         "public witness action_hash does not match value/nonce"
@@ -130,11 +122,16 @@ async fn test_set_value_with_real_sunspot_verifier() {
         accounts: example::accounts::Initialize {
             config: config_pda,
             authority: payer.pubkey(),
+            verifier_program: verifier_program_id,
+            verifier_program_data: None,
             system_program: system_program::ID,
         }
         .to_account_metas(None),
         data: example::instruction::Initialize {
-            verifier_program: verifier_program_id,
+            circuit_name: "ownership".to_string(),
+            circuit_version: 1,
+            vk_hash: [0u8; 32],
+            timelock_slots: 0,
         }
         .data(),
     };
@@ -143,30 +140,6 @@ async fn test_set_value_with_real_sunspot_verifier() {
     init_tx.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(init_tx).await.unwrap();
 
-    let prepare_ix = Instruction {
-        program_id,
-        accounts: example::accounts::Prepare {
-            state: expected_pda,
-            nullifier_state: Pubkey::find_program_address(
-                &[NULLIFIER_SEED_PREFIX, &nullifier],
-                &program_id,
-            )
-            .0,
-            payer: payer.pubkey(),
-            system_program: system_program::ID,
-        }
-        .to_account_metas(None),
-        data: example::instruction::Prepare {
-            commitment,
-            nullifier,
-        }
-        .data(),
-    };
-
-    let mut prepare_tx = Transaction::new_with_payer(&[prepare_ix], Some(&payer.pubkey()));
-    prepare_tx.sign(&[&payer], recent_blockhash);
-    banks_client.process_transaction(prepare_tx).await.unwrap();
-
     let accounts = example::accounts::SetValue {
         config: config_pda,
         state: expected_pda,
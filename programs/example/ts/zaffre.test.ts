@@ -68,18 +68,18 @@ describe("zaffre wasm + anchor compatibility", () => {
     );
 
     const decodeRawInputs = (witness: Uint8Array): Uint8Array => {
-      assert.equal(witness.length, 5132);
+      assert.equal(witness.length, 332);
       const raw = new Uint8Array(160);
       let offset = 12;
-      for (let i = 0; i < raw.length; i += 1) {
-        raw[i] = witness[offset + 31];
+      for (let i = 0; i < raw.length; i += 16) {
+        raw.set(witness.subarray(offset + 16, offset + 32), i);
         offset += 32;
       }
       return raw;
     };
 
     const rawInputs = decodeRawInputs(publicWitness);
-    assert.equal(publicWitness.length, 5132);
+    assert.equal(publicWitness.length, 332);
     assert.equal(
       Buffer.from(rawInputs.slice(32, 64)).toString("hex"),
       Buffer.from(derivedAddress).toString("hex")
@@ -0,0 +1,25 @@
+use solana_program::program_error::ProgramError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifierError {
+    InvalidInstruction,
+    InvalidVkAccount,
+    InvalidVkLength,
+    VkAlreadyFinalized,
+    VkNotFinalized,
+    VkHashMismatch,
+    ChunkOutOfBounds,
+    InvalidVerifyingKey,
+    UnsupportedPublicInputCount,
+    InvalidProofLength,
+    InvalidProof,
+    InvalidPublicWitness,
+    ProofVerificationFailed,
+    InvalidAuthority,
+}
+
+impl From<VerifierError> for ProgramError {
+    fn from(e: VerifierError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
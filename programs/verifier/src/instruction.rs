@@ -0,0 +1,184 @@
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use crate::error::VerifierError;
+use crate::state::find_vk_address;
+
+/// fits a write instruction and its accounts in one transaction
+pub const VK_CHUNK_SIZE: usize = 900;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifierInstruction<'a> {
+    /// creates the vk account for `vk_hash` with the payer as its authority. an
+    /// unfinalized account can be reset by its authority only
+    ///
+    /// accounts: [signer, writable] payer, [writable] vk account, [] system program
+    InitVk { vk_hash: [u8; 32], vk_len: u32 },
+    /// writes `data` at `offset` of an unfinalized vk
+    ///
+    /// accounts: [signer] authority, [writable] vk account
+    WriteVkChunk { offset: u32, data: &'a [u8] },
+    /// checks the contents against the vk hash and makes the account immutable
+    ///
+    /// accounts: [signer] authority, [writable] vk account
+    FinalizeVk,
+    /// verifies `proof || public_witness` against the vk account
+    ///
+    /// accounts: [] vk account
+    Verify { proof_and_witness: &'a [u8] },
+}
+
+impl<'a> VerifierInstruction<'a> {
+    pub fn unpack(data: &'a [u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = data
+            .split_first()
+            .ok_or(VerifierError::InvalidInstruction)?;
+        Ok(match tag {
+            0 if rest.len() == 36 => Self::InitVk {
+                vk_hash: rest[..32].try_into().unwrap(),
+                vk_len: u32::from_le_bytes(rest[32..].try_into().unwrap()),
+            },
+            1 if rest.len() >= 4 => Self::WriteVkChunk {
+                offset: u32::from_le_bytes(rest[..4].try_into().unwrap()),
+                data: &rest[4..],
+            },
+            2 if rest.is_empty() => Self::FinalizeVk,
+            3 => Self::Verify {
+                proof_and_witness: rest,
+            },
+            _ => return Err(VerifierError::InvalidInstruction.into()),
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        match self {
+            Self::InitVk { vk_hash, vk_len } => [&[0][..], vk_hash, &vk_len.to_le_bytes()].concat(),
+            Self::WriteVkChunk { offset, data } => [&[1][..], &offset.to_le_bytes(), data].concat(),
+            Self::FinalizeVk => vec![2],
+            Self::Verify { proof_and_witness } => [&[3][..], proof_and_witness].concat(),
+        }
+    }
+}
+
+pub fn init_vk(program_id: &Pubkey, payer: &Pubkey, vk_hash: [u8; 32], vk_len: u32) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(find_vk_address(program_id, &vk_hash).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: VerifierInstruction::InitVk { vk_hash, vk_len }.pack(),
+    }
+}
+
+pub fn write_vk_chunk(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    vk_hash: &[u8; 32],
+    offset: u32,
+    data: &[u8],
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(find_vk_address(program_id, vk_hash).0, false),
+        ],
+        data: VerifierInstruction::WriteVkChunk { offset, data }.pack(),
+    }
+}
+
+pub fn finalize_vk(program_id: &Pubkey, authority: &Pubkey, vk_hash: &[u8; 32]) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(find_vk_address(program_id, vk_hash).0, false),
+        ],
+        data: VerifierInstruction::FinalizeVk.pack(),
+    }
+}
+
+pub fn verify(
+    program_id: &Pubkey,
+    vk_hash: &[u8; 32],
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new_readonly(
+            find_vk_address(program_id, vk_hash).0,
+            false,
+        )],
+        data: VerifierInstruction::Verify {
+            proof_and_witness: &[proof, public_witness].concat(),
+        }
+        .pack(),
+    }
+}
+
+/// init, chunked writes and finalize for a gnark `.vk` file, all signed by `payer`; send
+/// them in order, one instruction per transaction
+pub fn upload_vk(program_id: &Pubkey, payer: &Pubkey, vk: &[u8]) -> Vec<Instruction> {
+    let vk_hash = hash(vk).to_bytes();
+    let mut instructions = vec![init_vk(program_id, payer, vk_hash, vk.len() as u32)];
+    for (i, chunk) in vk.chunks(VK_CHUNK_SIZE).enumerate() {
+        let offset = (i * VK_CHUNK_SIZE) as u32;
+        instructions.push(write_vk_chunk(program_id, payer, &vk_hash, offset, chunk));
+    }
+    instructions.push(finalize_vk(program_id, payer, &vk_hash));
+    instructions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_roundtrip() {
+        let data = [7u8; 40];
+        for ix in [
+            VerifierInstruction::InitVk {
+                vk_hash: [1u8; 32],
+                vk_len: 1616,
+            },
+            VerifierInstruction::WriteVkChunk {
+                offset: 900,
+                data: &data,
+            },
+            VerifierInstruction::FinalizeVk,
+            VerifierInstruction::Verify {
+                proof_and_witness: &data,
+            },
+        ] {
+            assert_eq!(VerifierInstruction::unpack(&ix.pack()).unwrap(), ix);
+        }
+        assert!(VerifierInstruction::unpack(&[]).is_err());
+        assert!(VerifierInstruction::unpack(&[0, 1, 2]).is_err());
+        assert!(VerifierInstruction::unpack(&[4]).is_err());
+    }
+
+    #[test]
+    fn test_upload_chunks_cover_key() {
+        let program_id = Pubkey::new_unique();
+        let vk = vec![9u8; 2 * VK_CHUNK_SIZE + 1];
+        let instructions = upload_vk(&program_id, &Pubkey::new_unique(), &vk);
+        assert_eq!(instructions.len(), 5);
+
+        let mut written = vec![0u8; vk.len()];
+        for ix in &instructions[1..4] {
+            let VerifierInstruction::WriteVkChunk { offset, data } =
+                VerifierInstruction::unpack(&ix.data).unwrap()
+            else {
+                panic!("expected a write");
+            };
+            written[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+        }
+        assert_eq!(written, vk);
+    }
+}
@@ -0,0 +1,18 @@
+//! multi-circuit groth16 verifier. gnark verifying keys are uploaded in chunks to accounts
+//! addressed by their sha256, and `Verify` checks `proof || public_witness` against the
+//! vk account it is given, so a new circuit needs an upload instead of a deployment.
+
+pub mod error;
+pub mod instruction;
+pub mod processor;
+pub mod state;
+
+pub use error::VerifierError;
+pub use instruction::{upload_vk, VerifierInstruction, VK_CHUNK_SIZE};
+pub use state::{find_vk_address, VkHeader, MAX_PUBLIC_INPUTS, MAX_VK_LEN, VK_SEED};
+
+#[cfg(not(feature = "no-entrypoint"))]
+use processor::process_instruction;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
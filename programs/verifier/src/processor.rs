@@ -0,0 +1,471 @@
+use gnark_verifier_solana::proof::GnarkProof;
+use gnark_verifier_solana::verifier::GnarkVerifier;
+use gnark_verifier_solana::vk::GnarkVerifyingkey;
+use gnark_verifier_solana::witness::GnarkWitness;
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::hash::hash;
+use solana_program::msg;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_program::sysvar::Sysvar;
+
+use crate::error::VerifierError;
+use crate::instruction::VerifierInstruction;
+use crate::state::{
+    find_vk_address, with_verifying_key, VkHeader, MAX_VK_LEN, VK_ACCOUNT_HEADER_LEN, VK_SEED,
+};
+
+/// ar (g1) | bs (g2) | krs (g1) | commitment count (u32) | pok (g1), plus 64 bytes per commitment
+pub const PROOF_BASE_LEN: usize = 64 + 128 + 64 + 4 + 64;
+pub const PUBLIC_WITNESS_HEADER_LEN: usize = 12;
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match VerifierInstruction::unpack(instruction_data)? {
+        VerifierInstruction::InitVk { vk_hash, vk_len } => {
+            process_init_vk(program_id, accounts, vk_hash, vk_len)
+        }
+        VerifierInstruction::WriteVkChunk { offset, data } => {
+            process_write_vk_chunk(program_id, accounts, offset, data)
+        }
+        VerifierInstruction::FinalizeVk => process_finalize_vk(program_id, accounts),
+        VerifierInstruction::Verify { proof_and_witness } => {
+            process_verify(program_id, accounts, proof_and_witness)
+        }
+    }
+}
+
+fn process_init_vk(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    vk_hash: [u8; 32],
+    vk_len: u32,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let vk_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if vk_len == 0 || vk_len as usize > MAX_VK_LEN {
+        return Err(VerifierError::InvalidVkLength.into());
+    }
+    let (address, bump) = find_vk_address(program_id, &vk_hash);
+    if address != *vk_account.key {
+        return Err(VerifierError::InvalidVkAccount.into());
+    }
+
+    let space = VK_ACCOUNT_HEADER_LEN + vk_len as usize;
+    if vk_account.owner == program_id {
+        // an unfinalized upload (e.g. with the wrong length) can be reset, but only by
+        // its authority, so nobody can wipe someone else's chunks between transactions
+        let header = VkHeader::read(&vk_account.try_borrow_data()?)?;
+        if header.finalized {
+            return Err(VerifierError::VkAlreadyFinalized.into());
+        }
+        require_authority(payer, &header)?;
+        vk_account.realloc(space, false)?;
+    } else {
+        let bump = [bump];
+        let seeds: &[&[u8]] = &[VK_SEED, &vk_hash, &bump];
+        let vk_accounts = [vk_account.clone(), system_program.clone()];
+        invoke_signed(
+            &system_instruction::allocate(vk_account.key, space as u64),
+            &vk_accounts,
+            &[seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(vk_account.key, program_id),
+            &vk_accounts,
+            &[seeds],
+        )?;
+    }
+
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(vk_account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, vk_account.key, shortfall),
+            &[payer.clone(), vk_account.clone(), system_program.clone()],
+        )?;
+    }
+
+    let mut data = vk_account.try_borrow_mut_data()?;
+    data.fill(0);
+    VkHeader {
+        vk_hash,
+        authority: *payer.key,
+        vk_len,
+        finalized: false,
+    }
+    .write(&mut data);
+    Ok(())
+}
+
+fn process_write_vk_chunk(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    offset: u32,
+    chunk: &[u8],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let vk_account = next_account_info(accounts_iter)?;
+    let mut data = vk_account_data_mut(program_id, vk_account)?;
+    let header = VkHeader::read(&data)?;
+    if header.finalized {
+        return Err(VerifierError::VkAlreadyFinalized.into());
+    }
+    require_authority(authority, &header)?;
+
+    let start = offset as usize;
+    let end = start
+        .checked_add(chunk.len())
+        .filter(|&end| end <= header.vk_len as usize)
+        .ok_or(VerifierError::ChunkOutOfBounds)?;
+    data[VK_ACCOUNT_HEADER_LEN + start..VK_ACCOUNT_HEADER_LEN + end].copy_from_slice(chunk);
+    Ok(())
+}
+
+fn process_finalize_vk(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let vk_account = next_account_info(accounts_iter)?;
+    let mut data = vk_account_data_mut(program_id, vk_account)?;
+    let mut header = VkHeader::read(&data)?;
+    if header.finalized {
+        return Err(VerifierError::VkAlreadyFinalized.into());
+    }
+    require_authority(authority, &header)?;
+
+    let vk = &data[VK_ACCOUNT_HEADER_LEN..];
+    if hash(vk).to_bytes() != header.vk_hash {
+        return Err(VerifierError::VkHashMismatch.into());
+    }
+    with_verifying_key(vk, |_| ())?;
+
+    header.finalized = true;
+    header.write(&mut data);
+    msg!("Verifying key finalized");
+    Ok(())
+}
+
+fn process_verify(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    proof_and_witness: &[u8],
+) -> ProgramResult {
+    let vk_account = next_account_info(&mut accounts.iter())?;
+    if vk_account.owner != program_id {
+        return Err(VerifierError::InvalidVkAccount.into());
+    }
+    let data = vk_account.try_borrow_data()?;
+    if !VkHeader::read(&data)?.finalized {
+        return Err(VerifierError::VkNotFinalized.into());
+    }
+
+    with_verifying_key(&data[VK_ACCOUNT_HEADER_LEN..], |vk| {
+        verify_proof(vk, proof_and_witness)
+    })??;
+    msg!("Proof verified successfully!");
+    Ok(())
+}
+
+fn require_authority(authority: &AccountInfo, header: &VkHeader) -> ProgramResult {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *authority.key != header.authority {
+        return Err(VerifierError::InvalidAuthority.into());
+    }
+    Ok(())
+}
+
+fn vk_account_data_mut<'a, 'info>(
+    program_id: &Pubkey,
+    vk_account: &'a AccountInfo<'info>,
+) -> Result<std::cell::RefMut<'a, &'info mut [u8]>, ProgramError> {
+    if vk_account.owner != program_id || !vk_account.is_writable {
+        return Err(VerifierError::InvalidVkAccount.into());
+    }
+    vk_account.try_borrow_mut_data()
+}
+
+/// the proof and witness lengths follow from the key, so any circuit with up to
+/// `MAX_PUBLIC_INPUTS` public inputs and any number of commitments is accepted
+pub fn verify_proof(vk: &GnarkVerifyingkey, proof_and_witness: &[u8]) -> Result<(), VerifierError> {
+    let proof_len = PROOF_BASE_LEN + vk.commitment_keys.len() * 64;
+    let witness_len = PUBLIC_WITNESS_HEADER_LEN + vk.nr_pubinputs * 32;
+    if proof_and_witness.len() != proof_len + witness_len {
+        return Err(VerifierError::InvalidProofLength);
+    }
+    let (proof, witness) = proof_and_witness.split_at(proof_len);
+    let proof = GnarkProof::from_bytes(proof).map_err(|_| VerifierError::InvalidProof)?;
+
+    // GnarkVerifier takes the input count as a const generic
+    macro_rules! dispatch {
+        ($($count:literal)*) => {
+            match vk.nr_pubinputs {
+                $($count => verify_with_inputs::<$count>(vk, proof, witness),)*
+                _ => Err(VerifierError::UnsupportedPublicInputCount),
+            }
+        };
+    }
+    dispatch!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16)
+}
+
+fn verify_with_inputs<const NR_INPUTS: usize>(
+    vk: &GnarkVerifyingkey,
+    proof: GnarkProof,
+    witness: &[u8],
+) -> Result<(), VerifierError> {
+    let witness = GnarkWitness::<NR_INPUTS>::from_bytes(witness)
+        .map_err(|_| VerifierError::InvalidPublicWitness)?;
+    GnarkVerifier::<NR_INPUTS>::new(vk)
+        .verify(proof, witness)
+        .map_err(|e| {
+            msg!("Gnark error: {:?}", e);
+            VerifierError::ProofVerificationFailed
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{init_vk, upload_vk, write_vk_chunk};
+
+    const FIXTURE_VK: &[u8] = include_bytes!("../../../zaffre-anchor/tests/fixtures/ownership.vk");
+    const PROOF: &[u8] = include_bytes!("../../../zaffre-anchor/tests/fixtures/ownership.proof");
+    const PUBLIC_WITNESS: &[u8] =
+        include_bytes!("../../../zaffre-anchor/tests/fixtures/ownership.pw");
+
+    fn empty_vk_data(vk: &[u8], authority: &Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; VK_ACCOUNT_HEADER_LEN + vk.len()];
+        VkHeader {
+            vk_hash: hash(vk).to_bytes(),
+            authority: *authority,
+            vk_len: vk.len() as u32,
+            finalized: false,
+        }
+        .write(&mut data);
+        data
+    }
+
+    fn custom(e: VerifierError) -> ProgramError {
+        e.into()
+    }
+
+    #[test]
+    fn test_upload_and_verify() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let (key, _) = find_vk_address(&program_id, &hash(FIXTURE_VK).to_bytes());
+        let (mut payer_lamports, mut payer_data) = (1u64, vec![]);
+        let payer_account = AccountInfo::new(
+            &payer,
+            true,
+            true,
+            &mut payer_lamports,
+            &mut payer_data,
+            &program_id,
+            false,
+            0,
+        );
+        let mut lamports = 1u64;
+        let mut data = empty_vk_data(FIXTURE_VK, &payer);
+        let vk_account = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            0,
+        );
+        let upload_accounts = [payer_account, vk_account.clone()];
+        let accounts = [vk_account];
+
+        let verify_data = VerifierInstruction::Verify {
+            proof_and_witness: &[PROOF, PUBLIC_WITNESS].concat(),
+        }
+        .pack();
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &verify_data),
+            Err(custom(VerifierError::VkNotFinalized))
+        );
+
+        let upload = upload_vk(&program_id, &payer, FIXTURE_VK);
+        for ix in &upload[1..] {
+            assert_eq!(ix.accounts[0].pubkey, payer);
+            assert_eq!(ix.accounts[1].pubkey, key);
+            process_instruction(&program_id, &upload_accounts, &ix.data).unwrap();
+        }
+        process_instruction(&program_id, &accounts, &verify_data).unwrap();
+
+        let mut bad_witness = PUBLIC_WITNESS.to_vec();
+        bad_witness[PUBLIC_WITNESS.len() - 1] ^= 1;
+        let bad_data = VerifierInstruction::Verify {
+            proof_and_witness: &[PROOF, &bad_witness].concat(),
+        }
+        .pack();
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &bad_data),
+            Err(custom(VerifierError::ProofVerificationFailed))
+        );
+
+        let short_data = VerifierInstruction::Verify {
+            proof_and_witness: PROOF,
+        }
+        .pack();
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &short_data),
+            Err(custom(VerifierError::InvalidProofLength))
+        );
+
+        // finalized keys are immutable
+        assert_eq!(
+            process_instruction(&program_id, &upload_accounts, &upload[1].data),
+            Err(custom(VerifierError::VkAlreadyFinalized))
+        );
+    }
+
+    #[test]
+    fn test_finalize_checks_contents() {
+        let program_id = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let (mut authority_lamports, mut authority_data) = (1u64, vec![]);
+        let authority_account = AccountInfo::new(
+            &authority,
+            true,
+            false,
+            &mut authority_lamports,
+            &mut authority_data,
+            &program_id,
+            false,
+            0,
+        );
+        let mut lamports = 1u64;
+        let mut data = empty_vk_data(FIXTURE_VK, &authority);
+        let vk_account = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            0,
+        );
+        let accounts = [authority_account, vk_account];
+
+        let finalize = VerifierInstruction::FinalizeVk.pack();
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &finalize),
+            Err(custom(VerifierError::VkHashMismatch))
+        );
+
+        let overflow = VerifierInstruction::WriteVkChunk {
+            offset: FIXTURE_VK.len() as u32 - 1,
+            data: &[0, 0],
+        }
+        .pack();
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &overflow),
+            Err(custom(VerifierError::ChunkOutOfBounds))
+        );
+
+        let other_program = Pubkey::new_unique();
+        assert_eq!(
+            process_instruction(&other_program, &accounts, &finalize),
+            Err(custom(VerifierError::InvalidVkAccount))
+        );
+    }
+
+    #[test]
+    fn test_only_authority_writes_and_resets() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let vk_hash = hash(FIXTURE_VK).to_bytes();
+        let (key, _) = find_vk_address(&program_id, &vk_hash);
+        let system_program = solana_program::system_program::ID;
+
+        let (mut lamports, mut data) = (1u64, empty_vk_data(FIXTURE_VK, &authority));
+        let vk_account = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            0,
+        );
+        let (mut signer_lamports, mut signer_data) = (1u64, vec![]);
+        let mut signer = AccountInfo::new(
+            &attacker,
+            true,
+            true,
+            &mut signer_lamports,
+            &mut signer_data,
+            &system_program,
+            false,
+            0,
+        );
+        let (mut system_lamports, mut system_data) = (1u64, vec![]);
+        let system_account = AccountInfo::new(
+            &system_program,
+            false,
+            false,
+            &mut system_lamports,
+            &mut system_data,
+            &system_program,
+            true,
+            0,
+        );
+
+        let write = write_vk_chunk(&program_id, &attacker, &vk_hash, 0, &[1, 2, 3]);
+        let finalize = VerifierInstruction::FinalizeVk.pack();
+        let reinit = init_vk(&program_id, &attacker, vk_hash, 1);
+        let accounts = [signer.clone(), vk_account.clone()];
+        for data in [&write.data, &finalize] {
+            assert_eq!(
+                process_instruction(&program_id, &accounts, data),
+                Err(custom(VerifierError::InvalidAuthority))
+            );
+        }
+        let init_accounts = [signer.clone(), vk_account.clone(), system_account];
+        assert_eq!(
+            process_instruction(&program_id, &init_accounts, &reinit.data),
+            Err(custom(VerifierError::InvalidAuthority))
+        );
+
+        // the authority's key without its signature is not enough either
+        signer.key = &authority;
+        signer.is_signer = false;
+        let accounts = [signer, vk_account];
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &write.data),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(
+            accounts[1].data_len(),
+            VK_ACCOUNT_HEADER_LEN + FIXTURE_VK.len()
+        );
+        assert!(accounts[1].data.borrow()[VK_ACCOUNT_HEADER_LEN..]
+            .iter()
+            .all(|b| *b == 0));
+    }
+}
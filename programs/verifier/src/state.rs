@@ -0,0 +1,220 @@
+//! vk account layout and zero-copy parsing of gnark verifying keys
+
+use gnark_verifier_solana::vk::GnarkVerifyingkey;
+use solana_program::pubkey::Pubkey;
+
+use crate::error::VerifierError;
+
+pub const VK_SEED: &[u8] = b"vk";
+pub const VK_ACCOUNT_DISCRIMINATOR: [u8; 8] = *b"zaffrevk";
+
+// layout: discriminator [8] | vk_hash [32] | authority [32] | vk_len (u32 le) |
+// finalized (u8) | vk [vk_len]
+pub const VK_HASH_OFFSET: usize = 8;
+pub const AUTHORITY_OFFSET: usize = VK_HASH_OFFSET + 32;
+pub const VK_LEN_OFFSET: usize = AUTHORITY_OFFSET + 32;
+pub const FINALIZED_OFFSET: usize = VK_LEN_OFFSET + 4;
+pub const VK_ACCOUNT_HEADER_LEN: usize = FINALIZED_OFFSET + 1;
+
+pub const MAX_VK_LEN: usize = 8192;
+pub const MAX_PUBLIC_INPUTS: usize = 16;
+
+const G1_LEN: usize = 64;
+const G2_LEN: usize = 128;
+const COMMITMENT_KEY_LEN: usize = 2 * G2_LEN;
+
+/// vk accounts are addressed by the sha256 of the gnark `.vk` file they hold
+pub fn find_vk_address(program_id: &Pubkey, vk_hash: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VK_SEED, vk_hash], program_id)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VkHeader {
+    pub vk_hash: [u8; 32],
+    /// the payer of the init, the only signer allowed to write, finalize or reset
+    pub authority: Pubkey,
+    pub vk_len: u32,
+    pub finalized: bool,
+}
+
+impl VkHeader {
+    pub fn read(data: &[u8]) -> Result<Self, VerifierError> {
+        if data.len() < VK_ACCOUNT_HEADER_LEN || data[..8] != VK_ACCOUNT_DISCRIMINATOR {
+            return Err(VerifierError::InvalidVkAccount);
+        }
+        let header = Self {
+            vk_hash: data[VK_HASH_OFFSET..AUTHORITY_OFFSET].try_into().unwrap(),
+            authority: Pubkey::new_from_array(
+                data[AUTHORITY_OFFSET..VK_LEN_OFFSET].try_into().unwrap(),
+            ),
+            vk_len: u32::from_le_bytes(data[VK_LEN_OFFSET..FINALIZED_OFFSET].try_into().unwrap()),
+            finalized: data[FINALIZED_OFFSET] != 0,
+        };
+        if data.len() != VK_ACCOUNT_HEADER_LEN + header.vk_len as usize {
+            return Err(VerifierError::InvalidVkAccount);
+        }
+        Ok(header)
+    }
+
+    pub fn write(&self, data: &mut [u8]) {
+        data[..8].copy_from_slice(&VK_ACCOUNT_DISCRIMINATOR);
+        data[VK_HASH_OFFSET..AUTHORITY_OFFSET].copy_from_slice(&self.vk_hash);
+        data[AUTHORITY_OFFSET..VK_LEN_OFFSET].copy_from_slice(self.authority.as_ref());
+        data[VK_LEN_OFFSET..FINALIZED_OFFSET].copy_from_slice(&self.vk_len.to_le_bytes());
+        data[FINALIZED_OFFSET] = self.finalized as u8;
+    }
+}
+
+/// parses a gnark `WriteRawTo` verifying key and passes it to `f`. the curve point slices
+/// borrow `vk` directly; only the committed input indices are decoded.
+pub fn with_verifying_key<R>(
+    vk: &[u8],
+    f: impl FnOnce(&GnarkVerifyingkey) -> R,
+) -> Result<R, VerifierError> {
+    let mut reader = Reader { data: vk };
+    let alpha_g1 = *reader.array::<G1_LEN>()?;
+    reader.take(G1_LEN)?; // beta_g1
+    let beta_g2 = *reader.array::<G2_LEN>()?;
+    let gamma_g2 = *reader.array::<G2_LEN>()?;
+    reader.take(G1_LEN)?; // delta_g1
+    let delta_g2 = *reader.array::<G2_LEN>()?;
+
+    let k_len = reader.u32()? as usize;
+    let k = reader.arrays::<G1_LEN>(k_len)?;
+
+    let committed_len = reader.u32()? as usize;
+    let mut committed = Vec::with_capacity(committed_len.min(k_len));
+    for _ in 0..committed_len {
+        let len = reader.u32()? as usize;
+        let indices = reader
+            .arrays::<8>(len)?
+            .iter()
+            .map(|index| u64::from_be_bytes(*index))
+            .collect::<Vec<_>>();
+        committed.push(indices);
+    }
+
+    let commitment_keys_len = reader.u32()? as usize;
+    let commitment_keys = reader.arrays::<COMMITMENT_KEY_LEN>(commitment_keys_len)?;
+
+    // the verifier indexes proof commitments and public inputs with these unchecked
+    let nr_pubinputs = k_len
+        .checked_sub(1 + commitment_keys_len)
+        .ok_or(VerifierError::InvalidVerifyingKey)?;
+    if nr_pubinputs > MAX_PUBLIC_INPUTS {
+        return Err(VerifierError::UnsupportedPublicInputCount);
+    }
+    if committed.len() != commitment_keys_len {
+        return Err(VerifierError::InvalidVerifyingKey);
+    }
+    for (i, indices) in committed.iter().enumerate() {
+        // commitment i may also commit to the wires of earlier commitments
+        let max_index = (nr_pubinputs + i) as u64;
+        if indices.iter().any(|&j| j == 0 || j > max_index) {
+            return Err(VerifierError::InvalidVerifyingKey);
+        }
+    }
+
+    let committed_refs: Vec<&[u64]> = committed.iter().map(Vec::as_slice).collect();
+    let vk = GnarkVerifyingkey {
+        nr_pubinputs,
+        alpha_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g2,
+        k,
+        commitment_keys,
+        public_and_commitment_committed: &committed_refs,
+    };
+    Ok(f(&vk))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VerifierError> {
+        if self.data.len() < len {
+            return Err(VerifierError::InvalidVerifyingKey);
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<&'a [u8; N], VerifierError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn arrays<const N: usize>(&mut self, count: usize) -> Result<&'a [[u8; N]], VerifierError> {
+        let len = count
+            .checked_mul(N)
+            .ok_or(VerifierError::InvalidVerifyingKey)?;
+        let bytes = self.take(len)?;
+        // SAFETY: [u8; N] has size N and alignment 1, and `bytes` is exactly count * N long
+        Ok(unsafe { std::slice::from_raw_parts(bytes.as_ptr().cast::<[u8; N]>(), count) })
+    }
+
+    fn u32(&mut self) -> Result<u32, VerifierError> {
+        Ok(u32::from_be_bytes(*self.array::<4>()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE_VK: &[u8] = include_bytes!("../../../zaffre-anchor/tests/fixtures/ownership.vk");
+
+    #[test]
+    fn test_parses_fixture_key() {
+        with_verifying_key(FIXTURE_VK, |vk| {
+            assert_eq!(vk.nr_pubinputs, 10);
+            assert_eq!(vk.k.len(), 12);
+            assert_eq!(vk.commitment_keys.len(), 1);
+            assert!(vk.public_and_commitment_committed[0].is_empty());
+            assert_eq!(vk.alpha_g1[..], FIXTURE_VK[..64]);
+            assert_eq!(vk.k[0][..], FIXTURE_VK[3 * 64 + 3 * 128 + 4..][..64]);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_rejects_malformed_keys() {
+        for len in [0, 100, FIXTURE_VK.len() - 1] {
+            assert_eq!(
+                with_verifying_key(&FIXTURE_VK[..len], |_| ()),
+                Err(VerifierError::InvalidVerifyingKey)
+            );
+        }
+
+        // point commitment 0 at a public input that does not exist
+        let committed_offset = 3 * 64 + 3 * 128 + 4 + 12 * 64;
+        let mut vk = FIXTURE_VK[..committed_offset + 4].to_vec();
+        vk.extend_from_slice(&1u32.to_be_bytes());
+        vk.extend_from_slice(&11u64.to_be_bytes());
+        vk.extend_from_slice(&FIXTURE_VK[committed_offset + 8..]);
+        assert_eq!(
+            with_verifying_key(&vk, |_| ()),
+            Err(VerifierError::InvalidVerifyingKey)
+        );
+    }
+
+    #[test]
+    fn test_header_roundtrip() {
+        let header = VkHeader {
+            vk_hash: [5u8; 32],
+            authority: Pubkey::new_unique(),
+            vk_len: 3,
+            finalized: true,
+        };
+        let mut data = vec![0u8; VK_ACCOUNT_HEADER_LEN + 3];
+        header.write(&mut data);
+        assert_eq!(VkHeader::read(&data), Ok(header));
+        assert_eq!(
+            VkHeader::read(&data[..VK_ACCOUNT_HEADER_LEN + 2]),
+            Err(VerifierError::InvalidVkAccount)
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! proc macros for zaffre-protected anchor instructions, re-exported by zaffre-anchor
+
+#[path = "../../zaffre-derive/src/discriminator.rs"]
+mod discriminator;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, parse_quote, Error, Field, Fields, Ident, ItemStruct, LitByteStr, Token,
+    Type,
+};
+
+use crate::discriminator::pad_discriminator;
+
+/// turns an anchor accounts struct into an ownership-proof action:
+///
+/// ```ignore
+/// #[zaffre_action(discriminator = b"setvalue", params(value: u64))]
+/// #[derive(Accounts)]
+/// pub struct SetValue<'info> {
+///     #[account(mut, seeds = [ZAFFRE_SEED_PREFIX, commitment.as_ref()], bump)]
+///     pub state: Account<'info, DemoState>,
+///     #[account(mut)]
+///     pub payer: Signer<'info>,
+///     pub system_program: Program<'info, System>,
+/// }
+/// ```
+///
+/// adds `#[instruction(commitment: [u8; 32], nullifier: [u8; 32])]`, so the instruction
+/// must take those two arguments first, and the `config`, `nullifier_state` and
+/// `verifier_program` accounts with their seeds and registry constraint. it also
+/// generates `ACTION_DISCRIMINATOR`, `action_hash` and `verify_and_spend`; the handler
+/// calls `ctx.accounts.verify_and_spend(ctx.program_id, &commitment, &nullifier, &proof,
+/// &value, nonce)?`.
+///
+/// params are borsh-encoded in order. instead of `discriminator` and `params`,
+/// `action = Type` takes both from a `zaffre_core::ZaffreAction` implementation shared
+/// with the client, and the generated functions take `action: &Type` in place of the
+/// params. other options: `config = Type` (default `Config`, must implement
+/// `zaffre_anchor::ZaffreConfig`), `payer = field` (default `payer`) and `params_digest`
+/// to hash the params with the params digest scheme. the attribute must sit above
+/// `#[derive(Accounts)]`.
+#[proc_macro_attribute]
+pub fn zaffre_action(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut options = ActionOptions::default();
+    let parser = syn::meta::parser(|meta| options.parse(meta));
+    parse_macro_input!(args with parser);
+    let item = parse_macro_input!(input as ItemStruct);
+    expand(options, item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ActionOptions {
+    discriminator: Option<[u8; 8]>,
+    params: Vec<Param>,
+    action: Option<Type>,
+    params_digest: bool,
+    config: Option<Type>,
+    payer: Option<Ident>,
+}
+
+enum Action {
+    Params([u8; 8]),
+    Typed(Box<Type>),
+}
+
+struct Param {
+    name: Ident,
+    ty: Type,
+}
+
+impl Parse for Param {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        Ok(Self {
+            name,
+            ty: input.parse()?,
+        })
+    }
+}
+
+impl ActionOptions {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("discriminator") {
+            let lit: LitByteStr = meta.value()?.parse()?;
+            self.discriminator = Some(pad_discriminator(&lit)?);
+        } else if meta.path.is_ident("params") {
+            let content;
+            syn::parenthesized!(content in meta.input);
+            self.params = Punctuated::<Param, Token![,]>::parse_terminated(&content)?
+                .into_iter()
+                .collect();
+        } else if meta.path.is_ident("action") {
+            self.action = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("params_digest") {
+            self.params_digest = true;
+        } else if meta.path.is_ident("config") {
+            self.config = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("payer") {
+            self.payer = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("unsupported zaffre_action option"));
+        }
+        Ok(())
+    }
+}
+
+fn expand(options: ActionOptions, mut item: ItemStruct) -> syn::Result<TokenStream2> {
+    let action = match (&options.action, options.discriminator) {
+        (Some(action), None) if options.params.is_empty() && !options.params_digest => {
+            Action::Typed(Box::new(action.clone()))
+        }
+        (None, Some(discriminator)) => Action::Params(discriminator),
+        (Some(_), _) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "`action` replaces `discriminator`, `params` and `params_digest`",
+            ))
+        }
+        (None, None) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "zaffre_action needs `discriminator = b\"...\"` or `action = Type`",
+            ))
+        }
+    };
+    let config = options.config.unwrap_or_else(|| parse_quote!(Config));
+    let payer = options.payer.unwrap_or_else(|| format_ident!("payer"));
+    let lifetime = item
+        .generics
+        .lifetimes()
+        .next()
+        .map(|param| param.lifetime.clone())
+        .ok_or_else(|| {
+            Error::new_spanned(&item.ident, "accounts struct needs an 'info lifetime")
+        })?;
+    if let Some(attr) = item
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("instruction"))
+    {
+        return Err(Error::new_spanned(
+            attr,
+            "zaffre_action adds #[instruction(commitment, nullifier)] itself",
+        ));
+    }
+
+    let Fields::Named(fields) = &mut item.fields else {
+        return Err(Error::new_spanned(
+            &item,
+            "zaffre_action needs named fields",
+        ));
+    };
+    let has_field = |name: &str| {
+        fields
+            .named
+            .iter()
+            .any(|field| field.ident.as_ref().is_some_and(|ident| ident == name))
+    };
+    for required in ["state", &payer.to_string(), "system_program"] {
+        if !has_field(required) {
+            return Err(Error::new_spanned(
+                &item.ident,
+                format!("zaffre_action needs a `{required}` account"),
+            ));
+        }
+    }
+    for generated in ["config", "nullifier_state", "verifier_program"] {
+        if has_field(generated) {
+            return Err(Error::new_spanned(
+                &item.ident,
+                format!("zaffre_action generates the `{generated}` account"),
+            ));
+        }
+    }
+
+    let named = |tokens: TokenStream2| Field::parse_named.parse2(tokens);
+    fields.named.insert(
+        0,
+        named(quote! {
+            #[account(seeds = [<#config as ::zaffre_anchor::ZaffreConfig>::SEED], bump)]
+            pub config: Account<#lifetime, #config>
+        })?,
+    );
+    fields.named.push(named(quote! {
+        /// CHECK: created by spend_nullifier, which fails if the nullifier was spent
+        #[account(
+            mut,
+            seeds = [::zaffre_anchor::NULLIFIER_SEED_PREFIX, nullifier.as_ref()],
+            bump
+        )]
+        pub nullifier_state: UncheckedAccount<#lifetime>
+    })?);
+    fields.named.push(named(quote! {
+        /// CHECK: must be the registry's active verifier, checked before the cpi
+        #[account(
+            constraint = ::zaffre_anchor::ZaffreConfig::registry(&*config)
+                .is_registered(verifier_program.key)
+                @ ::zaffre_anchor::ZaffreError::UnregisteredVerifier
+        )]
+        pub verifier_program: UncheckedAccount<#lifetime>
+    })?);
+    item.attrs
+        .push(parse_quote!(#[instruction(commitment: [u8; 32], nullifier: [u8; 32])]));
+
+    let ident = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let (discriminator, names, types, action_hash) = match action {
+        Action::Typed(action) => (
+            quote!(<#action as ::zaffre_anchor::ZaffreAction>::DISCRIMINATOR),
+            vec![format_ident!("action")],
+            vec![*action.clone()],
+            quote! {
+                Ok(
+                    ::zaffre_anchor::ZaffreAction::try_action_hash(action, program_id, state, nonce)
+                        .map_err(::zaffre_anchor::ZaffreError::from)?,
+                )
+            },
+        ),
+        Action::Params(discriminator) => {
+            let names: Vec<_> = options
+                .params
+                .iter()
+                .map(|param| param.name.clone())
+                .collect();
+            let types: Vec<_> = options
+                .params
+                .iter()
+                .map(|param| param.ty.clone())
+                .collect();
+            let (hash_fn, check) = if options.params_digest {
+                (quote!(compute_action_hash_with_params_digest), quote!())
+            } else {
+                (
+                    quote!(try_compute_action_hash),
+                    quote!(.map_err(::zaffre_anchor::ZaffreError::from)?),
+                )
+            };
+            let action_hash = quote! {
+                #[allow(unused_mut)]
+                let mut action_params = ::std::vec::Vec::new();
+                #(
+                    ::anchor_lang::AnchorSerialize::serialize(#names, &mut action_params)
+                        .map_err(|e| {
+                            ::anchor_lang::prelude::ProgramError::BorshIoError(e.to_string())
+                        })?;
+                )*
+                Ok(::zaffre_anchor::zaffre_core::#hash_fn(
+                    program_id,
+                    state,
+                    &Self::ACTION_DISCRIMINATOR,
+                    &action_params,
+                    nonce,
+                )#check)
+            };
+            (quote!([#(#discriminator),*]), names, types, action_hash)
+        }
+    };
+
+    Ok(quote! {
+        #item
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            pub const ACTION_DISCRIMINATOR: [u8; 8] = #discriminator;
+
+            /// action hash over the borsh-encoded params, as the client computes it
+            pub fn action_hash(
+                program_id: &::anchor_lang::prelude::Pubkey,
+                state: &::anchor_lang::prelude::Pubkey,
+                #(#names: &#types,)*
+                nonce: u64,
+            ) -> ::anchor_lang::Result<[u8; 32]> {
+                #action_hash
+            }
+
+            /// recomputes the action hash, verifies the ownership proof with the
+            /// registered verifier and spends the nullifier
+            #[allow(clippy::too_many_arguments)]
+            pub fn verify_and_spend(
+                &self,
+                program_id: &::anchor_lang::prelude::Pubkey,
+                commitment: &[u8; 32],
+                nullifier: &[u8; 32],
+                proof: &[u8],
+                #(#names: &#types,)*
+                nonce: u64,
+            ) -> ::anchor_lang::Result<()> {
+                let state = *::anchor_lang::ToAccountInfo::to_account_info(&self.state).key;
+                let action_hash = Self::action_hash(program_id, &state, #(#names,)* nonce)?;
+                let public_witness = ::zaffre_anchor::zaffre_core::serialize_public_witness(
+                    commitment,
+                    &state,
+                    program_id,
+                    &action_hash,
+                    nullifier,
+                );
+                ::zaffre_anchor::verify_registered_ownership_proof(
+                    ::zaffre_anchor::ZaffreConfig::registry(&*self.config),
+                    &self.verifier_program,
+                    proof,
+                    &public_witness,
+                )?;
+                ::zaffre_anchor::spend_nullifier(
+                    program_id,
+                    &self.nullifier_state,
+                    nullifier,
+                    &self.#payer,
+                    &self.system_program,
+                )?;
+                Ok(())
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_options(args: TokenStream2) -> syn::Result<ActionOptions> {
+        let mut options = ActionOptions::default();
+        syn::meta::parser(|meta| options.parse(meta)).parse2(args)?;
+        Ok(options)
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse_options(quote! {
+            discriminator = b"close", params(to: Pubkey, amount: u64), params_digest,
+            config = MyConfig, payer = relayer
+        })
+        .unwrap();
+        assert_eq!(options.discriminator, Some(*b"close\0\0\0"));
+        let names: Vec<_> = options.params.iter().map(|p| p.name.to_string()).collect();
+        assert_eq!(names, ["to", "amount"]);
+        assert!(options.params_digest);
+        assert_eq!(options.payer.unwrap(), "relayer");
+
+        assert!(parse_options(quote!(discriminator = b"toolongdisc")).is_err());
+        assert!(parse_options(quote!(seeds = b"zaffre")).is_err());
+
+        let options = parse_options(quote!(action = SetValueAction)).unwrap();
+        assert!(options.action.is_some());
+        assert!(options.discriminator.is_none());
+    }
+
+    #[test]
+    fn test_expand_rejects_incomplete_structs() {
+        let options = || parse_options(quote!(discriminator = b"setvalue")).unwrap();
+        let missing_payer: ItemStruct = parse_quote! {
+            pub struct Action<'info> {
+                pub state: Account<'info, State>,
+                pub system_program: Program<'info, System>,
+            }
+        };
+        let err = expand(options(), missing_payer).unwrap_err();
+        assert!(err.to_string().contains("`payer`"));
+
+        let duplicate_config: ItemStruct = parse_quote! {
+            pub struct Action<'info> {
+                pub config: Account<'info, Config>,
+                pub state: Account<'info, State>,
+                pub payer: Signer<'info>,
+                pub system_program: Program<'info, System>,
+            }
+        };
+        let err = expand(options(), duplicate_config).unwrap_err();
+        assert!(err.to_string().contains("`config`"));
+
+        let action: ItemStruct = parse_quote! {
+            pub struct Action<'info> {
+                pub state: Account<'info, State>,
+                pub payer: Signer<'info>,
+                pub system_program: Program<'info, System>,
+            }
+        };
+        let mixed = parse_options(quote!(action = SetValueAction, params(value: u64))).unwrap();
+        let err = expand(mixed, action).unwrap_err();
+        assert!(err.to_string().contains("`action` replaces"));
+    }
+}
@@ -0,0 +1,68 @@
+//! embeds gnark verifying keys for the `gnark-verifier` feature. each key is read from its
+//! env var (absolute path, or relative to this crate) or from the circuit's `target/` dir.
+//! the test fixture key is always embedded as `fixture_ownership_vk.rs` for the tests.
+
+fn main() {
+    #[cfg(feature = "gnark-verifier")]
+    gnark::generate_keys();
+}
+
+#[cfg(feature = "gnark-verifier")]
+mod gnark {
+    use std::env;
+    use std::path::{Path, PathBuf};
+
+    use gnark_verifier_solana::vk::generate_key_file;
+
+    const CIRCUITS: [(&str, &str, &str); 2] = [
+        (
+            "ownership",
+            "ZAFFRE_OWNERSHIP_VK",
+            "../circuits/ownership/target/ownership.vk",
+        ),
+        (
+            "membership",
+            "ZAFFRE_MEMBERSHIP_VK",
+            "../circuits/membership/target/membership.vk",
+        ),
+    ];
+
+    const FIXTURE_VK: &str = "tests/fixtures/ownership.vk";
+
+    pub fn generate_keys() {
+        let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR not set"));
+        println!("cargo:rerun-if-changed={FIXTURE_VK}");
+        embed(FIXTURE_VK, &out_dir.join("fixture_ownership_vk.rs"));
+
+        let mut embedded = 0;
+        for (name, var, default) in CIRCUITS {
+            println!("cargo:rerun-if-env-changed={var}");
+            let explicit = env::var(var).ok();
+            let vk_path = explicit.clone().unwrap_or_else(|| default.to_string());
+            // a missing path keeps the script dirty, so a key generated later is picked up
+            println!("cargo:rerun-if-changed={vk_path}");
+            if !Path::new(&vk_path).exists() {
+                if explicit.is_some() {
+                    panic!("{var} points to missing verifying key '{vk_path}'");
+                }
+                continue;
+            }
+
+            embed(&vk_path, &out_dir.join(format!("{name}_vk.rs")));
+            println!("cargo:rustc-cfg=zaffre_{name}_vk");
+            embedded += 1;
+        }
+        // not fatal, so --all-features builds; the verify functions are just absent
+        if embedded == 0 {
+            println!(
+                "cargo:warning=gnark-verifier has no circuit keys, set ZAFFRE_OWNERSHIP_VK or ZAFFRE_MEMBERSHIP_VK"
+            );
+        }
+    }
+
+    fn embed(vk_path: &str, target: &Path) {
+        if let Err(e) = generate_key_file(vk_path, target.to_str().expect("non-utf8 OUT_DIR")) {
+            panic!("failed to embed verifying key '{vk_path}': {e}");
+        }
+    }
+}
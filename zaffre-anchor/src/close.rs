@@ -0,0 +1,262 @@
+//! closing zaffre pda accounts and garbage-collecting old nullifier accounts
+
+use anchor_lang::prelude::*;
+use solana_program::system_program;
+use zaffre_core::{CloseAction, ZaffreAction};
+
+use crate::nullifier_set::{
+    contains_nullifier, spend_nullifier_in_shard, validate_nullifier_shard_pda,
+};
+use crate::validation::{
+    validate_nullifier_pda, validate_zaffre_pda, NullifierStateData, ZaffreError,
+    NULLIFIER_STATE_SIZE,
+};
+
+/// action discriminator for close proofs. the action params are the destination pubkey,
+/// so an ownership proof over `c` with this discriminator is the close proof.
+pub const CLOSE_DISCRIMINATOR: [u8; 8] = <CloseAction as ZaffreAction>::DISCRIMINATOR;
+
+/// closes `state` and sends all of its lamports to `destination`. the caller verifies
+/// the close proof first, with `destination.key` as the action params, and spends its
+/// nullifier so the proof cannot close a recreated account again.
+pub fn close_state_account(
+    program_id: &Pubkey,
+    state: &AccountInfo,
+    commitment: &[u8; 32],
+    destination: &AccountInfo,
+) -> Result<()> {
+    validate_zaffre_pda(program_id, commitment, state.key)?;
+    require_keys_eq!(*state.owner, *program_id, ZaffreError::InvalidStateOwner);
+    close_account(state, destination)
+}
+
+/// closes the nullifier account of `nullifier` once `window` slots have passed since it
+/// was spent, and records the nullifier in its shard (`nullifier_set`) instead, so it
+/// stays spent for 32 bytes of shard space rather than a whole account. the account's
+/// lamports go to `destination`, which must be `collector`.
+///
+/// no signer is required, so anyone can collect. `collector` is what keeps the rent from
+/// going to whoever calls first: take it from program state (a config account), never
+/// from instruction data or the accounts the caller passes.
+///
+/// programs that collect nullifiers must call `require_nullifier_not_collected` before
+/// every `spend_nullifier`, or a collected nullifier could be spent again.
+pub fn collect_nullifier(
+    program_id: &Pubkey,
+    nullifier_account: &AccountInfo,
+    nullifier: &[u8; 32],
+    shard: &AccountInfo,
+    destination: &AccountInfo,
+    collector: &Pubkey,
+    window: u64,
+) -> Result<()> {
+    require_keys_eq!(
+        *destination.key,
+        *collector,
+        ZaffreError::InvalidRentCollector
+    );
+    validate_nullifier_pda(program_id, nullifier, nullifier_account.key)?;
+    require!(
+        *nullifier_account.owner == *program_id
+            && nullifier_account.data_len() == NULLIFIER_STATE_SIZE,
+        ZaffreError::InvalidNullifierAccount
+    );
+    let mut bytes = [0u8; NULLIFIER_STATE_SIZE];
+    bytes.copy_from_slice(&nullifier_account.try_borrow_data()?);
+    let state = NullifierStateData::from_bytes(&bytes);
+    require!(
+        state.spent_at_slot.saturating_add(window) <= Clock::get()?.slot,
+        ZaffreError::NullifierWindowOpen
+    );
+
+    spend_nullifier_in_shard(program_id, shard, nullifier)?;
+    close_account(nullifier_account, destination)
+}
+
+/// fails with `NullifierAlreadySpent` if `nullifier` was collected into `shard`. a shard
+/// that was never created holds nothing.
+pub fn require_nullifier_not_collected(
+    program_id: &Pubkey,
+    shard: &AccountInfo,
+    nullifier: &[u8; 32],
+) -> Result<()> {
+    validate_nullifier_shard_pda(program_id, nullifier, shard.key)?;
+    if *shard.owner == system_program::ID && shard.data_is_empty() {
+        return Ok(());
+    }
+    require_keys_eq!(
+        *shard.owner,
+        *program_id,
+        ZaffreError::InvalidNullifierShard
+    );
+    require!(
+        !contains_nullifier(&shard.try_borrow_data()?, nullifier)?,
+        ZaffreError::NullifierAlreadySpent
+    );
+    Ok(())
+}
+
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let lamports = destination
+        .lamports()
+        .checked_add(account.lamports())
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **destination.try_borrow_mut_lamports()? = lamports;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&system_program::ID);
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nullifier_set::{
+        find_nullifier_shard_address, init_nullifier_shard, insert_nullifier, nullifier_shard_size,
+    };
+    use crate::validation::{NULLIFIER_SEED_PREFIX, ZAFFRE_SEED_PREFIX};
+
+    #[test]
+    fn test_close_rejects_foreign_or_mismatched_state() {
+        let program_id = Pubkey::new_unique();
+        let commitment = [1u8; 32];
+        let state_key =
+            Pubkey::find_program_address(&[ZAFFRE_SEED_PREFIX, &commitment], &program_id).0;
+        let destination_key = Pubkey::new_unique();
+        let foreign = Pubkey::new_unique();
+
+        let (mut state_lamports, mut destination_lamports) = (10u64, 0u64);
+        let (mut state_data, mut destination_data) = (vec![7u8; 16], vec![]);
+        let state = AccountInfo::new(
+            &state_key,
+            false,
+            true,
+            &mut state_lamports,
+            &mut state_data,
+            &foreign,
+            false,
+            0,
+        );
+        let destination = AccountInfo::new(
+            &destination_key,
+            false,
+            true,
+            &mut destination_lamports,
+            &mut destination_data,
+            &system_program::ID,
+            false,
+            0,
+        );
+
+        let err = close_state_account(&program_id, &state, &commitment, &destination).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidStateOwner.into());
+        let err = close_state_account(&program_id, &state, &[2u8; 32], &destination).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidPDA.into());
+        assert_eq!(state.lamports(), 10);
+        assert_eq!(destination.lamports(), 0);
+    }
+
+    #[test]
+    fn test_collected_nullifier_stays_spent() {
+        let program_id = Pubkey::new_unique();
+        let nullifier = [6u8; 32];
+        let (shard_key, _) = find_nullifier_shard_address(&program_id, 6);
+
+        let (mut lamports, mut data) = (0u64, vec![]);
+        let missing = AccountInfo::new(
+            &shard_key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &system_program::ID,
+            false,
+            0,
+        );
+        require_nullifier_not_collected(&program_id, &missing, &nullifier).unwrap();
+
+        let mut data = vec![0u8; nullifier_shard_size(2)];
+        init_nullifier_shard(&mut data, 6).unwrap();
+        insert_nullifier(&mut data, &nullifier).unwrap();
+        let mut lamports = 1u64;
+        let shard = AccountInfo::new(
+            &shard_key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            0,
+        );
+        let err = require_nullifier_not_collected(&program_id, &shard, &nullifier).unwrap_err();
+        assert_eq!(err, ZaffreError::NullifierAlreadySpent.into());
+        let mut other = nullifier;
+        other[1] = 0;
+        require_nullifier_not_collected(&program_id, &shard, &other).unwrap();
+    }
+
+    #[test]
+    fn test_collect_rejects_unspent_account() {
+        let program_id = Pubkey::new_unique();
+        let nullifier = [8u8; 32];
+        let nullifier_key =
+            Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &nullifier], &program_id).0;
+        let (shard_key, _) = find_nullifier_shard_address(&program_id, 8);
+        let destination_key = Pubkey::new_unique();
+
+        let (mut nullifier_lamports, mut shard_lamports, mut destination_lamports) =
+            (10u64, 1u64, 0u64);
+        let (mut nullifier_data, mut shard_data, mut destination_data) =
+            (vec![], vec![0u8; nullifier_shard_size(1)], vec![]);
+        let unspent = AccountInfo::new(
+            &nullifier_key,
+            false,
+            true,
+            &mut nullifier_lamports,
+            &mut nullifier_data,
+            &system_program::ID,
+            false,
+            0,
+        );
+        let shard = AccountInfo::new(
+            &shard_key,
+            false,
+            true,
+            &mut shard_lamports,
+            &mut shard_data,
+            &program_id,
+            false,
+            0,
+        );
+        let destination = AccountInfo::new(
+            &destination_key,
+            false,
+            true,
+            &mut destination_lamports,
+            &mut destination_data,
+            &system_program::ID,
+            false,
+            0,
+        );
+
+        let collect = |nullifier: &[u8; 32], collector: &Pubkey| {
+            collect_nullifier(
+                &program_id,
+                &unspent,
+                nullifier,
+                &shard,
+                &destination,
+                collector,
+                0,
+            )
+        };
+        let err = collect(&nullifier, &Pubkey::new_unique()).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidRentCollector.into());
+        let err = collect(&nullifier, &destination_key).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidNullifierAccount.into());
+        let err = collect(&[9u8; 32], &destination_key).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidPDA.into());
+        assert_eq!(destination.lamports(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+//! in-program groth16 verification with the vendored gnark verifier, against verifying keys
+//! embedded by build.rs. replaces the sunspot verifier cpi, so no verifier program is deployed.
+
+use anchor_lang::prelude::*;
+use gnark_verifier_solana::proof::GnarkProof;
+use gnark_verifier_solana::verifier::GnarkVerifier;
+use gnark_verifier_solana::vk::GnarkVerifyingkey;
+use gnark_verifier_solana::witness::GnarkWitness;
+
+use crate::sunspot::{SunspotError, GROTH16_PROOF_SIZE};
+#[cfg(zaffre_membership_vk)]
+use crate::sunspot::{MEMBERSHIP_PUBLIC_INPUT_COUNT, MEMBERSHIP_PUBLIC_WITNESS_SIZE};
+use crate::sunspot::{PUBLIC_INPUT_COUNT, PUBLIC_WITNESS_SIZE};
+
+#[cfg(zaffre_ownership_vk)]
+pub mod ownership_vk {
+    include!(concat!(env!("OUT_DIR"), "/ownership_vk.rs"));
+}
+
+#[cfg(zaffre_membership_vk)]
+pub mod membership_vk {
+    include!(concat!(env!("OUT_DIR"), "/membership_vk.rs"));
+}
+
+// a key from the wrong circuit fails the build instead of every proof
+#[cfg(zaffre_ownership_vk)]
+const _: () = assert!(
+    ownership_vk::VK.nr_pubinputs == PUBLIC_INPUT_COUNT
+        && ownership_vk::VK.commitment_keys.len() == 1,
+    "ZAFFRE_OWNERSHIP_VK is not an ownership circuit key"
+);
+
+#[cfg(zaffre_membership_vk)]
+const _: () = assert!(
+    membership_vk::VK.nr_pubinputs == MEMBERSHIP_PUBLIC_INPUT_COUNT
+        && membership_vk::VK.commitment_keys.len() == 1,
+    "ZAFFRE_MEMBERSHIP_VK is not a membership circuit key"
+);
+
+#[cfg(zaffre_ownership_vk)]
+pub fn verify_ownership_proof_native(proof: &[u8], public_witness: &[u8]) -> Result<()> {
+    verify_ownership_proof_with_key(&ownership_vk::VK, proof, public_witness)
+}
+
+/// like `verify_ownership_proof_native`, against a key that was not embedded by build.rs
+pub fn verify_ownership_proof_with_key(
+    vk: &GnarkVerifyingkey,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    require!(
+        vk.nr_pubinputs == PUBLIC_INPUT_COUNT && vk.commitment_keys.len() == 1,
+        SunspotError::ProofVerificationFailed
+    );
+    verify::<PUBLIC_INPUT_COUNT>(vk, proof, public_witness, PUBLIC_WITNESS_SIZE)?;
+    msg!("Zaffre ownership proof verified");
+    Ok(())
+}
+
+/// the caller must also check the root in the public witness against the commitment tree
+#[cfg(zaffre_membership_vk)]
+pub fn verify_membership_proof_native(proof: &[u8], public_witness: &[u8]) -> Result<()> {
+    verify::<MEMBERSHIP_PUBLIC_INPUT_COUNT>(
+        &membership_vk::VK,
+        proof,
+        public_witness,
+        MEMBERSHIP_PUBLIC_WITNESS_SIZE,
+    )?;
+    msg!("Zaffre membership proof verified");
+    Ok(())
+}
+
+fn verify<const NR_INPUTS: usize>(
+    vk: &GnarkVerifyingkey,
+    proof: &[u8],
+    public_witness: &[u8],
+    public_witness_size: usize,
+) -> Result<()> {
+    require!(
+        proof.len() == GROTH16_PROOF_SIZE,
+        SunspotError::InvalidProofLength
+    );
+    require!(
+        public_witness.len() == public_witness_size,
+        SunspotError::InvalidPublicWitnessLength
+    );
+
+    let proof = GnarkProof::from_bytes(proof).map_err(|e| {
+        msg!("Gnark error: {:?}", e);
+        SunspotError::InvalidProofEncoding
+    })?;
+    let public_witness = GnarkWitness::<NR_INPUTS>::from_bytes(public_witness)
+        .map_err(|_| SunspotError::InvalidPublicWitnessLength)?;
+
+    GnarkVerifier::<NR_INPUTS>::new(vk)
+        .verify(proof, public_witness)
+        .map_err(|e| {
+            msg!("Groth16 verification failed: {:?}", e);
+            SunspotError::ProofVerificationFailed
+        })?;
+    Ok(())
+}
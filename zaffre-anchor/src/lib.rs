@@ -1,13 +1,54 @@
 //! anchor helpers for zaffre
 
+pub mod close;
+#[cfg(feature = "gnark-verifier")]
+pub mod gnark_verifier;
+pub mod merkle;
+pub mod nullifier;
+pub mod nullifier_set;
+pub mod registry;
+pub mod relayer;
+pub mod rotation;
 pub mod sunspot;
 pub mod validation;
 
+pub use close::{
+    close_state_account, collect_nullifier, require_nullifier_not_collected, CLOSE_DISCRIMINATOR,
+};
+#[cfg(all(feature = "gnark-verifier", zaffre_membership_vk))]
+pub use gnark_verifier::verify_membership_proof_native;
+#[cfg(all(feature = "gnark-verifier", zaffre_ownership_vk))]
+pub use gnark_verifier::verify_ownership_proof_native;
+#[cfg(feature = "gnark-verifier")]
+pub use gnark_verifier::verify_ownership_proof_with_key;
+pub use merkle::{
+    append_commitment, current_root, init_commitment_tree, insert_commitment, is_known_root,
+    require_known_root, COMMITMENT_TREE_DEPTH, COMMITMENT_TREE_ROOT_HISTORY, COMMITMENT_TREE_SIZE,
+};
+pub use nullifier::spend_nullifier;
+pub use nullifier_set::{
+    contains_nullifier, find_nullifier_shard_address, grow_nullifier_shard, init_nullifier_shard,
+    insert_nullifier, nullifier_shard_index, nullifier_shard_size, spend_nullifier_in_shard,
+    validate_nullifier_shard_pda, NULLIFIER_SHARD_COUNT, NULLIFIER_SHARD_SEED_PREFIX,
+};
+pub use registry::{
+    compute_vk_hash, require_immutable_program, verify_registered_membership_proof,
+    verify_registered_membership_proof_with_vk_account, verify_registered_ownership_proof,
+    verify_registered_ownership_proof_with_vk_account, PendingVerifier, VerifierEntry,
+    VerifierRegistry, ZaffreConfig, CIRCUIT_NAME_MAX_LEN, VERIFIER_REGISTRY_SEED,
+};
+pub use relayer::pay_relayer;
+pub use rotation::{rotate_state_account, ROTATE_DISCRIMINATOR};
 pub use sunspot::{
-    try_verify_ownership_proof, verify_ownership_proof, SunspotError, GROTH16_PROOF_SIZE,
-    PUBLIC_WITNESS_SIZE,
+    try_verify_ownership_proof, verify_membership_proof, verify_membership_proof_with_vk_account,
+    verify_ownership_proof, verify_ownership_proof_with_vk_account, SunspotError,
+    GROTH16_PROOF_SIZE, MEMBERSHIP_PUBLIC_WITNESS_SIZE, PUBLIC_WITNESS_SIZE,
 };
 pub use validation::{
     validate_nullifier_pda, validate_zaffre_pda, NullifierStateData, ZaffreError,
     NULLIFIER_SEED_PREFIX, NULLIFIER_STATE_SIZE, ZAFFRE_SEED_PREFIX,
 };
+pub use zaffre_anchor_macros::zaffre_action;
+// generated `#[zaffre_action]` code reaches the action hash helpers through here
+pub use zaffre_core;
+pub use zaffre_core::{CloseAction, RotateAction, ZaffreAction, ZaffreCoreError};
@@ -0,0 +1,323 @@
+//! incremental poseidon merkle tree of commitments for membership proofs
+
+use anchor_lang::prelude::*;
+use solana_program::poseidon::{hashv, Endianness, Parameters};
+
+use crate::validation::ZaffreError;
+
+pub const COMMITMENT_TREE_DEPTH: usize = 20;
+pub const COMMITMENT_TREE_ROOT_HISTORY: usize = 32;
+
+// layout: next_index (u32 le) | current_root_index (u32 le)
+//         | filled_subtrees [32; DEPTH] | roots [32; ROOT_HISTORY]
+const NEXT_INDEX_OFFSET: usize = 0;
+const ROOT_INDEX_OFFSET: usize = 4;
+const FILLED_SUBTREES_OFFSET: usize = 8;
+const ROOTS_OFFSET: usize = FILLED_SUBTREES_OFFSET + COMMITMENT_TREE_DEPTH * 32;
+pub const COMMITMENT_TREE_SIZE: usize = ROOTS_OFFSET + COMMITMENT_TREE_ROOT_HISTORY * 32;
+
+/// roots of empty subtrees by height; `ZERO_HASHES[i + 1] = poseidon(ZERO_HASHES[i], ZERO_HASHES[i])`
+/// over little-endian field bytes, starting from the zero leaf
+pub const ZERO_HASHES: [[u8; 32]; COMMITMENT_TREE_DEPTH + 1] = [
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ],
+    [
+        0x64, 0x48, 0xb6, 0x46, 0x84, 0xee, 0x39, 0xa8, 0x23, 0xd5, 0xfe, 0x5f, 0xd5, 0x24, 0x31,
+        0xdc, 0x81, 0xe4, 0x81, 0x7b, 0xf2, 0xc3, 0xea, 0x3c, 0xab, 0x9e, 0x23, 0x9e, 0xfb, 0xf5,
+        0x98, 0x20,
+    ],
+    [
+        0xe1, 0xf1, 0xb1, 0x60, 0x44, 0x77, 0xa4, 0x67, 0xf0, 0x8d, 0xc6, 0x9d, 0xcb, 0x44, 0x1a,
+        0x26, 0xec, 0xa7, 0x84, 0xf5, 0x6f, 0x1a, 0x30, 0xdf, 0x63, 0x22, 0xb1, 0xcd, 0x3d, 0x67,
+        0x69, 0x10,
+    ],
+    [
+        0x38, 0xd2, 0x56, 0xb8, 0xb2, 0x7e, 0xd5, 0x28, 0xd5, 0x1d, 0x37, 0x50, 0xea, 0x6e, 0x7c,
+        0x46, 0x06, 0x21, 0xf7, 0x50, 0x8d, 0x75, 0x3d, 0x2e, 0xaf, 0xe2, 0x7e, 0x53, 0x31, 0x33,
+        0xf4, 0x18,
+    ],
+    [
+        0x2a, 0x95, 0xbc, 0x9d, 0x55, 0x97, 0xac, 0xca, 0x65, 0x82, 0x56, 0x1a, 0x57, 0x28, 0xb7,
+        0xf1, 0x45, 0x23, 0xa5, 0x3b, 0xe9, 0xff, 0x20, 0x63, 0xd3, 0xb0, 0x17, 0xcb, 0x37, 0xd8,
+        0xf9, 0x07,
+    ],
+    [
+        0x55, 0x3f, 0x18, 0x39, 0x16, 0xec, 0x5c, 0x7b, 0x4d, 0xad, 0xb2, 0x94, 0x8c, 0xc5, 0x99,
+        0xa6, 0x07, 0x29, 0xf3, 0x5d, 0x4c, 0x1f, 0x63, 0xc9, 0xf5, 0xb3, 0x46, 0x87, 0x5e, 0xcf,
+        0x94, 0x2b,
+    ],
+    [
+        0x78, 0x9d, 0xa0, 0x2e, 0xa3, 0xdd, 0x11, 0x1d, 0x61, 0x53, 0xb9, 0x51, 0x69, 0x1e, 0xd7,
+        0xfe, 0xbc, 0xe1, 0xa9, 0xcc, 0x22, 0x7d, 0xea, 0x46, 0x96, 0x45, 0x66, 0xa6, 0xc5, 0x93,
+        0xee, 0x2d,
+    ],
+    [
+        0x9d, 0x34, 0x87, 0x3c, 0xbe, 0xaa, 0xa4, 0xa8, 0x7f, 0xac, 0xb5, 0x8c, 0xa8, 0x15, 0x05,
+        0x8b, 0x7b, 0x59, 0x39, 0xb6, 0x1e, 0x60, 0xcf, 0x82, 0xe9, 0x84, 0x2b, 0xa2, 0xe5, 0x95,
+        0x82, 0x07,
+    ],
+    [
+        0x61, 0xcc, 0xf3, 0x99, 0x3a, 0xbe, 0x4c, 0x44, 0x1a, 0x21, 0x41, 0x4a, 0x27, 0x2e, 0x6b,
+        0x61, 0x2a, 0x47, 0x64, 0x45, 0x86, 0xec, 0x1b, 0x50, 0xa6, 0x27, 0x60, 0x8f, 0xf1, 0xe5,
+        0xa5, 0x2f,
+    ],
+    [
+        0x47, 0xd7, 0xfc, 0x14, 0xa6, 0x56, 0x21, 0x3e, 0xab, 0x28, 0xe2, 0xe3, 0xcc, 0x7a, 0x5e,
+        0xe4, 0x66, 0x1f, 0x94, 0x9e, 0x38, 0x80, 0xb7, 0xec, 0x21, 0xfd, 0xd8, 0xd0, 0x76, 0x43,
+        0x88, 0x0e,
+    ],
+    [
+        0xf2, 0x0a, 0x19, 0xda, 0xe5, 0x75, 0x61, 0xde, 0x33, 0x35, 0x71, 0x57, 0xf9, 0x92, 0x58,
+        0xf9, 0x69, 0xb4, 0x2e, 0xa5, 0xd1, 0x7a, 0x71, 0x28, 0x1e, 0x4f, 0x49, 0x72, 0xda, 0x01,
+        0x72, 0x1b,
+    ],
+    [
+        0x36, 0x76, 0x7d, 0xce, 0xfa, 0x6b, 0xbc, 0xbe, 0xb5, 0x08, 0x08, 0x65, 0xe4, 0xe1, 0xe6,
+        0xa6, 0x19, 0x98, 0x24, 0x01, 0xb2, 0xc0, 0x00, 0x52, 0x38, 0x36, 0x5e, 0x72, 0x22, 0x88,
+        0x8d, 0x1f,
+    ],
+    [
+        0x5a, 0xf8, 0xb5, 0x71, 0x04, 0x9a, 0x87, 0xd0, 0xa8, 0x88, 0xcf, 0x2a, 0xa1, 0xb0, 0x62,
+        0x61, 0xfb, 0xfc, 0x8c, 0xba, 0x89, 0x15, 0x70, 0xb9, 0xaf, 0x4b, 0x91, 0x6c, 0xf6, 0x82,
+        0x5d, 0x2c,
+    ],
+    [
+        0xd0, 0xbf, 0xbf, 0xe0, 0x70, 0xf2, 0x58, 0x64, 0x64, 0xf4, 0x13, 0xa1, 0xaa, 0xc4, 0xf5,
+        0x4e, 0x13, 0xa1, 0x3f, 0xdf, 0x5a, 0x7f, 0x95, 0x20, 0xb8, 0x0b, 0x94, 0xa0, 0x48, 0x41,
+        0xc5, 0x14,
+    ],
+    [
+        0x0c, 0xe8, 0xeb, 0xf4, 0x4b, 0x8e, 0x11, 0x16, 0xd4, 0x89, 0xad, 0x8c, 0x58, 0x25, 0xbe,
+        0x11, 0xaf, 0xb9, 0xd8, 0x44, 0xee, 0xc0, 0x10, 0x1e, 0x96, 0x6f, 0x98, 0x2f, 0xb1, 0x33,
+        0x0d, 0x19,
+    ],
+    [
+        0x92, 0x6c, 0xe0, 0x25, 0x93, 0x64, 0xb3, 0xa5, 0x0a, 0x51, 0xaf, 0x96, 0x65, 0xae, 0x67,
+        0x11, 0xed, 0x73, 0xad, 0x14, 0x49, 0x35, 0x17, 0xac, 0x52, 0x41, 0x70, 0xce, 0xa9, 0x8a,
+        0xf9, 0x22,
+    ],
+    [
+        0x23, 0x73, 0xba, 0x8b, 0xd3, 0x53, 0xb7, 0xf8, 0xee, 0xcc, 0x6e, 0xc6, 0x29, 0x6f, 0x52,
+        0x5a, 0x57, 0x6a, 0xbf, 0x72, 0x8d, 0x22, 0x6f, 0x9f, 0x0b, 0x88, 0xe5, 0x6c, 0x9b, 0x7c,
+        0x7c, 0x2a,
+    ],
+    [
+        0x92, 0xb9, 0x36, 0x3f, 0x64, 0xdd, 0x75, 0x4d, 0x95, 0x8b, 0x98, 0xc2, 0xc9, 0x43, 0x00,
+        0x47, 0xfc, 0x3f, 0x46, 0x4d, 0xc1, 0xf9, 0x7a, 0xc6, 0xc1, 0x8e, 0x69, 0x58, 0xe5, 0x86,
+        0x81, 0x2e,
+    ],
+    [
+        0x0f, 0xf1, 0x1f, 0x1c, 0x9d, 0x24, 0x46, 0x35, 0x27, 0x92, 0x73, 0x64, 0xad, 0x6e, 0xef,
+        0x8a, 0x94, 0xae, 0x0d, 0x05, 0xcf, 0xc8, 0xe2, 0x49, 0xab, 0x4e, 0x9a, 0x1e, 0x57, 0xc5,
+        0x57, 0x0f,
+    ],
+    [
+        0xca, 0x2c, 0xf7, 0x34, 0x61, 0xe3, 0x9c, 0x3c, 0xe4, 0x46, 0x7d, 0x69, 0x10, 0xe3, 0x78,
+        0xfe, 0x1c, 0x0e, 0x80, 0x88, 0x43, 0x3d, 0xf6, 0xd5, 0x4a, 0x55, 0xfb, 0xb5, 0x67, 0xee,
+        0x30, 0x18,
+    ],
+    [
+        0x3e, 0x1f, 0x19, 0x22, 0xdf, 0xb6, 0x71, 0xd3, 0xf9, 0x12, 0xf7, 0xea, 0x46, 0x1e, 0x0a,
+        0x88, 0xee, 0x84, 0x8f, 0xdd, 0xe1, 0x2b, 0x6c, 0x18, 0xab, 0x1a, 0xd2, 0xc5, 0x6a, 0xe7,
+        0x34, 0x21,
+    ],
+];
+
+/// poseidon(left, right) over little-endian field bytes, matching the membership circuit
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32]> {
+    let node = hashv(
+        Parameters::Bn254X5,
+        Endianness::LittleEndian,
+        &[left, right],
+    )
+    .map_err(|_| error!(ZaffreError::InvalidMerkleLeaf))?;
+    Ok(node.to_bytes())
+}
+
+/// writes an empty tree into `data`; the account must be at least `COMMITMENT_TREE_SIZE` bytes
+pub fn init_commitment_tree(data: &mut [u8]) -> Result<()> {
+    require!(
+        data.len() >= COMMITMENT_TREE_SIZE,
+        ZaffreError::InvalidCommitmentTree
+    );
+    data[..COMMITMENT_TREE_SIZE].fill(0);
+    for (level, zero) in ZERO_HASHES.iter().take(COMMITMENT_TREE_DEPTH).enumerate() {
+        write_node(data, FILLED_SUBTREES_OFFSET, level, zero);
+    }
+    write_node(data, ROOTS_OFFSET, 0, &ZERO_HASHES[COMMITMENT_TREE_DEPTH]);
+    Ok(())
+}
+
+/// appends `leaf` (a commitment, little-endian field bytes) and returns its index.
+/// clients rebuild the tree from the leaves in insertion order to compute paths.
+pub fn insert_commitment(data: &mut [u8], leaf: &[u8; 32]) -> Result<u32> {
+    require!(
+        data.len() >= COMMITMENT_TREE_SIZE,
+        ZaffreError::InvalidCommitmentTree
+    );
+    let index = read_u32(data, NEXT_INDEX_OFFSET);
+    require!(
+        (index as u64) < (1u64 << COMMITMENT_TREE_DEPTH),
+        ZaffreError::MerkleTreeFull
+    );
+
+    let mut node = *leaf;
+    let mut position = index;
+    for (level, zero) in ZERO_HASHES.iter().take(COMMITMENT_TREE_DEPTH).enumerate() {
+        node = if position & 1 == 0 {
+            write_node(data, FILLED_SUBTREES_OFFSET, level, &node);
+            hash_pair(&node, zero)?
+        } else {
+            hash_pair(&read_node(data, FILLED_SUBTREES_OFFSET, level), &node)?
+        };
+        position >>= 1;
+    }
+
+    let root_index =
+        (read_u32(data, ROOT_INDEX_OFFSET) as usize + 1) % COMMITMENT_TREE_ROOT_HISTORY;
+    write_node(data, ROOTS_OFFSET, root_index, &node);
+    write_u32(data, ROOT_INDEX_OFFSET, root_index as u32);
+    write_u32(data, NEXT_INDEX_OFFSET, index + 1);
+    Ok(index)
+}
+
+pub fn append_commitment(tree: &AccountInfo, leaf: &[u8; 32]) -> Result<u32> {
+    let mut data = tree.try_borrow_mut_data()?;
+    insert_commitment(&mut data, leaf)
+}
+
+pub fn current_root(data: &[u8]) -> Result<[u8; 32]> {
+    require!(
+        data.len() >= COMMITMENT_TREE_SIZE,
+        ZaffreError::InvalidCommitmentTree
+    );
+    let root_index = read_u32(data, ROOT_INDEX_OFFSET) as usize % COMMITMENT_TREE_ROOT_HISTORY;
+    Ok(read_node(data, ROOTS_OFFSET, root_index))
+}
+
+pub fn commitment_count(data: &[u8]) -> Result<u32> {
+    require!(
+        data.len() >= COMMITMENT_TREE_SIZE,
+        ZaffreError::InvalidCommitmentTree
+    );
+    Ok(read_u32(data, NEXT_INDEX_OFFSET))
+}
+
+/// true if `root` is one of the last `COMMITMENT_TREE_ROOT_HISTORY` roots, so proofs
+/// built against a slightly stale tree still verify
+pub fn is_known_root(data: &[u8], root: &[u8; 32]) -> bool {
+    if data.len() < COMMITMENT_TREE_SIZE || root == &[0u8; 32] {
+        return false;
+    }
+    (0..COMMITMENT_TREE_ROOT_HISTORY).any(|i| &read_node(data, ROOTS_OFFSET, i) == root)
+}
+
+pub fn require_known_root(tree: &AccountInfo, root: &[u8; 32]) -> Result<()> {
+    let data = tree.try_borrow_data()?;
+    require!(is_known_root(&data, root), ZaffreError::UnknownMerkleRoot);
+    Ok(())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn read_node(data: &[u8], base: usize, index: usize) -> [u8; 32] {
+    let offset = base + index * 32;
+    let mut node = [0u8; 32];
+    node.copy_from_slice(&data[offset..offset + 32]);
+    node
+}
+
+fn write_node(data: &mut [u8], base: usize, index: usize, node: &[u8; 32]) {
+    let offset = base + index * 32;
+    data[offset..offset + 32].copy_from_slice(node);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_leaf(value: u8) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[0] = value;
+        bytes
+    }
+
+    fn empty_tree() -> Vec<u8> {
+        let mut data = vec![0u8; COMMITMENT_TREE_SIZE];
+        init_commitment_tree(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_zero_hashes() {
+        for i in 0..COMMITMENT_TREE_DEPTH {
+            assert_eq!(
+                hash_pair(&ZERO_HASHES[i], &ZERO_HASHES[i]).unwrap(),
+                ZERO_HASHES[i + 1]
+            );
+        }
+    }
+
+    // same vectors as zaffre_prover::merkle
+    #[test]
+    fn test_roots_match_prover_vectors() {
+        let mut data = empty_tree();
+        assert_eq!(
+            current_root(&data).unwrap(),
+            ZERO_HASHES[COMMITMENT_TREE_DEPTH]
+        );
+
+        for value in 1..=3 {
+            insert_commitment(&mut data, &small_leaf(value)).unwrap();
+        }
+        let expected: [u8; 32] = [
+            0xd0, 0x99, 0xd9, 0xdd, 0x89, 0xd1, 0xdd, 0x9b, 0x31, 0xd8, 0x05, 0x62, 0x42, 0xae,
+            0x2e, 0x12, 0x18, 0xbd, 0x80, 0x4d, 0x14, 0x9d, 0xc9, 0x49, 0xb7, 0xe1, 0x47, 0xce,
+            0x6e, 0x31, 0x83, 0x24,
+        ];
+        assert_eq!(current_root(&data).unwrap(), expected);
+        assert_eq!(commitment_count(&data).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_root_history() {
+        let mut data = empty_tree();
+        insert_commitment(&mut data, &small_leaf(1)).unwrap();
+        let first = current_root(&data).unwrap();
+
+        for value in 2..(COMMITMENT_TREE_ROOT_HISTORY as u8 + 1) {
+            insert_commitment(&mut data, &small_leaf(value)).unwrap();
+        }
+        assert!(is_known_root(&data, &first));
+
+        insert_commitment(&mut data, &small_leaf(100)).unwrap();
+        assert!(!is_known_root(&data, &first));
+        assert!(is_known_root(&data, &current_root(&data).unwrap()));
+        assert!(!is_known_root(&data, &[0u8; 32]));
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        let mut data = empty_tree();
+        assert!(insert_commitment(&mut data, &[0xff; 32]).is_err());
+        assert!(insert_commitment(&mut data[..COMMITMENT_TREE_SIZE - 1], &small_leaf(1)).is_err());
+    }
+
+    #[test]
+    fn test_tree_full() {
+        let mut data = empty_tree();
+        write_u32(&mut data, NEXT_INDEX_OFFSET, 1 << COMMITMENT_TREE_DEPTH);
+        assert!(insert_commitment(&mut data, &small_leaf(1)).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! atomic nullifier spending
+
+use anchor_lang::prelude::*;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::{system_instruction, system_program};
+
+use crate::validation::{
+    validate_nullifier_pda, NullifierStateData, ZaffreError, NULLIFIER_SEED_PREFIX,
+    NULLIFIER_STATE_SIZE,
+};
+
+/// creates the nullifier pda inside the action instruction and records the current slot
+/// in it as `NullifierStateData`. an existing nullifier account means the nullifier was
+/// spent, so the instruction fails with `NullifierAlreadySpent` and no separate prepare
+/// step (or race between it and the action) is needed. returns the pda bump.
+///
+/// lamports already sent to the address are kept; `payer` (a signer) covers the rest of
+/// the rent.
+pub fn spend_nullifier<'info>(
+    program_id: &Pubkey,
+    nullifier_account: &AccountInfo<'info>,
+    nullifier: &[u8; 32],
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<u8> {
+    let bump = validate_nullifier_pda(program_id, nullifier, nullifier_account.key)?;
+    require!(
+        *nullifier_account.owner == system_program::ID && nullifier_account.data_is_empty(),
+        ZaffreError::NullifierAlreadySpent
+    );
+
+    let rent = Rent::get()?.minimum_balance(NULLIFIER_STATE_SIZE);
+    let shortfall = rent.saturating_sub(nullifier_account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, nullifier_account.key, shortfall),
+            &[
+                payer.clone(),
+                nullifier_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    // allocate + assign instead of create_account, which fails on prefunded addresses
+    let bump_seed = [bump];
+    let seeds: &[&[u8]] = &[NULLIFIER_SEED_PREFIX, nullifier, &bump_seed];
+    let accounts = [nullifier_account.clone(), system_program.clone()];
+    invoke_signed(
+        &system_instruction::allocate(nullifier_account.key, NULLIFIER_STATE_SIZE as u64),
+        &accounts,
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(nullifier_account.key, program_id),
+        &accounts,
+        &[seeds],
+    )?;
+
+    let state = NullifierStateData::new(Clock::get()?.slot);
+    nullifier_account
+        .try_borrow_mut_data()?
+        .copy_from_slice(&state.to_bytes());
+    Ok(bump)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_spent_or_mismatched_nullifier() {
+        let program_id = Pubkey::new_unique();
+        let nullifier = [4u8; 32];
+        let nullifier_key =
+            Pubkey::find_program_address(&[NULLIFIER_SEED_PREFIX, &nullifier], &program_id).0;
+        let payer_key = Pubkey::new_unique();
+        let system_key = system_program::ID;
+        let native_loader = Pubkey::default();
+
+        let (mut spent_lamports, mut payer_lamports, mut system_lamports) = (10u64, 10u64, 1u64);
+        let (mut spent_data, mut payer_data, mut system_data) = (
+            NullifierStateData::new(5).to_bytes().to_vec(),
+            vec![],
+            vec![],
+        );
+        let spent = AccountInfo::new(
+            &nullifier_key,
+            false,
+            true,
+            &mut spent_lamports,
+            &mut spent_data,
+            &program_id,
+            false,
+            0,
+        );
+        let payer = AccountInfo::new(
+            &payer_key,
+            true,
+            true,
+            &mut payer_lamports,
+            &mut payer_data,
+            &system_key,
+            false,
+            0,
+        );
+        let system = AccountInfo::new(
+            &system_key,
+            false,
+            false,
+            &mut system_lamports,
+            &mut system_data,
+            &native_loader,
+            true,
+            0,
+        );
+
+        let err = spend_nullifier(&program_id, &spent, &nullifier, &payer, &system).unwrap_err();
+        assert_eq!(err, ZaffreError::NullifierAlreadySpent.into());
+
+        let err = spend_nullifier(&program_id, &spent, &[5u8; 32], &payer, &system).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidPDA.into());
+        assert_eq!(payer.lamports(), 10);
+    }
+}
@@ -0,0 +1,271 @@
+//! sharded nullifier set: many spent nullifiers per account instead of one account each
+
+use std::cmp::Ordering;
+
+use anchor_lang::prelude::*;
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use solana_program::program::invoke;
+use solana_program::system_instruction;
+pub use zaffre_core::pda::{
+    find_nullifier_shard_address, nullifier_shard_index, NULLIFIER_SHARD_SEED_PREFIX,
+};
+use zaffre_core::Nullifier;
+
+use crate::validation::ZaffreError;
+
+pub const NULLIFIER_SHARD_COUNT: usize = 256;
+
+// layout: shard (u8) | len (u32 le) | nullifiers [32; capacity], the first `len` sorted
+// ascending by bytes
+const SHARD_OFFSET: usize = 0;
+const LEN_OFFSET: usize = 1;
+const NULLIFIERS_OFFSET: usize = 5;
+pub const NULLIFIER_SHARD_HEADER_SIZE: usize = NULLIFIERS_OFFSET;
+
+/// most nullifiers a shard can grow by in one instruction (realloc limit)
+pub const NULLIFIER_SHARD_MAX_GROWTH: usize = MAX_PERMITTED_DATA_INCREASE / 32;
+
+/// account size for a shard holding `capacity` nullifiers
+pub const fn nullifier_shard_size(capacity: usize) -> usize {
+    NULLIFIER_SHARD_HEADER_SIZE + capacity * 32
+}
+
+/// checks that `provided_pda` is the shard `nullifier` belongs to
+pub fn validate_nullifier_shard_pda(
+    program_id: &Pubkey,
+    nullifier: &[u8; 32],
+    provided_pda: &Pubkey,
+) -> Result<u8> {
+    let shard = nullifier_shard_index(&Nullifier::from_bytes(*nullifier));
+    let (derived, bump) = find_nullifier_shard_address(program_id, shard);
+    require_keys_eq!(derived, *provided_pda, ZaffreError::InvalidPDA);
+    Ok(bump)
+}
+
+/// writes an empty shard into `data`; the capacity is whatever fits after the header
+pub fn init_nullifier_shard(data: &mut [u8], shard: u8) -> Result<()> {
+    require!(
+        data.len() >= NULLIFIER_SHARD_HEADER_SIZE,
+        ZaffreError::InvalidNullifierShard
+    );
+    data.fill(0);
+    data[SHARD_OFFSET] = shard;
+    Ok(())
+}
+
+pub fn nullifier_shard_len(data: &[u8]) -> Result<u32> {
+    require!(
+        data.len() >= NULLIFIER_SHARD_HEADER_SIZE,
+        ZaffreError::InvalidNullifierShard
+    );
+    let len = read_u32(data, LEN_OFFSET);
+    require!(
+        len as usize <= nullifier_shard_capacity(data),
+        ZaffreError::InvalidNullifierShard
+    );
+    Ok(len)
+}
+
+pub fn nullifier_shard_capacity(data: &[u8]) -> usize {
+    data.len().saturating_sub(NULLIFIER_SHARD_HEADER_SIZE) / 32
+}
+
+pub fn contains_nullifier(data: &[u8], nullifier: &[u8; 32]) -> Result<bool> {
+    Ok(search(data, nullifier)?.is_ok())
+}
+
+/// inserts `nullifier`, keeping the shard sorted, and returns the new length. fails with
+/// `NullifierAlreadySpent` if it is present and `NullifierShardFull` if there is no room.
+pub fn insert_nullifier(data: &mut [u8], nullifier: &[u8; 32]) -> Result<u32> {
+    let len = nullifier_shard_len(data)? as usize;
+    let position = match search(data, nullifier)? {
+        Ok(_) => return err!(ZaffreError::NullifierAlreadySpent),
+        Err(position) => position,
+    };
+    require!(
+        len < nullifier_shard_capacity(data),
+        ZaffreError::NullifierShardFull
+    );
+
+    let start = NULLIFIERS_OFFSET + position * 32;
+    let end = NULLIFIERS_OFFSET + len * 32;
+    data.copy_within(start..end, start + 32);
+    data[start..start + 32].copy_from_slice(nullifier);
+    write_u32(data, LEN_OFFSET, len as u32 + 1);
+    Ok(len as u32 + 1)
+}
+
+/// records `nullifier` as spent in its shard account, failing if it already was. the
+/// shard must be owned by the program and created with `init_nullifier_shard`.
+pub fn spend_nullifier_in_shard(
+    program_id: &Pubkey,
+    shard: &AccountInfo,
+    nullifier: &[u8; 32],
+) -> Result<()> {
+    validate_nullifier_shard_pda(program_id, nullifier, shard.key)?;
+    require_keys_eq!(
+        *shard.owner,
+        *program_id,
+        ZaffreError::InvalidNullifierShard
+    );
+    let mut data = shard.try_borrow_mut_data()?;
+    require!(
+        data.first() == Some(&nullifier_shard_index(&Nullifier::from_bytes(*nullifier))),
+        ZaffreError::InvalidNullifierShard
+    );
+    insert_nullifier(&mut data, nullifier)?;
+    Ok(())
+}
+
+/// reallocs `shard` to hold `additional` more nullifiers (at most
+/// `NULLIFIER_SHARD_MAX_GROWTH` per instruction), with `payer` topping up the rent
+pub fn grow_nullifier_shard<'info>(
+    shard: &AccountInfo<'info>,
+    additional: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    require!(
+        additional <= NULLIFIER_SHARD_MAX_GROWTH,
+        ZaffreError::InvalidNullifierShard
+    );
+    let new_len = shard.data_len() + additional * 32;
+    let rent = Rent::get()?.minimum_balance(new_len);
+    let shortfall = rent.saturating_sub(shard.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, shard.key, shortfall),
+            &[payer.clone(), shard.clone(), system_program.clone()],
+        )?;
+    }
+    shard.realloc(new_len, true)?;
+    Ok(())
+}
+
+/// binary search over the sorted entries without collecting them; shards can hold more
+/// entries than the on-chain heap has room for
+fn search(data: &[u8], nullifier: &[u8; 32]) -> Result<std::result::Result<usize, usize>> {
+    let (mut low, mut high) = (0, nullifier_shard_len(data)? as usize);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let offset = NULLIFIERS_OFFSET + mid * 32;
+        match data[offset..offset + 32].cmp(&nullifier[..]) {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Ok(Ok(mid)),
+        }
+    }
+    Ok(Err(low))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nullifier(shard: u8, tag: u8) -> [u8; 32] {
+        let mut bytes = [tag; 32];
+        bytes[0] = shard;
+        bytes
+    }
+
+    fn empty_shard(shard: u8, capacity: usize) -> Vec<u8> {
+        let mut data = vec![0u8; nullifier_shard_size(capacity)];
+        init_nullifier_shard(&mut data, shard).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_insert_keeps_shard_sorted() {
+        let mut data = empty_shard(7, 4);
+        for tag in [5, 1, 9, 3] {
+            insert_nullifier(&mut data, &nullifier(7, tag)).unwrap();
+        }
+        assert_eq!(nullifier_shard_len(&data).unwrap(), 4);
+        let stored: Vec<u8> = data[NULLIFIERS_OFFSET..]
+            .chunks_exact(32)
+            .map(|entry| entry[1])
+            .collect();
+        assert_eq!(stored, vec![1, 3, 5, 9]);
+        assert!(contains_nullifier(&data, &nullifier(7, 3)).unwrap());
+        assert!(!contains_nullifier(&data, &nullifier(7, 4)).unwrap());
+    }
+
+    #[test]
+    fn test_double_spend_rejected() {
+        let mut data = empty_shard(2, 8);
+        insert_nullifier(&mut data, &nullifier(2, 4)).unwrap();
+        insert_nullifier(&mut data, &nullifier(2, 6)).unwrap();
+
+        let err = insert_nullifier(&mut data, &nullifier(2, 4)).unwrap_err();
+        assert_eq!(err, ZaffreError::NullifierAlreadySpent.into());
+        assert_eq!(nullifier_shard_len(&data).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_full_shard_rejects_new_but_not_spent() {
+        let mut data = empty_shard(0, 2);
+        insert_nullifier(&mut data, &nullifier(0, 1)).unwrap();
+        insert_nullifier(&mut data, &nullifier(0, 2)).unwrap();
+
+        let err = insert_nullifier(&mut data, &nullifier(0, 3)).unwrap_err();
+        assert_eq!(err, ZaffreError::NullifierShardFull.into());
+        let err = insert_nullifier(&mut data, &nullifier(0, 2)).unwrap_err();
+        assert_eq!(err, ZaffreError::NullifierAlreadySpent.into());
+
+        // growing the account (as realloc would) adds capacity without touching entries
+        data.extend_from_slice(&[0u8; 32]);
+        insert_nullifier(&mut data, &nullifier(0, 3)).unwrap();
+        assert!(contains_nullifier(&data, &nullifier(0, 1)).unwrap());
+    }
+
+    #[test]
+    fn test_spend_in_shard_checks_account() {
+        let program_id = Pubkey::new_unique();
+        let spent = nullifier(3, 1);
+        let (shard_key, _) = find_nullifier_shard_address(&program_id, 3);
+        let (other_key, _) = find_nullifier_shard_address(&program_id, 4);
+
+        let (mut lamports, mut data) = (1u64, empty_shard(3, 4));
+        let shard = AccountInfo::new(
+            &shard_key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            0,
+        );
+        spend_nullifier_in_shard(&program_id, &shard, &spent).unwrap();
+        let err = spend_nullifier_in_shard(&program_id, &shard, &spent).unwrap_err();
+        assert_eq!(err, ZaffreError::NullifierAlreadySpent.into());
+
+        let err = spend_nullifier_in_shard(&program_id, &shard, &nullifier(4, 1)).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidPDA.into());
+
+        let (mut lamports, mut data) = (1u64, empty_shard(4, 4));
+        let foreign = Pubkey::new_unique();
+        let not_owned = AccountInfo::new(
+            &other_key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &foreign,
+            false,
+            0,
+        );
+        let err = spend_nullifier_in_shard(&program_id, &not_owned, &nullifier(4, 1)).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidNullifierShard.into());
+    }
+}
@@ -0,0 +1,585 @@
+//! verifier registry: pins the verifier program a program accepts proofs from, with
+//! timelocked upgrades
+
+use anchor_lang::prelude::*;
+use solana_program::hash::hash;
+use solana_program::{bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable};
+
+use crate::sunspot::{
+    verify_membership_proof, verify_membership_proof_with_vk_account, verify_ownership_proof,
+    verify_ownership_proof_with_vk_account,
+};
+use crate::validation::ZaffreError;
+
+pub const VERIFIER_REGISTRY_SEED: &[u8] = b"zaffre_verifier";
+pub const CIRCUIT_NAME_MAX_LEN: usize = 32;
+
+// vk account header of the multi-circuit verifier (programs/verifier):
+// discriminator [8] | vk_hash [32] | authority [32] | vk_len (u32 le) | finalized (u8)
+const VK_ACCOUNT_DISCRIMINATOR: &[u8; 8] = b"zaffrevk";
+const VK_ACCOUNT_HEADER_LEN: usize = 8 + 32 + 32 + 4 + 1;
+
+// bincode `UpgradeableLoaderState::ProgramData { slot, upgrade_authority_address }`:
+// variant (u32 le) | slot (u64 le) | option tag (u8) | authority [32]
+const PROGRAM_DATA_VARIANT: u32 = 3;
+const PROGRAM_DATA_AUTHORITY_TAG_OFFSET: usize = 4 + 8;
+/// owner of builtin programs; solana-program does not export it
+const NATIVE_LOADER_ID: Pubkey =
+    solana_program::pubkey!("NativeLoader1111111111111111111111111111111");
+/// loaders whose programs can never be upgraded
+const IMMUTABLE_LOADERS: [Pubkey; 3] =
+    [bpf_loader::ID, bpf_loader_deprecated::ID, NATIVE_LOADER_ID];
+
+/// sha256 of the gnark verifying key (`<circuit>/target/<name>.vk`) the verifier embeds
+pub fn compute_vk_hash(vk: &[u8]) -> [u8; 32] {
+    hash(vk).to_bytes()
+}
+
+fn program_data_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+/// fails unless nobody can change `program`'s code: a builtin, a non-upgradeable bpf
+/// program, or an upgradeable one whose `program_data` has no upgrade authority. an
+/// upgrade authority could otherwise swap a verifier's embedded key at once, around the
+/// registry timelock. dropping the authority cannot be undone, so checking when an entry
+/// is registered covers every later cpi.
+pub fn require_immutable_program(
+    program: &AccountInfo,
+    program_data: Option<&AccountInfo>,
+) -> Result<()> {
+    require!(program.executable, ZaffreError::MutableVerifier);
+    if IMMUTABLE_LOADERS.contains(program.owner) {
+        return Ok(());
+    }
+    require_keys_eq!(
+        *program.owner,
+        bpf_loader_upgradeable::id(),
+        ZaffreError::MutableVerifier
+    );
+    let program_data = program_data.ok_or(ZaffreError::MutableVerifier)?;
+    require_keys_eq!(
+        *program_data.key,
+        program_data_address(program.key),
+        ZaffreError::MutableVerifier
+    );
+    require_keys_eq!(
+        *program_data.owner,
+        bpf_loader_upgradeable::id(),
+        ZaffreError::MutableVerifier
+    );
+    let data = program_data.try_borrow_data()?;
+    require!(
+        data.len() > PROGRAM_DATA_AUTHORITY_TAG_OFFSET
+            && data[..4] == PROGRAM_DATA_VARIANT.to_le_bytes()
+            && data[PROGRAM_DATA_AUTHORITY_TAG_OFFSET] == 0,
+        ZaffreError::MutableVerifier
+    );
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifierEntry {
+    pub program_id: Pubkey,
+    /// ascii circuit name, zero padded
+    pub circuit_name: [u8; CIRCUIT_NAME_MAX_LEN],
+    pub circuit_version: u32,
+    pub vk_hash: [u8; 32],
+}
+
+impl VerifierEntry {
+    pub const SIZE: usize = 32 + CIRCUIT_NAME_MAX_LEN + 4 + 32;
+
+    pub fn new(
+        program_id: Pubkey,
+        circuit_name: &str,
+        circuit_version: u32,
+        vk_hash: [u8; 32],
+    ) -> Result<Self> {
+        let name = circuit_name.as_bytes();
+        require!(
+            !name.is_empty() && name.len() <= CIRCUIT_NAME_MAX_LEN && !name.contains(&0),
+            ZaffreError::InvalidCircuitName
+        );
+        let mut padded = [0u8; CIRCUIT_NAME_MAX_LEN];
+        padded[..name.len()].copy_from_slice(name);
+        Ok(Self {
+            program_id,
+            circuit_name: padded,
+            circuit_version,
+            vk_hash,
+        })
+    }
+
+    pub fn circuit_name(&self) -> &str {
+        let len = self
+            .circuit_name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(CIRCUIT_NAME_MAX_LEN);
+        std::str::from_utf8(&self.circuit_name[..len]).unwrap_or_default()
+    }
+
+    fn require_program(
+        &self,
+        verifier_program: &AccountInfo,
+        verifier_program_data: Option<&AccountInfo>,
+    ) -> Result<()> {
+        require_keys_eq!(
+            *verifier_program.key,
+            self.program_id,
+            ZaffreError::UnregisteredVerifier
+        );
+        require_immutable_program(verifier_program, verifier_program_data)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PendingVerifier {
+    pub entry: VerifierEntry,
+    pub activates_at_slot: u64,
+}
+
+/// embed in a program account (the example keeps it in its config pda). only `active` is
+/// accepted; a proposed entry replaces it once `timelock_slots` have passed, so users see
+/// a verifier change coming and can exit before it applies. every entry must name an
+/// immutable program (`require_immutable_program`), so the code behind an entry cannot
+/// change without going through the timelock.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifierRegistry {
+    pub authority: Pubkey,
+    pub active: VerifierEntry,
+    pub pending: Option<PendingVerifier>,
+    pub timelock_slots: u64,
+}
+
+/// a program's config account holding its `VerifierRegistry` at the pda `[SEED]`;
+/// `#[zaffre_action]` uses it for the config and verifier constraints
+pub trait ZaffreConfig {
+    const SEED: &'static [u8];
+
+    fn registry(&self) -> &VerifierRegistry;
+}
+
+impl VerifierRegistry {
+    pub const SIZE: usize = 32 + VerifierEntry::SIZE + 1 + VerifierEntry::SIZE + 8 + 8;
+
+    /// `verifier_program` is `active`'s program and `verifier_program_data` its programdata
+    /// account, needed only for upgradeable programs
+    pub fn new(
+        authority: Pubkey,
+        active: VerifierEntry,
+        verifier_program: &AccountInfo,
+        verifier_program_data: Option<&AccountInfo>,
+        timelock_slots: u64,
+    ) -> Result<Self> {
+        active.require_program(verifier_program, verifier_program_data)?;
+        Ok(Self {
+            authority,
+            active,
+            pending: None,
+            timelock_slots,
+        })
+    }
+
+    /// schedules `entry` to become active after the timelock; replaces any earlier
+    /// proposal and restarts the timer. returns the activation slot.
+    pub fn propose_upgrade(
+        &mut self,
+        entry: VerifierEntry,
+        verifier_program: &AccountInfo,
+        verifier_program_data: Option<&AccountInfo>,
+        current_slot: u64,
+    ) -> Result<u64> {
+        entry.require_program(verifier_program, verifier_program_data)?;
+        let activates_at_slot = current_slot
+            .checked_add(self.timelock_slots)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.pending = Some(PendingVerifier {
+            entry,
+            activates_at_slot,
+        });
+        Ok(activates_at_slot)
+    }
+
+    pub fn apply_upgrade(&mut self, current_slot: u64) -> Result<VerifierEntry> {
+        let pending = self.pending.ok_or(ZaffreError::NoPendingVerifierUpgrade)?;
+        require!(
+            current_slot >= pending.activates_at_slot,
+            ZaffreError::VerifierUpgradeTimelocked
+        );
+        self.active = pending.entry;
+        self.pending = None;
+        Ok(self.active)
+    }
+
+    pub fn cancel_upgrade(&mut self) -> Result<()> {
+        require!(
+            self.pending.take().is_some(),
+            ZaffreError::NoPendingVerifierUpgrade
+        );
+        Ok(())
+    }
+
+    /// for `constraint = ...` checks on the verifier account
+    pub fn is_registered(&self, verifier_program: &Pubkey) -> bool {
+        self.active.program_id == *verifier_program
+    }
+
+    pub fn require_registered(&self, verifier_program: &AccountInfo) -> Result<()> {
+        require_keys_eq!(
+            *verifier_program.key,
+            self.active.program_id,
+            ZaffreError::UnregisteredVerifier
+        );
+        require!(
+            verifier_program.executable,
+            ZaffreError::UnregisteredVerifier
+        );
+        Ok(())
+    }
+
+    /// for the multi-circuit verifier: `vk_account` must be a finalized vk account of the
+    /// active verifier whose contents hash to the registered vk hash
+    pub fn require_registered_vk_account(
+        &self,
+        verifier_program: &AccountInfo,
+        vk_account: &AccountInfo,
+    ) -> Result<()> {
+        self.require_registered(verifier_program)?;
+        require_keys_eq!(
+            *vk_account.owner,
+            self.active.program_id,
+            ZaffreError::UnregisteredVerifyingKey
+        );
+        let data = vk_account.try_borrow_data()?;
+        require!(
+            data.len() >= VK_ACCOUNT_HEADER_LEN
+                && data[..8] == VK_ACCOUNT_DISCRIMINATOR[..]
+                && data[8..40] == self.active.vk_hash
+                && data[VK_ACCOUNT_HEADER_LEN - 1] != 0,
+            ZaffreError::UnregisteredVerifyingKey
+        );
+        Ok(())
+    }
+}
+
+/// `verify_ownership_proof` that refuses to cpi into anything but the registry's active verifier
+pub fn verify_registered_ownership_proof<'info>(
+    registry: &VerifierRegistry,
+    verifier_program: &AccountInfo<'info>,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    registry.require_registered(verifier_program)?;
+    verify_ownership_proof(verifier_program, proof, public_witness)
+}
+
+pub fn verify_registered_membership_proof<'info>(
+    registry: &VerifierRegistry,
+    verifier_program: &AccountInfo<'info>,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    registry.require_registered(verifier_program)?;
+    verify_membership_proof(verifier_program, proof, public_witness)
+}
+
+pub fn verify_registered_ownership_proof_with_vk_account<'info>(
+    registry: &VerifierRegistry,
+    verifier_program: &AccountInfo<'info>,
+    vk_account: &AccountInfo<'info>,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    registry.require_registered_vk_account(verifier_program, vk_account)?;
+    verify_ownership_proof_with_vk_account(verifier_program, vk_account, proof, public_witness)
+}
+
+pub fn verify_registered_membership_proof_with_vk_account<'info>(
+    registry: &VerifierRegistry,
+    verifier_program: &AccountInfo<'info>,
+    vk_account: &AccountInfo<'info>,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    registry.require_registered_vk_account(verifier_program, vk_account)?;
+    verify_membership_proof_with_vk_account(verifier_program, vk_account, proof, public_witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(program_id: Pubkey, version: u32) -> VerifierEntry {
+        VerifierEntry::new(program_id, "ownership", version, compute_vk_hash(b"vk")).unwrap()
+    }
+
+    // runs `f` with an executable builtin account for `program_id`, which is immutable
+    fn with_builtin<R>(program_id: Pubkey, f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let (mut lamports, mut data, owner) = (1u64, vec![], NATIVE_LOADER_ID);
+        let account = AccountInfo::new(
+            &program_id,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            true,
+            0,
+        );
+        f(&account)
+    }
+
+    fn registry(active: VerifierEntry, timelock_slots: u64) -> VerifierRegistry {
+        with_builtin(active.program_id, |program| {
+            VerifierRegistry::new(Pubkey::new_unique(), active, program, None, timelock_slots)
+        })
+        .unwrap()
+    }
+
+    fn propose(registry: &mut VerifierRegistry, entry: VerifierEntry, slot: u64) -> Result<u64> {
+        with_builtin(entry.program_id, |program| {
+            registry.propose_upgrade(entry, program, None, slot)
+        })
+    }
+
+    #[test]
+    fn test_serialized_size() {
+        let mut registry = registry(entry(Pubkey::new_unique(), 1), 100);
+        propose(&mut registry, entry(Pubkey::new_unique(), 2), 5).unwrap();
+        assert_eq!(registry.try_to_vec().unwrap().len(), VerifierRegistry::SIZE);
+        assert_eq!(registry.active.circuit_name(), "ownership");
+    }
+
+    #[test]
+    fn test_circuit_name_limits() {
+        let program_id = Pubkey::new_unique();
+        assert!(VerifierEntry::new(program_id, "", 1, [0u8; 32]).is_err());
+        assert!(VerifierEntry::new(program_id, &"a".repeat(33), 1, [0u8; 32]).is_err());
+        let full = VerifierEntry::new(program_id, &"a".repeat(32), 1, [0u8; 32]).unwrap();
+        assert_eq!(full.circuit_name().len(), 32);
+    }
+
+    #[test]
+    fn test_upgrade_timelock() {
+        let old = entry(Pubkey::new_unique(), 1);
+        let new = entry(Pubkey::new_unique(), 2);
+        let mut registry = registry(old, 100);
+
+        let err = registry.apply_upgrade(0).unwrap_err();
+        assert_eq!(err, ZaffreError::NoPendingVerifierUpgrade.into());
+
+        assert_eq!(propose(&mut registry, new, 50).unwrap(), 150);
+        assert!(registry.is_registered(&old.program_id));
+        assert!(!registry.is_registered(&new.program_id));
+
+        let err = registry.apply_upgrade(149).unwrap_err();
+        assert_eq!(err, ZaffreError::VerifierUpgradeTimelocked.into());
+        assert_eq!(registry.apply_upgrade(150).unwrap(), new);
+        assert!(registry.is_registered(&new.program_id));
+        assert!(registry.pending.is_none());
+
+        propose(&mut registry, old, 200).unwrap();
+        registry.cancel_upgrade().unwrap();
+        assert!(registry.cancel_upgrade().is_err());
+        assert!(registry.apply_upgrade(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_require_registered() {
+        let verifier = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let loader = Pubkey::new_unique();
+        let registry = registry(entry(verifier, 1), 0);
+
+        let (mut lamports, mut data) = (1u64, vec![]);
+        let account = AccountInfo::new(
+            &verifier,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &loader,
+            true,
+            0,
+        );
+        registry.require_registered(&account).unwrap();
+
+        let (mut lamports, mut data) = (1u64, vec![]);
+        let unregistered = AccountInfo::new(
+            &other,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &loader,
+            true,
+            0,
+        );
+        let err =
+            verify_registered_ownership_proof(&registry, &unregistered, &[], &[]).unwrap_err();
+        assert_eq!(err, ZaffreError::UnregisteredVerifier.into());
+
+        let (mut lamports, mut data) = (1u64, vec![]);
+        let not_program = AccountInfo::new(
+            &verifier,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &loader,
+            false,
+            0,
+        );
+        let err = registry.require_registered(&not_program).unwrap_err();
+        assert_eq!(err, ZaffreError::UnregisteredVerifier.into());
+    }
+
+    #[test]
+    fn test_require_registered_vk_account() {
+        let verifier = Pubkey::new_unique();
+        let loader = Pubkey::new_unique();
+        let registry = registry(entry(verifier, 1), 0);
+
+        let (mut lamports, mut data) = (1u64, vec![]);
+        let program = AccountInfo::new(
+            &verifier,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &loader,
+            true,
+            0,
+        );
+
+        let vk_header = |vk_hash: [u8; 32], finalized: u8| {
+            let mut data = b"zaffrevk".to_vec();
+            data.extend_from_slice(&vk_hash);
+            data.extend_from_slice(&[3u8; 32]);
+            data.extend_from_slice(&2u32.to_le_bytes());
+            data.extend_from_slice(&[finalized, 7, 7]);
+            data
+        };
+        let vk_key = Pubkey::new_unique();
+        let check = |owner: &Pubkey, mut data: Vec<u8>| {
+            let mut lamports = 1u64;
+            let vk_account = AccountInfo::new(
+                &vk_key,
+                false,
+                false,
+                &mut lamports,
+                &mut data,
+                owner,
+                false,
+                0,
+            );
+            registry.require_registered_vk_account(&program, &vk_account)
+        };
+
+        check(&verifier, vk_header(compute_vk_hash(b"vk"), 1)).unwrap();
+        let err = ZaffreError::UnregisteredVerifyingKey.into();
+        assert_eq!(
+            check(&verifier, vk_header(compute_vk_hash(b"vk"), 0)).unwrap_err(),
+            err
+        );
+        assert_eq!(check(&verifier, vk_header([1u8; 32], 1)).unwrap_err(), err);
+        assert_eq!(
+            check(&loader, vk_header(compute_vk_hash(b"vk"), 1)).unwrap_err(),
+            err
+        );
+        assert_eq!(check(&verifier, vec![0u8; 10]).unwrap_err(), err);
+    }
+
+    #[test]
+    fn test_require_immutable_program() {
+        let program_id = Pubkey::new_unique();
+        let upgradeable = bpf_loader_upgradeable::id();
+        let program_data_id = program_data_address(&program_id);
+        let check = |owner: &Pubkey, executable: bool, data_key: &Pubkey, authority: Option<u8>| {
+            let (mut lamports, mut data) = (1u64, vec![]);
+            let program = AccountInfo::new(
+                &program_id,
+                false,
+                false,
+                &mut lamports,
+                &mut data,
+                owner,
+                executable,
+                0,
+            );
+            let mut program_data = PROGRAM_DATA_VARIANT.to_le_bytes().to_vec();
+            program_data.extend_from_slice(&7u64.to_le_bytes());
+            match authority {
+                Some(byte) => {
+                    program_data.push(1);
+                    program_data.extend_from_slice(&[byte; 32]);
+                }
+                None => program_data.push(0),
+            }
+            program_data.extend_from_slice(&[0xee; 16]);
+            let mut data_lamports = 1u64;
+            let program_data = AccountInfo::new(
+                data_key,
+                false,
+                false,
+                &mut data_lamports,
+                &mut program_data,
+                &upgradeable,
+                false,
+                0,
+            );
+            require_immutable_program(&program, Some(&program_data))
+        };
+
+        check(&bpf_loader::id(), true, &program_data_id, None).unwrap();
+        check(&NATIVE_LOADER_ID, true, &program_data_id, None).unwrap();
+        check(&upgradeable, true, &program_data_id, None).unwrap();
+
+        let err = ZaffreError::MutableVerifier.into();
+        assert_eq!(
+            check(&upgradeable, true, &program_data_id, Some(5)).unwrap_err(),
+            err
+        );
+        assert_eq!(
+            check(&upgradeable, true, &Pubkey::new_unique(), None).unwrap_err(),
+            err
+        );
+        assert_eq!(
+            check(&bpf_loader::id(), false, &program_data_id, None).unwrap_err(),
+            err
+        );
+        assert_eq!(
+            check(&Pubkey::new_unique(), true, &program_data_id, None).unwrap_err(),
+            err
+        );
+
+        // an upgradeable verifier cannot be registered while it has an authority
+        let (mut lamports, mut data) = (1u64, vec![]);
+        let program = AccountInfo::new(
+            &program_id,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &upgradeable,
+            true,
+            0,
+        );
+        let err = VerifierRegistry::new(
+            Pubkey::new_unique(),
+            entry(program_id, 1),
+            &program,
+            None,
+            0,
+        )
+        .unwrap_err();
+        assert_eq!(err, ZaffreError::MutableVerifier.into());
+        let err = with_builtin(Pubkey::new_unique(), |other| {
+            VerifierRegistry::new(Pubkey::new_unique(), entry(program_id, 1), other, None, 0)
+        })
+        .unwrap_err();
+        assert_eq!(err, ZaffreError::UnregisteredVerifier.into());
+    }
+}
@@ -0,0 +1,77 @@
+//! relayer fees paid out of a zaffre pda, so the user's wallet never signs
+
+use anchor_lang::prelude::*;
+
+use crate::validation::ZaffreError;
+
+/// moves `fee` lamports from `state` to `relayer`. `state` must be owned by the calling
+/// program and stays rent exempt. the fee and relayer must be the ones bound into the
+/// verified action hash (`zaffre_core::compute_relayed_action_hash`), and the relayer
+/// account must be that key; this helper only moves the lamports.
+pub fn pay_relayer(
+    program_id: &Pubkey,
+    state: &AccountInfo,
+    relayer: &AccountInfo,
+    fee: u64,
+) -> Result<()> {
+    require_keys_eq!(*state.owner, *program_id, ZaffreError::InvalidStateOwner);
+    if fee == 0 {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?.minimum_balance(state.data_len());
+    let remaining = state
+        .lamports()
+        .checked_sub(fee)
+        .filter(|remaining| *remaining >= rent)
+        .ok_or(ZaffreError::InsufficientRelayerFeeBalance)?;
+    let relayer_lamports = relayer
+        .lamports()
+        .checked_add(fee)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    **state.try_borrow_mut_lamports()? = remaining;
+    **relayer.try_borrow_mut_lamports()? = relayer_lamports;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_foreign_state() {
+        let program_id = Pubkey::new_unique();
+        let (state_key, relayer_key, foreign) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (mut state_lamports, mut relayer_lamports) = (1_000_000u64, 0u64);
+        let (mut state_data, mut relayer_data) = (vec![0u8; 16], vec![]);
+        let state = AccountInfo::new(
+            &state_key,
+            false,
+            true,
+            &mut state_lamports,
+            &mut state_data,
+            &foreign,
+            false,
+            0,
+        );
+        let relayer = AccountInfo::new(
+            &relayer_key,
+            true,
+            true,
+            &mut relayer_lamports,
+            &mut relayer_data,
+            &foreign,
+            false,
+            0,
+        );
+
+        let err = pay_relayer(&program_id, &state, &relayer, 10).unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidStateOwner.into());
+        assert_eq!(relayer.lamports(), 0);
+    }
+}
@@ -0,0 +1,122 @@
+//! secret rotation: move a zaffre pda account to a new commitment
+
+use anchor_lang::prelude::*;
+use solana_program::program::invoke_signed;
+use solana_program::{system_instruction, system_program};
+use zaffre_core::{RotateAction, ZaffreAction};
+
+use crate::validation::{validate_zaffre_pda, ZaffreError, ZAFFRE_SEED_PREFIX};
+
+/// action discriminator for rotation proofs. the action params are the new commitment,
+/// so an ownership proof over `c_old` with this discriminator is the rotation proof.
+pub const ROTATE_DISCRIMINATOR: [u8; 8] = <RotateAction as ZaffreAction>::DISCRIMINATOR;
+
+/// moves `old_state` to the pda of `new_commitment`: the new account is allocated with
+/// the same size and owner, receives the data and all lamports, and the old account is
+/// closed. returns the bump of the new pda.
+///
+/// the caller verifies the rotation proof first and afterwards rewrites any commitment
+/// or bump stored in the account data.
+pub fn rotate_state_account<'info>(
+    program_id: &Pubkey,
+    old_state: &AccountInfo<'info>,
+    new_state: &AccountInfo<'info>,
+    old_commitment: &[u8; 32],
+    new_commitment: &[u8; 32],
+    system_program: &AccountInfo<'info>,
+) -> Result<u8> {
+    validate_zaffre_pda(program_id, old_commitment, old_state.key)?;
+    let new_bump = validate_zaffre_pda(program_id, new_commitment, new_state.key)?;
+    require_keys_eq!(*old_state.owner, *program_id, ZaffreError::InvalidStateOwner);
+    // lamports may already sit at the new address; only data or an owner means it is taken
+    require!(
+        *new_state.owner == system_program::ID && new_state.data_is_empty(),
+        ZaffreError::StateAccountInUse
+    );
+
+    let space = old_state.data_len() as u64;
+    let bump = [new_bump];
+    let seeds: &[&[u8]] = &[ZAFFRE_SEED_PREFIX, new_commitment, &bump];
+    let accounts = [new_state.clone(), system_program.clone()];
+    invoke_signed(
+        &system_instruction::allocate(new_state.key, space),
+        &accounts,
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_state.key, program_id),
+        &accounts,
+        &[seeds],
+    )?;
+
+    new_state
+        .try_borrow_mut_data()?
+        .copy_from_slice(&old_state.try_borrow_data()?);
+
+    let lamports = new_state
+        .lamports()
+        .checked_add(old_state.lamports())
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **new_state.try_borrow_mut_lamports()? = lamports;
+    **old_state.try_borrow_mut_lamports()? = 0;
+    old_state.assign(&system_program::ID);
+    old_state.realloc(0, false)?;
+
+    Ok(new_bump)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_pda(program_id: &Pubkey, commitment: &[u8; 32]) -> Pubkey {
+        Pubkey::find_program_address(&[ZAFFRE_SEED_PREFIX, commitment], program_id).0
+    }
+
+    #[test]
+    fn test_rejects_existing_or_mismatched_target() {
+        let program_id = Pubkey::new_unique();
+        let old_commitment = [1u8; 32];
+        let new_commitment = [2u8; 32];
+        let old_key = state_pda(&program_id, &old_commitment);
+        let new_key = state_pda(&program_id, &new_commitment);
+        let system_key = system_program::ID;
+        let native_loader = Pubkey::default();
+
+        let (mut old_lamports, mut new_lamports, mut system_lamports) = (10u64, 0u64, 1u64);
+        let (mut old_data, mut new_data, mut system_data) = (vec![7u8; 16], vec![0u8; 16], vec![]);
+        let old_state = AccountInfo::new(
+            &old_key, false, true, &mut old_lamports, &mut old_data, &program_id, false, 0,
+        );
+        let taken_state = AccountInfo::new(
+            &new_key, false, true, &mut new_lamports, &mut new_data, &program_id, false, 0,
+        );
+        let system = AccountInfo::new(
+            &system_key, false, false, &mut system_lamports, &mut system_data, &native_loader,
+            true, 0,
+        );
+
+        let err = rotate_state_account(
+            &program_id,
+            &old_state,
+            &taken_state,
+            &old_commitment,
+            &new_commitment,
+            &system,
+        )
+        .unwrap_err();
+        assert_eq!(err, ZaffreError::StateAccountInUse.into());
+
+        let err = rotate_state_account(
+            &program_id,
+            &old_state,
+            &taken_state,
+            &old_commitment,
+            &[3u8; 32],
+            &system,
+        )
+        .unwrap_err();
+        assert_eq!(err, ZaffreError::InvalidPDA.into());
+        assert_eq!(old_state.lamports(), 10);
+    }
+}
@@ -1,56 +1,143 @@
 //! sunspot verifier cpi helpers
 
 use anchor_lang::prelude::*;
-use solana_program::instruction::Instruction;
+use solana_program::instruction::{AccountMeta, Instruction};
 use solana_program::program::invoke;
 
 #[error_code]
 pub enum SunspotError {
     #[msg("Invalid proof length")]
     InvalidProofLength,
-    #[msg("Invalid public witness length (expected 5132 bytes)")]
+    #[msg("Invalid public witness length")]
     InvalidPublicWitnessLength,
     #[msg("Verifier invocation failed")]
     VerifierInvocationFailed,
+    #[msg("Invalid proof encoding")]
+    InvalidProofEncoding,
+    #[msg("Proof verification failed")]
+    ProofVerificationFailed,
 }
 
 pub const GROTH16_PROOF_SIZE: usize = 388;
 
-pub const PUBLIC_INPUT_BYTES: usize = 160;
-pub const PUBLIC_INPUT_COUNT: usize = PUBLIC_INPUT_BYTES;
-pub const PUBLIC_WITNESS_HEADER_LEN: usize = 12;
-pub const PUBLIC_WITNESS_SIZE: usize = PUBLIC_WITNESS_HEADER_LEN + PUBLIC_INPUT_COUNT * 32;
+pub use zaffre_core::witness::{
+    MEMBERSHIP_PUBLIC_INPUT_BYTES, MEMBERSHIP_PUBLIC_INPUT_COUNT, MEMBERSHIP_PUBLIC_WITNESS_SIZE,
+    PUBLIC_INPUT_BYTES, PUBLIC_INPUT_COUNT, PUBLIC_INPUT_LIMB_BYTES, PUBLIC_WITNESS_HEADER_LEN,
+    PUBLIC_WITNESS_SIZE,
+};
+
+/// instruction tag of `Verify` in the multi-circuit verifier
+pub const VERIFY_WITH_VK_ACCOUNT_TAG: u8 = 3;
 
 pub fn verify_ownership_proof<'info>(
     verifier_program: &AccountInfo<'info>,
     proof: &[u8],
     public_witness: &[u8],
+) -> Result<()> {
+    invoke_verifier(
+        verifier_program,
+        None,
+        proof,
+        public_witness,
+        PUBLIC_WITNESS_SIZE,
+    )?;
+    msg!("Zaffre ownership proof verified");
+    Ok(())
+}
+
+/// verifies a membership proof; the caller must also check the root in the public
+/// witness against the commitment tree (see `merkle::require_known_root`). any tree
+/// member can prove an action on any pda, so this does not authorize a specific account.
+pub fn verify_membership_proof<'info>(
+    verifier_program: &AccountInfo<'info>,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    invoke_verifier(
+        verifier_program,
+        None,
+        proof,
+        public_witness,
+        MEMBERSHIP_PUBLIC_WITNESS_SIZE,
+    )?;
+    msg!("Zaffre membership proof verified");
+    Ok(())
+}
+
+/// verifies through the multi-circuit verifier (`programs/verifier`), which reads the
+/// verifying key from `vk_account` instead of embedding it
+pub fn verify_ownership_proof_with_vk_account<'info>(
+    verifier_program: &AccountInfo<'info>,
+    vk_account: &AccountInfo<'info>,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    invoke_verifier(
+        verifier_program,
+        Some(vk_account),
+        proof,
+        public_witness,
+        PUBLIC_WITNESS_SIZE,
+    )?;
+    msg!("Zaffre ownership proof verified");
+    Ok(())
+}
+
+pub fn verify_membership_proof_with_vk_account<'info>(
+    verifier_program: &AccountInfo<'info>,
+    vk_account: &AccountInfo<'info>,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<()> {
+    invoke_verifier(
+        verifier_program,
+        Some(vk_account),
+        proof,
+        public_witness,
+        MEMBERSHIP_PUBLIC_WITNESS_SIZE,
+    )?;
+    msg!("Zaffre membership proof verified");
+    Ok(())
+}
+
+fn invoke_verifier<'info>(
+    verifier_program: &AccountInfo<'info>,
+    vk_account: Option<&AccountInfo<'info>>,
+    proof: &[u8],
+    public_witness: &[u8],
+    public_witness_size: usize,
 ) -> Result<()> {
     require!(
         proof.len() == GROTH16_PROOF_SIZE,
         SunspotError::InvalidProofLength
     );
     require!(
-        public_witness.len() == PUBLIC_WITNESS_SIZE,
+        public_witness.len() == public_witness_size,
         SunspotError::InvalidPublicWitnessLength
     );
 
-    let mut instruction_data = Vec::with_capacity(proof.len() + PUBLIC_WITNESS_SIZE);
+    let mut instruction_data = Vec::with_capacity(1 + proof.len() + public_witness_size);
+    if vk_account.is_some() {
+        instruction_data.push(VERIFY_WITH_VK_ACCOUNT_TAG);
+    }
     instruction_data.extend_from_slice(proof);
     instruction_data.extend_from_slice(public_witness);
 
+    // sunspot verifiers are stateless; the multi-circuit verifier reads the vk account
     let ix = Instruction {
         program_id: *verifier_program.key,
-        accounts: vec![], // sunspot verifiers are stateless
+        accounts: vk_account
+            .map(|vk| AccountMeta::new_readonly(*vk.key, false))
+            .into_iter()
+            .collect(),
         data: instruction_data,
     };
+    let account_infos: Vec<AccountInfo<'info>> = vk_account.into_iter().cloned().collect();
 
-    invoke(&ix, &[]).map_err(|e| {
+    invoke(&ix, &account_infos).map_err(|e| {
         msg!("Sunspot verification failed: {:?}", e);
         SunspotError::VerifierInvocationFailed
     })?;
-
-    msg!("Zaffre ownership proof verified");
     Ok(())
 }
 
@@ -69,7 +156,10 @@ mod tests {
     #[test]
     fn test_constants() {
         assert_eq!(PUBLIC_INPUT_BYTES, 160);
-        assert_eq!(PUBLIC_WITNESS_SIZE, 5132);
+        assert_eq!(PUBLIC_INPUT_COUNT, 10);
+        assert_eq!(PUBLIC_WITNESS_SIZE, 332);
         assert_eq!(GROTH16_PROOF_SIZE, 388);
+        assert_eq!(MEMBERSHIP_PUBLIC_INPUT_COUNT, 8);
+        assert_eq!(MEMBERSHIP_PUBLIC_WITNESS_SIZE, 268);
     }
 }
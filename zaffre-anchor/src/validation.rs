@@ -1,9 +1,8 @@
 //! pda validation and nullifier helpers
 
 use anchor_lang::prelude::*;
-
-pub const ZAFFRE_SEED_PREFIX: &[u8] = b"zaffre";
-pub const NULLIFIER_SEED_PREFIX: &[u8] = b"nullifier";
+pub use zaffre_core::pda::{NULLIFIER_SEED_PREFIX, ZAFFRE_SEED_PREFIX};
+use zaffre_core::ZaffreCoreError;
 
 #[error_code]
 pub enum ZaffreError {
@@ -11,6 +10,70 @@ pub enum ZaffreError {
     InvalidPDA,
     #[msg("Nullifier has already been spent")]
     NullifierAlreadySpent,
+    #[msg("Merkle root is not in the recent root history")]
+    UnknownMerkleRoot,
+    #[msg("Commitment tree is full")]
+    MerkleTreeFull,
+    #[msg("Merkle leaf is not a canonical field element")]
+    InvalidMerkleLeaf,
+    #[msg("Commitment tree account is too small")]
+    InvalidCommitmentTree,
+    #[msg("State account is not owned by the program")]
+    InvalidStateOwner,
+    #[msg("State account already exists")]
+    StateAccountInUse,
+    #[msg("Verifier program is not registered")]
+    UnregisteredVerifier,
+    #[msg("Circuit name must be 1 to 32 bytes without nul")]
+    InvalidCircuitName,
+    #[msg("No verifier upgrade is pending")]
+    NoPendingVerifierUpgrade,
+    #[msg("Verifier upgrade timelock has not elapsed")]
+    VerifierUpgradeTimelocked,
+    #[msg("Verifying key account does not hold the registered key")]
+    UnregisteredVerifyingKey,
+    #[msg("Nullifier shard account is invalid or belongs to another shard")]
+    InvalidNullifierShard,
+    #[msg("Nullifier shard is full")]
+    NullifierShardFull,
+    #[msg("State account cannot pay the relayer fee and stay rent exempt")]
+    InsufficientRelayerFeeBalance,
+    #[msg("Nullifier account is not a spent nullifier of this program")]
+    InvalidNullifierAccount,
+    #[msg("Nullifier is still inside the garbage collection window")]
+    NullifierWindowOpen,
+    #[msg("Action params are too long for the ownership circuit")]
+    ActionParamsTooLong,
+    #[msg("Public witness has the wrong length")]
+    InvalidPublicWitnessLength,
+    #[msg("Public witness header declares the wrong number of inputs")]
+    InvalidPublicWitnessHeader,
+    #[msg("Public input limb is wider than 128 bits")]
+    NonCanonicalPublicInput,
+    #[msg("Poseidon hash failed")]
+    PoseidonHashFailed,
+    #[msg("Secret derivation input is invalid")]
+    InvalidSecretDerivation,
+    #[msg("Verifier program can still be upgraded")]
+    MutableVerifier,
+    #[msg("Destination is not the rent collector")]
+    InvalidRentCollector,
+}
+
+/// lets programs surface core failures with `.map_err(ZaffreError::from)?`
+impl From<ZaffreCoreError> for ZaffreError {
+    fn from(err: ZaffreCoreError) -> Self {
+        match err {
+            ZaffreCoreError::ActionParamsTooLong { .. } => Self::ActionParamsTooLong,
+            ZaffreCoreError::InvalidPublicWitnessLength { .. } => Self::InvalidPublicWitnessLength,
+            ZaffreCoreError::InvalidPublicWitnessHeader { .. } => Self::InvalidPublicWitnessHeader,
+            ZaffreCoreError::NonCanonicalPublicInput { .. } => Self::NonCanonicalPublicInput,
+            ZaffreCoreError::Poseidon => Self::PoseidonHashFailed,
+            ZaffreCoreError::InvalidMnemonic | ZaffreCoreError::InvalidDerivationPurpose => {
+                Self::InvalidSecretDerivation
+            }
+        }
+    }
 }
 
 pub fn validate_zaffre_pda(
@@ -44,7 +107,9 @@ pub struct NullifierStateData {
 
 impl NullifierStateData {
     pub fn new(slot: u64) -> Self {
-        Self { spent_at_slot: slot }
+        Self {
+            spent_at_slot: slot,
+        }
     }
 
     pub fn to_bytes(&self) -> [u8; 8] {
@@ -0,0 +1,46 @@
+// the fixtures come from a synthetic setup (zaffre_prover::verify tests), not a real
+// ownership circuit; the witness is for program id [3; 32] and pda [4; 32]. build.rs embeds
+// tests/fixtures/ownership.vk whenever gnark-verifier is on, which the dev-dependency forces
+
+use gnark_verifier_solana::vk::GnarkVerifyingkey;
+use zaffre_anchor::*;
+
+mod fixture_vk {
+    include!(concat!(env!("OUT_DIR"), "/fixture_ownership_vk.rs"));
+}
+
+const PROOF: &[u8] = include_bytes!("fixtures/ownership.proof");
+const PUBLIC_WITNESS: &[u8] = include_bytes!("fixtures/ownership.pw");
+
+fn verify(proof: &[u8], public_witness: &[u8]) -> anchor_lang::Result<()> {
+    verify_ownership_proof_with_key(&fixture_vk::VK, proof, public_witness)
+}
+
+#[test]
+fn test_native_verifier_accepts_valid_proof() {
+    verify(PROOF, PUBLIC_WITNESS).unwrap();
+}
+
+#[test]
+fn test_native_verifier_rejects_bad_input() {
+    let mut public_witness = PUBLIC_WITNESS.to_vec();
+    public_witness[PUBLIC_WITNESS.len() - 1] ^= 1;
+    let err = verify(PROOF, &public_witness).unwrap_err();
+    assert_eq!(err, SunspotError::ProofVerificationFailed.into());
+
+    let err = verify(&PROOF[1..], PUBLIC_WITNESS).unwrap_err();
+    assert_eq!(err, SunspotError::InvalidProofLength.into());
+
+    let err = verify(PROOF, &PUBLIC_WITNESS[1..]).unwrap_err();
+    assert_eq!(err, SunspotError::InvalidPublicWitnessLength.into());
+}
+
+#[test]
+fn test_native_verifier_rejects_wrong_circuit_key() {
+    let vk = GnarkVerifyingkey {
+        nr_pubinputs: fixture_vk::VK.nr_pubinputs - 1,
+        ..fixture_vk::VK
+    };
+    let err = verify_ownership_proof_with_key(&vk, PROOF, PUBLIC_WITNESS).unwrap_err();
+    assert_eq!(err, SunspotError::ProofVerificationFailed.into());
+}
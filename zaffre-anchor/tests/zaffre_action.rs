@@ -0,0 +1,125 @@
+// anchor's derive checks its own `anchor-debug` feature, which this crate does not have
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use zaffre_anchor::*;
+
+declare_id!("HDGiZbLeKG5XqixDtAQb9dzuCiCTVP7Yg6En3ZyDHXM1");
+
+#[account]
+pub struct Config {
+    pub registry: VerifierRegistry,
+}
+
+impl ZaffreConfig for Config {
+    const SEED: &'static [u8] = b"zaffre_config";
+
+    fn registry(&self) -> &VerifierRegistry {
+        &self.registry
+    }
+}
+
+#[account]
+pub struct DemoState {
+    pub value: u64,
+}
+
+#[zaffre_action(discriminator = b"setvalue", params(value: u64))]
+#[derive(Accounts)]
+pub struct SetValue<'info> {
+    #[account(mut, seeds = [ZAFFRE_SEED_PREFIX, commitment.as_ref()], bump)]
+    pub state: Account<'info, DemoState>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[zaffre_action(discriminator = b"memo", params(to: Pubkey, memo: String), params_digest, payer = relayer)]
+#[derive(Accounts)]
+pub struct Memo<'info> {
+    /// CHECK: only its address is bound
+    pub state: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[zaffre_action(discriminator = b"note", params(note: String))]
+#[derive(Accounts)]
+pub struct Note<'info> {
+    /// CHECK: only its address is bound
+    pub state: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(ZaffreAction)]
+#[zaffre(discriminator = b"withdraw", crate = ::zaffre_anchor::zaffre_core)]
+pub struct WithdrawAction {
+    pub to: Pubkey,
+}
+
+#[zaffre_action(action = WithdrawAction)]
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut, seeds = [ZAFFRE_SEED_PREFIX, commitment.as_ref()], bump)]
+    pub state: Account<'info, DemoState>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[test]
+fn test_action_hash_matches_core() {
+    let program_id = Pubkey::new_unique();
+    let state = Pubkey::new_unique();
+
+    assert_eq!(SetValue::ACTION_DISCRIMINATOR, *b"setvalue");
+    assert_eq!(
+        SetValue::action_hash(&program_id, &state, &7u64, 3).unwrap(),
+        zaffre_core::compute_action_hash(&program_id, &state, b"setvalue", &7u64.to_le_bytes(), 3)
+    );
+
+    let to = Pubkey::new_unique();
+    let memo = "a memo longer than the inline params limit".to_string();
+    let mut params = to.to_bytes().to_vec();
+    params.extend_from_slice(&(memo.len() as u32).to_le_bytes());
+    params.extend_from_slice(memo.as_bytes());
+    assert_eq!(Memo::ACTION_DISCRIMINATOR, *b"memo\0\0\0\0");
+    assert_eq!(
+        Memo::action_hash(&program_id, &state, &to, &memo, 9).unwrap(),
+        zaffre_core::compute_action_hash_with_params_digest(
+            &program_id,
+            &state,
+            b"memo\0\0\0\0",
+            &params,
+            9
+        )
+    );
+}
+
+#[test]
+fn test_typed_action_hash() {
+    let program_id = Pubkey::new_unique();
+    let state = Pubkey::new_unique();
+    let action = WithdrawAction {
+        to: Pubkey::new_unique(),
+    };
+
+    assert_eq!(Withdraw::ACTION_DISCRIMINATOR, *b"withdraw");
+    assert_eq!(
+        Withdraw::action_hash(&program_id, &state, &action, 2).unwrap(),
+        zaffre_core::compute_action_hash(&program_id, &state, b"withdraw", action.to.as_ref(), 2)
+    );
+}
+
+#[test]
+fn test_long_params_fail_with_zaffre_error() {
+    let program_id = Pubkey::new_unique();
+    let state = Pubkey::new_unique();
+
+    let err = Note::action_hash(&program_id, &state, &"x".repeat(40), 1).unwrap_err();
+    assert_eq!(err, ZaffreError::ActionParamsTooLong.into());
+    assert!(Note::action_hash(&program_id, &state, &"short".to_string(), 1).is_ok());
+}
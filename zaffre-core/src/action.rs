@@ -0,0 +1,185 @@
+//! typed zaffre actions: one definition of the discriminator and params encoding
+
+use crate::error::ZaffreCoreError;
+use crate::hash::{compute_action_params_digest, try_compute_action_hash};
+use crate::types::AddressBytes;
+
+/// an action an ownership proof can authorize. implemented once and shared by the
+/// program and its clients, usually with `#[derive(ZaffreAction)]`, so both sides hash
+/// the same discriminator and params.
+pub trait ZaffreAction {
+    const DISCRIMINATOR: [u8; 8];
+    /// hash the params with `compute_action_hash_with_params_digest`, for params longer
+    /// than `OWNERSHIP_ACTION_PARAMS_MAX_LEN`
+    const PARAMS_DIGEST: bool = false;
+
+    /// borsh-encoded action params
+    fn action_params(&self) -> Vec<u8>;
+
+    /// the params the circuit sees: the encoded params, or their digest
+    fn circuit_params(&self) -> Vec<u8> {
+        let params = self.action_params();
+        if Self::PARAMS_DIGEST {
+            compute_action_params_digest(&params).to_vec()
+        } else {
+            params
+        }
+    }
+
+    /// fails if the params are too long for the ownership circuit and the action does
+    /// not use the params digest
+    fn try_action_hash(
+        &self,
+        program_id: &impl AddressBytes,
+        pda: &impl AddressBytes,
+        nonce: u64,
+    ) -> Result<[u8; 32], ZaffreCoreError> {
+        try_compute_action_hash(
+            program_id,
+            pda,
+            &Self::DISCRIMINATOR,
+            &self.circuit_params(),
+            nonce,
+        )
+    }
+
+    /// panics where `try_action_hash` fails
+    fn action_hash(
+        &self,
+        program_id: &impl AddressBytes,
+        pda: &impl AddressBytes,
+        nonce: u64,
+    ) -> [u8; 32] {
+        self.try_action_hash(program_id, pda, nonce)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+}
+
+/// moves a zaffre pda account to the pda of `new_commitment`
+#[derive(Clone, Debug, PartialEq, Eq, crate::ZaffreAction)]
+#[zaffre(discriminator = b"rotate", crate = crate)]
+pub struct RotateAction {
+    pub new_commitment: [u8; 32],
+}
+
+/// closes a zaffre pda account and sends its lamports to `destination`
+#[derive(Clone, Debug, PartialEq, Eq, crate::ZaffreAction)]
+#[zaffre(discriminator = b"close", crate = crate)]
+pub struct CloseAction {
+    pub destination: [u8; 32],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{
+        compute_action_hash, compute_action_hash_with_params_digest, compute_relayed_action_hash,
+    };
+    use crate::ZaffreAction;
+
+    #[derive(crate::ZaffreAction)]
+    #[zaffre(discriminator = b"memo", params_digest, crate = crate)]
+    struct MemoAction {
+        to: [u8; 32],
+        memo: String,
+    }
+
+    #[test]
+    fn test_builtin_actions() {
+        let (program_id, pda) = ([1u8; 32], [2u8; 32]);
+
+        let rotate = RotateAction {
+            new_commitment: [4u8; 32],
+        };
+        assert_eq!(RotateAction::DISCRIMINATOR, *b"rotate\0\0");
+        assert_eq!(rotate.action_params(), [4u8; 32]);
+        assert_eq!(
+            rotate.action_hash(&program_id, &pda, 1),
+            compute_action_hash(&program_id, &pda, b"rotate\0\0", &[4u8; 32], 1)
+        );
+
+        let close = CloseAction {
+            destination: [5u8; 32],
+        };
+        assert_eq!(CloseAction::DISCRIMINATOR, *b"close\0\0\0");
+        assert_eq!(close.action_params(), [5u8; 32]);
+    }
+
+    #[test]
+    fn test_derived_relayed_action() {
+        #[derive(crate::ZaffreAction)]
+        #[zaffre(discriminator = b"relayset", params_digest, crate = crate)]
+        struct RelayedSet {
+            value: u64,
+            relayer: [u8; 32],
+            fee: u64,
+        }
+
+        let (program_id, pda, relayer) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+        let action = RelayedSet {
+            value: 7,
+            relayer,
+            fee: 1000,
+        };
+        assert_eq!(
+            action.action_hash(&program_id, &pda, 4),
+            compute_relayed_action_hash(
+                &program_id,
+                &pda,
+                b"relayset",
+                &7u64.to_le_bytes(),
+                &relayer,
+                1000,
+                4
+            )
+        );
+    }
+
+    #[test]
+    fn test_try_action_hash_rejects_long_params() {
+        #[derive(crate::ZaffreAction)]
+        #[zaffre(discriminator = b"memo", crate = crate)]
+        struct InlineMemo(String);
+
+        let memo = InlineMemo("a memo longer than the inline params limit".to_string());
+        let err = memo.try_action_hash(&[1u8; 32], &[2u8; 32], 1).unwrap_err();
+        assert!(matches!(
+            err,
+            ZaffreCoreError::ActionParamsTooLong { max: 32, .. }
+        ));
+    }
+
+    #[test]
+    fn test_derived_params_digest() {
+        let (program_id, pda) = ([1u8; 32], [2u8; 32]);
+        let memo = MemoAction {
+            to: [3u8; 32],
+            memo: "a memo longer than the inline params limit".to_string(),
+        };
+
+        let mut params = [3u8; 32].to_vec();
+        params.extend_from_slice(&(memo.memo.len() as u32).to_le_bytes());
+        params.extend_from_slice(memo.memo.as_bytes());
+        assert_eq!(memo.action_params(), params);
+        assert_eq!(memo.circuit_params(), compute_action_params_digest(&params));
+        assert_eq!(
+            memo.action_hash(&program_id, &pda, 5),
+            compute_action_hash_with_params_digest(&program_id, &pda, b"memo\0\0\0\0", &params, 5)
+        );
+    }
+
+    #[cfg(feature = "onchain")]
+    #[test]
+    fn test_pubkey_and_bytes_agree() {
+        use solana_program::pubkey::Pubkey;
+
+        let (program_id, pda) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let rotate = RotateAction {
+            new_commitment: [4u8; 32],
+        };
+        assert_eq!(
+            rotate.action_hash(&program_id, &pda, 1),
+            rotate.action_hash(&program_id.to_bytes(), &pda.to_bytes(), 1)
+        );
+    }
+}
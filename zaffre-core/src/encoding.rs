@@ -1,12 +1,17 @@
 //! encoding helpers
 
-use solana_program::{hash::{hash, hashv}, pubkey::Pubkey};
+use solana_program::pubkey::Pubkey;
+
+pub use crate::hash::{
+    compute_action_hash, compute_action_hash_with_params_digest, compute_action_params_digest,
+    compute_domain_separator, compute_relayed_action_hash, encode_relayed_action_params,
+    try_compute_action_hash,
+};
+use crate::witness::{
+    pack_public_witness, unpack_public_witness, PublicInputs, MEMBERSHIP_PUBLIC_INPUT_BYTES,
+    PUBLIC_INPUT_BYTES,
+};
 
-pub const PUBLIC_INPUT_BYTES: usize = 160;
-pub const PUBLIC_INPUT_COUNT: usize = PUBLIC_INPUT_BYTES;
-pub const PUBLIC_WITNESS_HEADER_LEN: usize = 12;
-pub const PUBLIC_WITNESS_SIZE: usize = PUBLIC_WITNESS_HEADER_LEN + PUBLIC_INPUT_COUNT * 32;
-pub const OWNERSHIP_ACTION_PARAMS_MAX_LEN: usize = 32;
 pub fn serialize_public_inputs(
     commitment: &[u8; 32],
     pda: &Pubkey,
@@ -31,72 +36,42 @@ pub fn serialize_public_witness(
     nullifier: &[u8; 32],
 ) -> Vec<u8> {
     let raw = serialize_public_inputs(commitment, pda, program_id, action_hash, nullifier);
-    let mut out = vec![0u8; PUBLIC_WITNESS_SIZE];
-    out[0..4].copy_from_slice(&(PUBLIC_INPUT_COUNT as u32).to_be_bytes());
-    out[4..8].copy_from_slice(&0u32.to_be_bytes());
-    out[8..12].copy_from_slice(&(PUBLIC_INPUT_COUNT as u32).to_be_bytes());
-
-    let mut offset = PUBLIC_WITNESS_HEADER_LEN + 31;
-    for b in raw {
-        out[offset] = b;
-        offset += 32;
-    }
-    out
+    pack_public_witness(&raw)
 }
 
+/// `None` on any malformed witness; `PublicInputs::from_public_witness` says why
 pub fn extract_public_inputs(public_witness: &[u8]) -> Option<[u8; PUBLIC_INPUT_BYTES]> {
-    if public_witness.len() != PUBLIC_WITNESS_SIZE {
-        return None;
-    }
-
-    let public_count = u32::from_be_bytes(public_witness[0..4].try_into().ok()?) as usize;
-    let reserved = u32::from_be_bytes(public_witness[4..8].try_into().ok()?) as usize;
-    let vector_len = u32::from_be_bytes(public_witness[8..12].try_into().ok()?) as usize;
-    if public_count != PUBLIC_INPUT_COUNT || reserved != 0 || vector_len != PUBLIC_INPUT_COUNT {
-        return None;
-    }
-
-    let mut raw = [0u8; PUBLIC_INPUT_BYTES];
-    let mut offset = PUBLIC_WITNESS_HEADER_LEN;
-    for byte in raw.iter_mut() {
-        *byte = public_witness[offset + 31];
-        offset += 32;
-    }
-    Some(raw)
+    unpack_public_witness(public_witness).ok()
 }
 
-pub fn compute_action_hash(
+pub fn serialize_membership_public_inputs(
+    root: &[u8; 32],
     program_id: &Pubkey,
-    pda: &Pubkey,
-    action_discriminator: &[u8; 8],
-    action_params: &[u8],
-    nonce: u64,
-) -> [u8; 32] {
-    assert!(
-        action_params.len() <= OWNERSHIP_ACTION_PARAMS_MAX_LEN,
-        "action_params must be <= {OWNERSHIP_ACTION_PARAMS_MAX_LEN} bytes"
-    );
-    let nonce_bytes = nonce.to_le_bytes();
-    hashv(&[
-        &program_id.to_bytes(),
-        &pda.to_bytes(),
-        action_discriminator,
-        action_params,
-        &nonce_bytes,
-    ])
-    .to_bytes()
+    action_hash: &[u8; 32],
+    nullifier: &[u8; 32],
+) -> [u8; MEMBERSHIP_PUBLIC_INPUT_BYTES] {
+    let mut result = [0u8; MEMBERSHIP_PUBLIC_INPUT_BYTES];
+    result[0..32].copy_from_slice(root);
+    result[32..64].copy_from_slice(&program_id.to_bytes());
+    result[64..96].copy_from_slice(action_hash);
+    result[96..MEMBERSHIP_PUBLIC_INPUT_BYTES].copy_from_slice(nullifier);
+    result
 }
 
-pub fn compute_domain_separator(program_id: &Pubkey) -> [u8; 32] {
-    hash(&program_id.to_bytes()).to_bytes()
+pub fn serialize_membership_public_witness(
+    root: &[u8; 32],
+    program_id: &Pubkey,
+    action_hash: &[u8; 32],
+    nullifier: &[u8; 32],
+) -> Vec<u8> {
+    let raw = serialize_membership_public_inputs(root, program_id, action_hash, nullifier);
+    pack_public_witness(&raw)
 }
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct PublicInputs {
-    pub commitment: [u8; 32],
-    pub pda: [u8; 32],
-    pub program_id: [u8; 32],
-    pub action_hash: [u8; 32],
-    pub nullifier: [u8; 32],
+
+pub fn extract_membership_public_inputs(
+    public_witness: &[u8],
+) -> Option<[u8; MEMBERSHIP_PUBLIC_INPUT_BYTES]> {
+    unpack_public_witness(public_witness).ok()
 }
 
 impl PublicInputs {
@@ -115,31 +90,13 @@ impl PublicInputs {
             nullifier,
         }
     }
-
-    pub fn to_bytes(&self) -> [u8; PUBLIC_INPUT_BYTES] {
-        let mut result = [0u8; PUBLIC_INPUT_BYTES];
-        result[0..32].copy_from_slice(&self.commitment);
-        result[32..64].copy_from_slice(&self.pda);
-        result[64..96].copy_from_slice(&self.program_id);
-        result[96..128].copy_from_slice(&self.action_hash);
-        result[128..PUBLIC_INPUT_BYTES].copy_from_slice(&self.nullifier);
-        result
-    }
-
-    pub fn to_public_witness(&self) -> Vec<u8> {
-        serialize_public_witness(
-            &self.commitment,
-            &Pubkey::new_from_array(self.pda),
-            &Pubkey::new_from_array(self.program_id),
-            &self.action_hash,
-            &self.nullifier,
-        )
-    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ZaffreCoreError;
+    use crate::witness::*;
 
     #[test]
     fn test_serialize_public_inputs() {
@@ -219,6 +176,91 @@ mod tests {
         let _ = compute_action_hash(&program_id, &pda, &discriminator, &params, 1);
     }
 
+    #[test]
+    fn test_try_action_hash_rejects_long_params() {
+        let program_id = Pubkey::new_unique();
+        let pda = Pubkey::new_unique();
+        let params = [1u8; OWNERSHIP_ACTION_PARAMS_MAX_LEN];
+
+        assert_eq!(
+            try_compute_action_hash(&program_id, &pda, b"transfer", &params, 1).unwrap(),
+            compute_action_hash(&program_id, &pda, b"transfer", &params, 1)
+        );
+        assert_eq!(
+            try_compute_action_hash(&program_id, &pda, b"transfer", &[1u8; 33], 1),
+            Err(ZaffreCoreError::ActionParamsTooLong { len: 33, max: 32 })
+        );
+    }
+
+    #[test]
+    fn test_action_hash_with_params_digest() {
+        let program_id = Pubkey::new_unique();
+        let pda = Pubkey::new_unique();
+        let discriminator = *b"transfer";
+        let params = vec![7u8; 200];
+
+        let hash = compute_action_hash_with_params_digest(
+            &program_id,
+            &pda,
+            &discriminator,
+            &params,
+            9,
+        );
+        let digest = compute_action_params_digest(&params);
+        assert_eq!(
+            hash,
+            compute_action_hash(&program_id, &pda, &discriminator, &digest, 9)
+        );
+
+        let mut other = params.clone();
+        other[199] = 8;
+        assert_ne!(
+            hash,
+            compute_action_hash_with_params_digest(&program_id, &pda, &discriminator, &other, 9)
+        );
+    }
+
+    #[test]
+    fn test_relayed_action_hash_binds_relayer_and_fee() {
+        let program_id = Pubkey::new_unique();
+        let pda = Pubkey::new_unique();
+        let relayer = Pubkey::new_unique();
+        let discriminator = *b"relayset";
+        let params = 5u64.to_le_bytes();
+
+        let hash = compute_relayed_action_hash(
+            &program_id,
+            &pda,
+            &discriminator,
+            &params,
+            &relayer,
+            1000,
+            1,
+        );
+        let encoded = encode_relayed_action_params(&params, &relayer, 1000);
+        assert_eq!(encoded.len(), 48);
+        assert_eq!(
+            hash,
+            compute_action_hash_with_params_digest(&program_id, &pda, &discriminator, &encoded, 1)
+        );
+
+        let other_relayer = Pubkey::new_unique();
+        for (relayer, fee) in [(&other_relayer, 1000), (&relayer, 1001)] {
+            assert_ne!(
+                hash,
+                compute_relayed_action_hash(
+                    &program_id,
+                    &pda,
+                    &discriminator,
+                    &params,
+                    relayer,
+                    fee,
+                    1
+                )
+            );
+        }
+    }
+
     #[test]
     fn test_public_inputs_struct() {
         let commitment = [1u8; 32];
@@ -239,12 +281,15 @@ mod tests {
         let commitment = [1u8; 32];
         let pda = Pubkey::new_from_array([2u8; 32]);
         let program_id = Pubkey::new_from_array([3u8; 32]);
-        let action_hash = [4u8; 32];
+        let mut action_hash = [4u8; 32];
+        action_hash[16] = 0xaa;
         let nullifier = [5u8; 32];
 
         let witness =
             serialize_public_witness(&commitment, &pda, &program_id, &action_hash, &nullifier);
         assert_eq!(witness.len(), PUBLIC_WITNESS_SIZE);
+        assert_eq!(PUBLIC_INPUT_COUNT, 10);
+        assert_eq!(PUBLIC_WITNESS_SIZE, 332);
 
         let expected_count = (PUBLIC_INPUT_COUNT as u32).to_be_bytes();
         assert_eq!(&witness[0..4], &expected_count);
@@ -252,22 +297,92 @@ mod tests {
         assert_eq!(&witness[8..12], &expected_count);
 
         let first_entry = PUBLIC_WITNESS_HEADER_LEN;
-        assert!(witness[first_entry..first_entry + 31]
-            .iter()
-            .all(|b| *b == 0));
-        assert_eq!(witness[first_entry + 31], commitment[0]);
+        assert!(witness[first_entry..first_entry + 16].iter().all(|b| *b == 0));
+        assert_eq!(&witness[first_entry + 16..first_entry + 32], &commitment[0..16]);
 
-        let action_hash_entry = PUBLIC_WITNESS_HEADER_LEN + 32 * 96;
-        assert!(witness[action_hash_entry..action_hash_entry + 31]
+        // action_hash is the fourth value, so its lo limb is entry 7
+        let action_hash_lo = PUBLIC_WITNESS_HEADER_LEN + PUBLIC_WITNESS_ENTRY_LEN * 7;
+        assert!(witness[action_hash_lo..action_hash_lo + 16]
             .iter()
             .all(|b| *b == 0));
-        assert_eq!(witness[action_hash_entry + 31], action_hash[0]);
+        assert_eq!(
+            &witness[action_hash_lo + 16..action_hash_lo + 32],
+            &action_hash[16..32]
+        );
+
+        let nullifier_hi = PUBLIC_WITNESS_HEADER_LEN + PUBLIC_WITNESS_ENTRY_LEN * 8;
+        assert_eq!(
+            &witness[nullifier_hi + 16..nullifier_hi + 32],
+            &nullifier[0..16]
+        );
+    }
 
-        let nullifier_entry = PUBLIC_WITNESS_HEADER_LEN + 32 * 128;
-        assert!(witness[nullifier_entry..nullifier_entry + 31]
-            .iter()
-            .all(|b| *b == 0));
-        assert_eq!(witness[nullifier_entry + 31], nullifier[0]);
+    #[test]
+    fn test_extract_rejects_oversized_limb() {
+        let pda = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let mut witness =
+            serialize_public_witness(&[1u8; 32], &pda, &program_id, &[2u8; 32], &[3u8; 32]);
+        assert!(extract_public_inputs(&witness).is_some());
+
+        witness[PUBLIC_WITNESS_HEADER_LEN] = 1;
+        assert!(extract_public_inputs(&witness).is_none());
+    }
+
+    #[test]
+    fn test_public_inputs_from_public_witness() {
+        let pda = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let inputs = PublicInputs::new([1u8; 32], &pda, &program_id, [2u8; 32], [3u8; 32]);
+        let mut witness = inputs.to_public_witness();
+        assert_eq!(PublicInputs::from_public_witness(&witness), Ok(inputs));
+
+        assert_eq!(
+            PublicInputs::from_public_witness(&witness[1..]),
+            Err(ZaffreCoreError::InvalidPublicWitnessLength {
+                expected: PUBLIC_WITNESS_SIZE,
+                actual: PUBLIC_WITNESS_SIZE - 1,
+            })
+        );
+        witness[3] = 9;
+        assert_eq!(
+            PublicInputs::from_public_witness(&witness),
+            Err(ZaffreCoreError::InvalidPublicWitnessHeader { expected: 10 })
+        );
+        witness[3] = 10;
+        witness[PUBLIC_WITNESS_HEADER_LEN + PUBLIC_WITNESS_ENTRY_LEN * 4] = 1;
+        assert_eq!(
+            PublicInputs::from_public_witness(&witness),
+            Err(ZaffreCoreError::NonCanonicalPublicInput { index: 4 })
+        );
+
+        let membership =
+            serialize_membership_public_witness(&[7u8; 32], &program_id, &[8u8; 32], &[9u8; 32]);
+        assert!(matches!(
+            PublicInputs::from_public_witness(&membership),
+            Err(ZaffreCoreError::InvalidPublicWitnessLength { .. })
+        ));
+    }
+
+    #[test]
+    fn test_membership_public_witness_roundtrip() {
+        let root = [7u8; 32];
+        let program_id = Pubkey::new_unique();
+        let action_hash = [8u8; 32];
+        let nullifier = [9u8; 32];
+
+        let witness =
+            serialize_membership_public_witness(&root, &program_id, &action_hash, &nullifier);
+        assert_eq!(witness.len(), MEMBERSHIP_PUBLIC_WITNESS_SIZE);
+        assert_eq!(MEMBERSHIP_PUBLIC_WITNESS_SIZE, 268);
+        assert_eq!(&witness[0..4], &(MEMBERSHIP_PUBLIC_INPUT_COUNT as u32).to_be_bytes());
+
+        let raw = extract_membership_public_inputs(&witness).expect("witness should decode");
+        assert_eq!(
+            raw,
+            serialize_membership_public_inputs(&root, &program_id, &action_hash, &nullifier)
+        );
+        assert!(extract_public_inputs(&witness).is_none());
     }
 
     #[test]
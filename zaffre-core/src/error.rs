@@ -0,0 +1,21 @@
+//! errors returned by the fallible core helpers
+
+use thiserror::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum ZaffreCoreError {
+    #[error("action_params must be <= {max} bytes, got {len}")]
+    ActionParamsTooLong { len: usize, max: usize },
+    #[error("public witness must be {expected} bytes, got {actual}")]
+    InvalidPublicWitnessLength { expected: usize, actual: usize },
+    #[error("public witness header must declare {expected} public inputs")]
+    InvalidPublicWitnessHeader { expected: usize },
+    #[error("public input limb {index} is wider than 128 bits")]
+    NonCanonicalPublicInput { index: usize },
+    #[error("poseidon hash failed")]
+    Poseidon,
+    #[error("invalid bip-39 mnemonic or entropy")]
+    InvalidMnemonic,
+    #[error("derivation purpose must be 1 to 64 printable ascii bytes")]
+    InvalidDerivationPurpose,
+}
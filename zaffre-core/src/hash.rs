@@ -0,0 +1,160 @@
+//! action hashes and params encodings, over any `AddressBytes` so programs and off-chain
+//! clients run the same code. with `onchain`, sha256 goes through solana-program (a
+//! syscall on-chain); otherwise it is computed with sha2.
+
+use crate::error::ZaffreCoreError;
+use crate::types::AddressBytes;
+use crate::witness::OWNERSHIP_ACTION_PARAMS_MAX_LEN;
+
+#[cfg(feature = "onchain")]
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    solana_program::hash::hashv(parts).to_bytes()
+}
+
+#[cfg(not(feature = "onchain"))]
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// panics if `action_params` is longer than `OWNERSHIP_ACTION_PARAMS_MAX_LEN`; on-chain
+/// code should use `try_compute_action_hash`
+pub fn compute_action_hash(
+    program_id: &impl AddressBytes,
+    pda: &impl AddressBytes,
+    action_discriminator: &[u8; 8],
+    action_params: &[u8],
+    nonce: u64,
+) -> [u8; 32] {
+    try_compute_action_hash(program_id, pda, action_discriminator, action_params, nonce)
+        .unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// sha256(program_id || pda || discriminator || params || nonce_le), matching the circuits
+pub fn try_compute_action_hash(
+    program_id: &impl AddressBytes,
+    pda: &impl AddressBytes,
+    action_discriminator: &[u8; 8],
+    action_params: &[u8],
+    nonce: u64,
+) -> Result<[u8; 32], ZaffreCoreError> {
+    if action_params.len() > OWNERSHIP_ACTION_PARAMS_MAX_LEN {
+        return Err(ZaffreCoreError::ActionParamsTooLong {
+            len: action_params.len(),
+            max: OWNERSHIP_ACTION_PARAMS_MAX_LEN,
+        });
+    }
+    Ok(sha256(&[
+        &program_id.address_bytes(),
+        &pda.address_bytes(),
+        action_discriminator,
+        action_params,
+        &nonce.to_le_bytes(),
+    ]))
+}
+
+/// sha256 of borsh-serialized (or otherwise encoded) action params of any length
+pub fn compute_action_params_digest(action_params: &[u8]) -> [u8; 32] {
+    sha256(&[action_params])
+}
+
+/// action hash for params longer than `OWNERSHIP_ACTION_PARAMS_MAX_LEN`: the hash
+/// commits to `sha256(params)` in place of the params, so it equals
+/// `compute_action_hash` over the 32-byte digest and the ownership circuit proves it
+/// with the digest as its action params. a program must pick one scheme per
+/// discriminator.
+pub fn compute_action_hash_with_params_digest(
+    program_id: &impl AddressBytes,
+    pda: &impl AddressBytes,
+    action_discriminator: &[u8; 8],
+    action_params: &[u8],
+    nonce: u64,
+) -> [u8; 32] {
+    let digest = compute_action_params_digest(action_params);
+    compute_action_hash(program_id, pda, action_discriminator, &digest, nonce)
+}
+
+/// action params of a relayed action: `action_params || relayer || fee_le_u64`. the proof
+/// then authorizes one relayer to collect one fee, so neither can be swapped in transit.
+pub fn encode_relayed_action_params(
+    action_params: &[u8],
+    relayer: &impl AddressBytes,
+    fee: u64,
+) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(action_params.len() + 40);
+    encoded.extend_from_slice(action_params);
+    encoded.extend_from_slice(&relayer.address_bytes());
+    encoded.extend_from_slice(&fee.to_le_bytes());
+    encoded
+}
+
+/// action hash for a relayed action, always over the params digest of
+/// `encode_relayed_action_params` since the relayer binding alone is 40 bytes
+pub fn compute_relayed_action_hash(
+    program_id: &impl AddressBytes,
+    pda: &impl AddressBytes,
+    action_discriminator: &[u8; 8],
+    action_params: &[u8],
+    relayer: &impl AddressBytes,
+    fee: u64,
+    nonce: u64,
+) -> [u8; 32] {
+    let encoded = encode_relayed_action_params(action_params, relayer, fee);
+    compute_action_hash_with_params_digest(program_id, pda, action_discriminator, &encoded, nonce)
+}
+
+/// `sha256(program_id)`, the nullifier's domain
+pub fn compute_domain_separator(program_id: &impl AddressBytes) -> [u8; 32] {
+    sha256(&[&program_id.address_bytes()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_action_hash_layout() {
+        let mut hasher = Sha256::new();
+        hasher.update([1u8; 32]);
+        hasher.update([2u8; 32]);
+        hasher.update(b"setvalue");
+        hasher.update([3u8; 8]);
+        hasher.update(4u64.to_le_bytes());
+        let expected: [u8; 32] = hasher.finalize().into();
+        assert_eq!(
+            compute_action_hash(&[1u8; 32], &[2u8; 32], b"setvalue", &[3u8; 8], 4),
+            expected
+        );
+        assert_eq!(
+            compute_domain_separator(&[1u8; 32]),
+            <[u8; 32]>::from(Sha256::digest([1u8; 32]))
+        );
+    }
+
+    #[test]
+    fn test_action_hash_binds_every_field() {
+        let hash = |program_id: u8, pda: u8, discriminator: &[u8; 8], params: &[u8], nonce| {
+            compute_action_hash(&[program_id; 32], &[pda; 32], discriminator, params, nonce)
+        };
+        let base = hash(1, 2, b"setvalue", &[3u8; 8], 4);
+        assert_ne!(base, hash(1, 2, b"setvalue", &[3u8; 8], 5));
+        assert_ne!(base, hash(1, 9, b"setvalue", &[3u8; 8], 4));
+        assert_ne!(base, hash(1, 2, b"rotate\0\0", &[3u8; 8], 4));
+        assert_ne!(base, hash(1, 2, b"setvalue", &[3u8; 7], 4));
+    }
+
+    #[test]
+    fn test_relayed_action_params() {
+        let encoded = encode_relayed_action_params(&[1, 2], &[3u8; 32], 0x0405);
+        assert_eq!(encoded.len(), 42);
+        assert_eq!(&encoded[..2], &[1, 2]);
+        assert_eq!(&encoded[2..34], &[3u8; 32]);
+        assert_eq!(&encoded[34..], &0x0405u64.to_le_bytes());
+    }
+}
@@ -1,19 +1,68 @@
-//! core primitives for zaffre
+//! core primitives for zaffre. without default features only the types and the public
+//! witness layout are built; `actions` adds typed actions, action hashes and pda search
+//! over raw 32-byte ids, and `onchain` adds everything that needs solana-program.
 
+#[cfg(feature = "actions")]
+pub mod action;
+#[cfg(feature = "onchain")]
 pub mod encoding;
+pub mod error;
+#[cfg(feature = "prover")]
+pub mod field;
+#[cfg(feature = "actions")]
+pub mod hash;
+#[cfg(feature = "actions")]
 pub mod pda;
+#[cfg(any(feature = "onchain", feature = "prover"))]
+pub mod poseidon;
+#[cfg(feature = "onchain")]
+pub mod scan;
+pub mod seed;
 pub mod types;
+pub mod wallet;
+pub mod witness;
 
+#[cfg(feature = "actions")]
+pub use action::{CloseAction, RotateAction, ZaffreAction};
+#[cfg(feature = "onchain")]
 pub use encoding::{
-    compute_action_hash, compute_domain_separator, extract_public_inputs, serialize_public_inputs,
-    serialize_public_witness, PublicInputs, PUBLIC_INPUT_BYTES, PUBLIC_WITNESS_SIZE,
+    extract_membership_public_inputs, extract_public_inputs, serialize_membership_public_inputs,
+    serialize_membership_public_witness, serialize_public_inputs, serialize_public_witness,
 };
+pub use error::ZaffreCoreError;
+#[cfg(feature = "actions")]
+pub use hash::{
+    compute_action_hash, compute_action_hash_with_params_digest, compute_action_params_digest,
+    compute_domain_separator, compute_relayed_action_hash, encode_relayed_action_params,
+    try_compute_action_hash,
+};
+#[cfg(feature = "onchain")]
+pub use pda::{
+    derive_nullifier_pda, derive_nullifier_shard_pda, derive_zaffre_pda,
+    find_nullifier_shard_address, ZaffrePDA,
+};
+#[cfg(feature = "actions")]
 pub use pda::{
-    derive_nullifier_pda, derive_zaffre_pda, ZaffrePDA, NULLIFIER_SEED_PREFIX, ZAFFRE_SEED_PREFIX,
+    nullifier_shard_index, NULLIFIER_SEED_PREFIX, NULLIFIER_SHARD_SEED_PREFIX, ZAFFRE_SEED_PREFIX,
 };
-pub use types::{Commitment, Nullifier, Secret};
+#[cfg(any(feature = "onchain", feature = "prover"))]
+pub use poseidon::{compute_commitment, compute_nullifier};
+#[cfg(feature = "onchain")]
+pub use scan::{scan_zaffre_pdas, PdaUsage, ScannedAccount, DEFAULT_GAP_LIMIT};
+pub use seed::MasterSeed;
+pub use types::{AddressBytes, Commitment, Nullifier, Secret};
+pub use wallet::{secret_from_wallet_signature, wallet_derivation_message};
+pub use witness::{
+    pack_public_witness, unpack_public_witness, PublicInputs, MEMBERSHIP_PUBLIC_INPUT_BYTES,
+    MEMBERSHIP_PUBLIC_WITNESS_SIZE, OWNERSHIP_ACTION_PARAMS_MAX_LEN, PUBLIC_INPUT_BYTES,
+    PUBLIC_WITNESS_SIZE,
+};
+
+pub use borsh;
+#[cfg(feature = "actions")]
+pub use zaffre_derive::ZaffreAction;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "onchain"))]
 mod tests {
     use super::*;
     use solana_program::pubkey::Pubkey;
@@ -1,10 +1,18 @@
+//! program derived addresses of zaffre accounts. the `Pubkey` functions need `onchain`;
+//! `bytes` finds the same addresses over raw 32-byte ids, without solana-program.
+
+#[cfg(feature = "onchain")]
 use solana_program::pubkey::Pubkey;
 
-use crate::types::{Commitment, Nullifier};
+#[cfg(feature = "onchain")]
+use crate::types::Commitment;
+use crate::types::Nullifier;
 
 pub const ZAFFRE_SEED_PREFIX: &[u8] = b"zaffre";
 pub const NULLIFIER_SEED_PREFIX: &[u8] = b"nullifier";
+pub const NULLIFIER_SHARD_SEED_PREFIX: &[u8] = b"nullifier_shard";
 
+#[cfg(feature = "onchain")]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ZaffrePDA {
     pub address: Pubkey,
@@ -12,6 +20,7 @@ pub struct ZaffrePDA {
     pub commitment: Commitment,
 }
 
+#[cfg(feature = "onchain")]
 pub fn derive_zaffre_pda(program_id: &Pubkey, commitment: &Commitment) -> ZaffrePDA {
     let (address, bump) = Pubkey::find_program_address(
         &[ZAFFRE_SEED_PREFIX, commitment.as_bytes()],
@@ -24,6 +33,7 @@ pub fn derive_zaffre_pda(program_id: &Pubkey, commitment: &Commitment) -> Zaffre
     }
 }
 
+#[cfg(feature = "onchain")]
 pub fn derive_nullifier_pda(program_id: &Pubkey, nullifier: &Nullifier) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[NULLIFIER_SEED_PREFIX, nullifier.as_bytes()],
@@ -31,7 +41,152 @@ pub fn derive_nullifier_pda(program_id: &Pubkey, nullifier: &Nullifier) -> (Pubk
     )
 }
 
-#[cfg(test)]
+/// shard of the sharded nullifier set holding `nullifier`: the first byte of the
+/// little-endian nullifier picks one of 256 shards
+pub fn nullifier_shard_index(nullifier: &Nullifier) -> u8 {
+    nullifier.as_bytes()[0]
+}
+
+#[cfg(feature = "onchain")]
+pub fn derive_nullifier_shard_pda(program_id: &Pubkey, nullifier: &Nullifier) -> (Pubkey, u8) {
+    find_nullifier_shard_address(program_id, nullifier_shard_index(nullifier))
+}
+
+/// shard account by index, for programs that create or walk the shards
+#[cfg(feature = "onchain")]
+pub fn find_nullifier_shard_address(program_id: &Pubkey, shard: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[NULLIFIER_SHARD_SEED_PREFIX, &[shard]], program_id)
+}
+
+/// the pda functions over raw 32-byte ids, for clients without solana-program. programs
+/// use the `Pubkey` versions, which run the syscall.
+#[cfg(not(target_os = "solana"))]
+pub mod bytes {
+    use curve25519_dalek::edwards::CompressedEdwardsY;
+    use sha2::{Digest, Sha256};
+
+    use super::{
+        nullifier_shard_index, NULLIFIER_SEED_PREFIX, NULLIFIER_SHARD_SEED_PREFIX,
+        ZAFFRE_SEED_PREFIX,
+    };
+    use crate::types::{Commitment, Nullifier};
+
+    const PDA_MARKER: &[u8] = b"ProgramDerivedAddress";
+
+    /// same search as `Pubkey::find_program_address`: the first bump from 255 down whose
+    /// address is off the ed25519 curve
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &[u8; 32]) -> Option<([u8; 32], u8)> {
+        (0..=u8::MAX).rev().find_map(|bump| {
+            create_program_address(seeds, bump, program_id).map(|address| (address, bump))
+        })
+    }
+
+    pub fn create_program_address(
+        seeds: &[&[u8]],
+        bump: u8,
+        program_id: &[u8; 32],
+    ) -> Option<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update([bump]);
+        hasher.update(program_id);
+        hasher.update(PDA_MARKER);
+        let address: [u8; 32] = hasher.finalize().into();
+        CompressedEdwardsY(address)
+            .decompress()
+            .is_none()
+            .then_some(address)
+    }
+
+    pub fn derive_zaffre_pda(program_id: &[u8; 32], commitment: &Commitment) -> ([u8; 32], u8) {
+        find_program_address(&[ZAFFRE_SEED_PREFIX, commitment.as_bytes()], program_id)
+            .expect("no valid bump for zaffre pda")
+    }
+
+    pub fn derive_nullifier_pda(program_id: &[u8; 32], nullifier: &Nullifier) -> ([u8; 32], u8) {
+        find_program_address(&[NULLIFIER_SEED_PREFIX, nullifier.as_bytes()], program_id)
+            .expect("no valid bump for nullifier pda")
+    }
+
+    pub fn derive_nullifier_shard_pda(
+        program_id: &[u8; 32],
+        nullifier: &Nullifier,
+    ) -> ([u8; 32], u8) {
+        let shard = [nullifier_shard_index(nullifier)];
+        find_program_address(&[NULLIFIER_SHARD_SEED_PREFIX, &shard], program_id)
+            .expect("no valid bump for nullifier shard pda")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // expected addresses from solana_program::pubkey::Pubkey::find_program_address
+        #[test]
+        fn test_matches_solana_derivation() {
+            let program_id = [3u8; 32];
+            assert_eq!(
+                bs58::encode(program_id).into_string(),
+                "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8"
+            );
+
+            let (address, bump) =
+                derive_zaffre_pda(&program_id, &Commitment::from_bytes([1u8; 32]));
+            assert_eq!(
+                bs58::encode(address).into_string(),
+                "BPUEPFN2JMZcK3DzrG3MJXAAwBXr4nnUX4bxYVam91wD"
+            );
+            assert_eq!(bump, 255);
+
+            // bumps 255..=246 land on the curve for this seed
+            let (address, bump) =
+                derive_zaffre_pda(&program_id, &Commitment::from_bytes([3u8; 32]));
+            assert_eq!(
+                bs58::encode(address).into_string(),
+                "26rpAHSxcEKsrNs5nw63boFx4VzrVR5WYgntEHNvBPMG"
+            );
+            assert_eq!(bump, 245);
+            assert!(
+                create_program_address(&[ZAFFRE_SEED_PREFIX, &[3u8; 32]], 246, &program_id)
+                    .is_none()
+            );
+
+            let (address, bump) =
+                derive_nullifier_shard_pda(&program_id, &Nullifier::from_bytes([7u8; 32]));
+            assert_eq!(
+                bs58::encode(address).into_string(),
+                "HGDwZEn7uENxSt29c6x2hZBCWTX44EwaQPbCXFbnUNoe"
+            );
+            assert_eq!(bump, 255);
+        }
+
+        #[cfg(feature = "onchain")]
+        #[test]
+        fn test_matches_pubkey_derivation() {
+            use solana_program::pubkey::Pubkey;
+
+            let program_id = Pubkey::new_unique();
+            for byte in 0..16u8 {
+                let commitment = Commitment::from_bytes([byte; 32]);
+                let pda = super::super::derive_zaffre_pda(&program_id, &commitment);
+                assert_eq!(
+                    derive_zaffre_pda(&program_id.to_bytes(), &commitment),
+                    (pda.address.to_bytes(), pda.bump)
+                );
+                let nullifier = Nullifier::from_bytes([byte; 32]);
+                let (address, bump) = super::super::derive_nullifier_pda(&program_id, &nullifier);
+                assert_eq!(
+                    derive_nullifier_pda(&program_id.to_bytes(), &nullifier),
+                    (address.to_bytes(), bump)
+                );
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "onchain"))]
 mod tests {
     use super::*;
 
@@ -54,4 +209,21 @@ mod tests {
         let pda2 = derive_zaffre_pda(&program_id, &c2);
         assert_ne!(pda1.address, pda2.address);
     }
+
+    #[test]
+    fn test_nullifier_shard_by_first_byte() {
+        let program_id = Pubkey::new_unique();
+        let mut bytes = [9u8; 32];
+        let shard = derive_nullifier_shard_pda(&program_id, &Nullifier::from_bytes(bytes));
+        bytes[31] = 1;
+        assert_eq!(
+            derive_nullifier_shard_pda(&program_id, &Nullifier::from_bytes(bytes)),
+            shard
+        );
+        bytes[0] = 1;
+        assert_ne!(
+            derive_nullifier_shard_pda(&program_id, &Nullifier::from_bytes(bytes)),
+            shard
+        );
+    }
 }
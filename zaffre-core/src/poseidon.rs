@@ -0,0 +1,162 @@
+//! poseidon commitments and nullifiers, matching `poseidon::bn254` in the ownership
+//! circuit. with `onchain` this runs the `sol_poseidon` syscall (solana-program uses
+//! light-poseidon off-chain); `prover` alone hashes with light-poseidon directly.
+
+use crate::error::ZaffreCoreError;
+use crate::hash::compute_domain_separator;
+use crate::types::{AddressBytes, Commitment, Nullifier, Secret};
+
+/// bn254 scalar field modulus as little-endian u64 limbs
+const MODULUS: [u64; 4] = [
+    0x43e1f593f0000001,
+    0x2833e84879b97091,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+];
+
+/// reduces 32 little-endian bytes modulo the bn254 scalar field, the way the circuit
+/// and `zaffre_prover` turn secrets and sha256 outputs into field elements. the
+/// syscall itself rejects inputs that are not canonical.
+pub fn reduce_to_field(bytes: &[u8; 32]) -> [u8; 32] {
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    // 2^256 < 6p, so at most five subtractions
+    while !less_than_modulus(&limbs) {
+        let mut borrow = false;
+        for (limb, modulus) in limbs.iter_mut().zip(MODULUS) {
+            let (diff, b1) = limb.overflowing_sub(modulus);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            *limb = diff;
+            borrow = b1 || b2;
+        }
+    }
+    let mut out = [0u8; 32];
+    for (chunk, limb) in out.chunks_exact_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    out
+}
+
+fn less_than_modulus(limbs: &[u64; 4]) -> bool {
+    for (limb, modulus) in limbs.iter().zip(MODULUS).rev() {
+        if *limb != modulus {
+            return *limb < modulus;
+        }
+    }
+    false
+}
+
+#[cfg(feature = "onchain")]
+fn poseidon(inputs: &[&[u8]]) -> Result<[u8; 32], ZaffreCoreError> {
+    use solana_program::poseidon::{hashv, Endianness, Parameters};
+
+    hashv(Parameters::Bn254X5, Endianness::LittleEndian, inputs)
+        .map(|hash| hash.to_bytes())
+        .map_err(|_| ZaffreCoreError::Poseidon)
+}
+
+#[cfg(not(feature = "onchain"))]
+fn poseidon(inputs: &[&[u8]]) -> Result<[u8; 32], ZaffreCoreError> {
+    use ark_bn254::Fr;
+    use light_poseidon::{Poseidon, PoseidonBytesHasher};
+
+    let mut hasher =
+        Poseidon::<Fr>::new_circom(inputs.len()).map_err(|_| ZaffreCoreError::Poseidon)?;
+    hasher
+        .hash_bytes_le(inputs)
+        .map_err(|_| ZaffreCoreError::Poseidon)
+}
+
+/// `c = Poseidon(secret)`, little-endian like the circuit's `to_le_bytes`
+pub fn compute_commitment(secret: &Secret) -> Result<Commitment, ZaffreCoreError> {
+    let secret = reduce_to_field(secret.as_bytes());
+    poseidon(&[&secret]).map(Commitment::from_bytes)
+}
+
+/// `n = Poseidon(secret, sha256(program_id), action_hash)`
+pub fn compute_nullifier(
+    secret: &Secret,
+    program_id: &impl AddressBytes,
+    action_hash: &[u8; 32],
+) -> Result<Nullifier, ZaffreCoreError> {
+    let secret = reduce_to_field(secret.as_bytes());
+    let domain = reduce_to_field(&compute_domain_separator(program_id));
+    let action = reduce_to_field(action_hash);
+    poseidon(&[&secret, &domain, &action]).map(Nullifier::from_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(hex: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        out
+    }
+
+    fn from_be_hex(hex: &str) -> [u8; 32] {
+        let mut out = from_hex(hex);
+        out.reverse();
+        out
+    }
+
+    fn field(value: u8) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out[0] = value;
+        out
+    }
+
+    #[test]
+    fn test_noir_bn254_vectors() {
+        // poseidon::bn254::hash_1([1]) and hash_2([1, 2]) from the noir test suite
+        assert_eq!(
+            poseidon(&[&field(1)]).unwrap(),
+            from_be_hex("29176100eaa962bdc1fe6c654d6a3c130e96a4d1168b33848b897dc502820133")
+        );
+        assert_eq!(
+            poseidon(&[&field(1), &field(2)]).unwrap(),
+            from_be_hex("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a")
+        );
+        assert_eq!(
+            compute_commitment(&Secret::from_bytes(field(1))).unwrap(),
+            Commitment::from_bytes(poseidon(&[&field(1)]).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_bytes_to_field_reduces() {
+        let mut modulus = [0u8; 32];
+        for (chunk, limb) in modulus.chunks_exact_mut(8).zip(MODULUS) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        assert_eq!(reduce_to_field(&modulus), [0u8; 32]);
+        assert_eq!(reduce_to_field(&field(7)), field(7));
+        // 2^256 - 1 is 5p plus this
+        assert_eq!(
+            reduce_to_field(&[0xff; 32]),
+            from_be_hex("0e0a77c19a07df2f666ea36f7879462e36fc76959f60cd29ac96341c4ffffffa")
+        );
+    }
+
+    #[test]
+    fn test_matches_prover_vectors() {
+        // the vectors light-poseidon gave zaffre_prover before it used this module; without
+        // `onchain` they check the light-poseidon path. the secret is above the modulus,
+        // so this also covers the reduction.
+        let secret = Secret::from_bytes([0xab; 32]);
+        let nullifier = compute_nullifier(&secret, &[1u8; 32], &[2u8; 32]).unwrap();
+        assert_eq!(
+            compute_commitment(&secret).unwrap().0,
+            from_hex("50bd18d5cba427878783f53ae4ba1c8efb99f3c4041292d8018656337eb4d000")
+        );
+        assert_eq!(
+            nullifier.0,
+            from_hex("23803275aaa3a62be0b6af19bb47112e2c68871669420f2b0b409494a4b08a11")
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! recovers the zaffre accounts of a master seed by re-deriving secrets index by index
+
+use solana_program::pubkey::Pubkey;
+
+use crate::error::ZaffreCoreError;
+use crate::pda::{derive_zaffre_pda, ZaffrePDA};
+use crate::poseidon::compute_commitment;
+use crate::seed::MasterSeed;
+
+/// consecutive unused indices after which `scan_zaffre_pdas` stops, as in bip-44
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// what the lookup found at a zaffre pda
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PdaUsage {
+    /// no account and no transaction ever touched the address
+    Unused,
+    /// no account, but the address has transaction history: it was rotated away or closed
+    Closed,
+    /// the account exists
+    Live,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScannedAccount {
+    pub index: u32,
+    pub pda: ZaffrePDA,
+}
+
+/// derives the secret, commitment and zaffre pda of `purpose` at indices 0, 1, .. and
+/// asks `lookup` how each pda was used, returning the live accounts. the scan stops after
+/// `gap_limit` consecutive `Unused` indices; closed ones do not count, since rotation
+/// closes the account at every index below the live one.
+///
+/// `lookup` is usually two rpc calls: `getMultipleAccounts` for `Live`, then
+/// `getSignaturesForAddress` with a limit of 1 to tell `Closed` from `Unused`.
+pub fn scan_zaffre_pdas<E>(
+    seed: &MasterSeed,
+    program_id: &Pubkey,
+    purpose: &str,
+    gap_limit: u32,
+    mut lookup: impl FnMut(&Pubkey) -> Result<PdaUsage, E>,
+) -> Result<Vec<ScannedAccount>, E>
+where
+    E: From<ZaffreCoreError>,
+{
+    let mut found = Vec::new();
+    let mut misses = 0;
+    let mut index = 0;
+    while misses < gap_limit {
+        let secret = seed.derive_secret(&program_id.to_bytes(), purpose, index);
+        let pda = derive_zaffre_pda(program_id, &compute_commitment(&secret)?);
+        match lookup(&pda.address)? {
+            PdaUsage::Unused => misses += 1,
+            PdaUsage::Closed => misses = 0,
+            PdaUsage::Live => {
+                found.push(ScannedAccount { index, pda });
+                misses = 0;
+            }
+        }
+        index = match index.checked_add(1) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn pda_at(seed: &MasterSeed, program_id: &Pubkey, index: u32) -> Pubkey {
+        let secret = seed.derive_secret(&program_id.to_bytes(), "ownership", index);
+        derive_zaffre_pda(program_id, &compute_commitment(&secret).unwrap()).address
+    }
+
+    fn indices(found: &[ScannedAccount]) -> Vec<u32> {
+        found.iter().map(|account| account.index).collect()
+    }
+
+    #[test]
+    fn test_scan_stops_at_gap_limit() {
+        let seed = MasterSeed::from_entropy(&[7u8; 32], "").unwrap();
+        let program_id = Pubkey::new_unique();
+        let usage: HashMap<Pubkey, PdaUsage> = [0, 2, 5, 9]
+            .into_iter()
+            .map(|index| (pda_at(&seed, &program_id, index), PdaUsage::Live))
+            .collect();
+        let lookup = |pda: &Pubkey| Ok(usage.get(pda).copied().unwrap_or(PdaUsage::Unused));
+
+        let mut lookups = 0;
+        let found =
+            scan_zaffre_pdas::<ZaffreCoreError>(&seed, &program_id, "ownership", 3, |pda| {
+                lookups += 1;
+                lookup(pda)
+            })
+            .unwrap();
+        // index 9 sits behind a gap of three (6, 7, 8) and is not reached
+        assert_eq!(indices(&found), vec![0, 2, 5]);
+        assert_eq!(found[1].pda.address, pda_at(&seed, &program_id, 2));
+        assert_eq!(lookups, 9);
+
+        let found =
+            scan_zaffre_pdas::<ZaffreCoreError>(&seed, &program_id, "membership", 3, lookup)
+                .unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_scan_continues_past_closed_indices() {
+        let seed = MasterSeed::from_entropy(&[7u8; 32], "").unwrap();
+        let program_id = Pubkey::new_unique();
+        // one account rotated from 0 to 6, a second one created at 7 and rotated to 11
+        let mut usage: HashMap<Pubkey, PdaUsage> = (0..11)
+            .filter(|index| *index != 6)
+            .map(|index| (pda_at(&seed, &program_id, index), PdaUsage::Closed))
+            .collect();
+        usage.insert(pda_at(&seed, &program_id, 6), PdaUsage::Live);
+        usage.insert(pda_at(&seed, &program_id, 11), PdaUsage::Live);
+
+        let found =
+            scan_zaffre_pdas::<ZaffreCoreError>(&seed, &program_id, "ownership", 3, |pda| {
+                Ok(usage.get(pda).copied().unwrap_or(PdaUsage::Unused))
+            })
+            .unwrap();
+        assert_eq!(indices(&found), vec![6, 11]);
+    }
+}
@@ -0,0 +1,181 @@
+//! deterministic secrets from one master seed, so a single backup recovers every
+//! commitment. the seed is a bip-39 mnemonic; each secret sits at a
+//! (program id, purpose, index) path below it.
+
+use bip39::{Language, Mnemonic};
+use getrandom::getrandom;
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use zeroize::Zeroize;
+
+use crate::error::ZaffreCoreError;
+use crate::types::Secret;
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// hmac key that turns a bip-39 seed into the zaffre master key, like bip-32's
+/// "Bitcoin seed"
+const MASTER_KEY_DOMAIN: &[u8] = b"zaffre master seed";
+/// prefix of every derivation path, bumped if the path encoding ever changes
+const SECRET_DOMAIN: &[u8] = b"zaffre-secret-v1";
+
+/// bytes of entropy behind a freshly generated 24-word mnemonic
+pub const MASTER_SEED_ENTROPY_BYTES: usize = 32;
+
+pub struct MasterSeed {
+    entropy: Vec<u8>,
+    key: [u8; 64],
+}
+
+impl MasterSeed {
+    /// a new seed backed by a 24-word mnemonic
+    pub fn generate() -> Self {
+        let mut entropy = [0u8; MASTER_SEED_ENTROPY_BYTES];
+        getrandom(&mut entropy).expect("getrandom failed");
+        let seed = Self::from_entropy(&entropy, "").expect("32 bytes is valid bip-39 entropy");
+        entropy.zeroize();
+        seed
+    }
+
+    /// `entropy` is 16 to 32 bytes in steps of 4, as in bip-39
+    pub fn from_entropy(entropy: &[u8], passphrase: &str) -> Result<Self, ZaffreCoreError> {
+        let mnemonic =
+            Mnemonic::from_entropy(entropy).map_err(|_| ZaffreCoreError::InvalidMnemonic)?;
+        Ok(Self::from_parsed(&mnemonic, passphrase))
+    }
+
+    /// imports an english bip-39 phrase. the passphrase is the optional bip-39 "25th
+    /// word"; it is not stored, so it must be supplied again on every import.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, ZaffreCoreError> {
+        let mnemonic = Mnemonic::parse_in_normalized(Language::English, phrase)
+            .map_err(|_| ZaffreCoreError::InvalidMnemonic)?;
+        Ok(Self::from_parsed(&mnemonic, passphrase))
+    }
+
+    fn from_parsed(mnemonic: &Mnemonic, passphrase: &str) -> Self {
+        let mut bip39_seed = mnemonic.to_seed_normalized(passphrase);
+        let mut mac = HmacSha512::new_from_slice(MASTER_KEY_DOMAIN).expect("hmac takes any key");
+        mac.update(&bip39_seed);
+        bip39_seed.zeroize();
+        Self {
+            entropy: mnemonic.to_entropy(),
+            key: mac.finalize().into_bytes().into(),
+        }
+    }
+
+    /// the english mnemonic to back up; it recovers every derived secret
+    pub fn mnemonic(&self) -> String {
+        Mnemonic::from_entropy(&self.entropy)
+            .expect("entropy was validated on construction")
+            .to_string()
+    }
+
+    /// the secret at `(program_id, purpose, index)`. `purpose` separates uses within a
+    /// program (e.g. "ownership" and "membership") and `index` counts rotations, so a
+    /// rotated account moves to the next index.
+    pub fn derive_secret(&self, program_id: &[u8; 32], purpose: &str, index: u32) -> Secret {
+        let mut mac = HmacSha512::new_from_slice(&self.key).expect("hmac takes any key");
+        mac.update(SECRET_DOMAIN);
+        mac.update(program_id);
+        mac.update(&(purpose.len() as u32).to_le_bytes());
+        mac.update(purpose.as_bytes());
+        mac.update(&index.to_le_bytes());
+        let mut output: [u8; 64] = mac.finalize().into_bytes().into();
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&output[..32]);
+        output.zeroize();
+        secret_from_uniform_bytes(bytes)
+    }
+}
+
+impl Drop for MasterSeed {
+    fn drop(&mut self) {
+        self.entropy.zeroize();
+        self.key.zeroize();
+    }
+}
+
+/// clears the top three bits of uniformly random little-endian bytes, so the secret is
+/// below 2^253 < p and stays uniform. every component then hashes the same canonical
+/// field element instead of relying on its own reduction.
+pub(crate) fn secret_from_uniform_bytes(mut bytes: [u8; 32]) -> Secret {
+    bytes[31] &= 0x1f;
+    let secret = Secret::from_bytes(bytes);
+    bytes.zeroize();
+    secret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                          abandon abandon abandon about";
+
+    #[test]
+    fn test_derive_secret_paths() {
+        let seed = MasterSeed::from_mnemonic(PHRASE, "").unwrap();
+        let secret = seed.derive_secret(&[1u8; 32], "ownership", 0);
+        assert_eq!(
+            secret.as_bytes(),
+            MasterSeed::from_mnemonic(PHRASE, "")
+                .unwrap()
+                .derive_secret(&[1u8; 32], "ownership", 0)
+                .as_bytes()
+        );
+        // pinned so the derivation can never change under an existing backup
+        assert_eq!(
+            secret.as_bytes(),
+            &[
+                0x1f, 0x60, 0x36, 0xc6, 0x9a, 0x47, 0x8b, 0x15, 0x24, 0x13, 0xab, 0xdb, 0x87, 0x25,
+                0xb8, 0xbe, 0x97, 0x12, 0x60, 0x24, 0x9f, 0xd7, 0x19, 0x98, 0x25, 0x01, 0x6f, 0x14,
+                0x2b, 0xa3, 0xd9, 0x00,
+            ]
+        );
+        assert!(secret.as_bytes()[31] < 0x20);
+
+        let others = [
+            seed.derive_secret(&[2u8; 32], "ownership", 0),
+            seed.derive_secret(&[1u8; 32], "membership", 0),
+            seed.derive_secret(&[1u8; 32], "ownership", 1),
+            MasterSeed::from_mnemonic(PHRASE, "passphrase")
+                .unwrap()
+                .derive_secret(&[1u8; 32], "ownership", 0),
+        ];
+        for other in &others {
+            assert_ne!(other.as_bytes(), secret.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_mnemonic_roundtrip() {
+        let seed = MasterSeed::generate();
+        let phrase = seed.mnemonic();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+
+        let restored = MasterSeed::from_mnemonic(&phrase, "").unwrap();
+        assert_eq!(restored.mnemonic(), phrase);
+        assert_eq!(
+            restored
+                .derive_secret(&[3u8; 32], "ownership", 7)
+                .as_bytes(),
+            seed.derive_secret(&[3u8; 32], "ownership", 7).as_bytes()
+        );
+
+        let from_entropy = MasterSeed::from_entropy(&[0u8; 16], "").unwrap();
+        assert_eq!(from_entropy.mnemonic(), PHRASE);
+    }
+
+    #[test]
+    fn test_invalid_mnemonic() {
+        let bad_checksum = PHRASE.replace("about", "abandon");
+        assert_eq!(
+            MasterSeed::from_mnemonic(&bad_checksum, "").err(),
+            Some(ZaffreCoreError::InvalidMnemonic)
+        );
+        assert_eq!(
+            MasterSeed::from_entropy(&[0u8; 15], "").err(),
+            Some(ZaffreCoreError::InvalidMnemonic)
+        );
+    }
+}
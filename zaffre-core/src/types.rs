@@ -73,6 +73,25 @@ impl AsRef<[u8]> for Nullifier {
     }
 }
 
+/// a 32-byte account address: a raw `[u8; 32]`, or a `Pubkey` with `onchain`. the action
+/// hash and pda helpers take either, so programs and clients share them.
+pub trait AddressBytes {
+    fn address_bytes(&self) -> [u8; 32];
+}
+
+impl AddressBytes for [u8; 32] {
+    fn address_bytes(&self) -> [u8; 32] {
+        *self
+    }
+}
+
+#[cfg(feature = "onchain")]
+impl AddressBytes for solana_program::pubkey::Pubkey {
+    fn address_bytes(&self) -> [u8; 32] {
+        self.to_bytes()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
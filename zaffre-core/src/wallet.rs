@@ -0,0 +1,140 @@
+//! secrets derived from a solana wallet signature, for users who would rather not keep
+//! another seed phrase.
+//!
+//! the wallet signs `wallet_derivation_message(program_id, purpose, index)` and the
+//! secret is a hash of that signature. this only recovers state if the wallet signs
+//! deterministically: plain ed25519 (rfc 8032) does, but some hardware, mpc and
+//! custodial wallets add randomness, so sign twice and compare before trusting the
+//! result. the threat model follows from the construction:
+//! - anyone holding the signature holds the secret, so it must never leave the client.
+//!   a site that gets the wallet to sign the same message can take over the accounts;
+//!   the message names zaffre and the program to make such a request recognizable.
+//! - whoever controls the wallet key can re-derive every secret, so the zaffre accounts
+//!   are only as safe as the wallet.
+//! - the derivation itself does not link the wallet to the commitment on-chain; only
+//!   using the same wallet to pay for zaffre transactions would.
+
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+use crate::error::ZaffreCoreError;
+use crate::seed::secret_from_uniform_bytes;
+use crate::types::Secret;
+
+/// first line of every derivation message, bumped if the format ever changes
+pub const WALLET_MESSAGE_HEADER: &str = "Zaffre secret derivation v1";
+pub const WALLET_SIGNATURE_BYTES: usize = 64;
+
+const WALLET_SECRET_DOMAIN: &[u8] = b"zaffre-wallet-secret-v1";
+const PURPOSE_MAX_LEN: usize = 64;
+
+/// the utf-8 message a wallet signs for the secret at `(program_id, purpose, index)`:
+///
+/// ```text
+/// Zaffre secret derivation v1
+/// program: <base58 program id>
+/// purpose: <purpose>
+/// index: <index>
+///
+/// Only sign this in an app you trust with your Zaffre accounts.
+/// ```
+///
+/// `purpose` is 1 to 64 bytes of printable ascii, so a path has exactly one message.
+pub fn wallet_derivation_message(
+    program_id: &[u8; 32],
+    purpose: &str,
+    index: u32,
+) -> Result<Vec<u8>, ZaffreCoreError> {
+    if purpose.is_empty()
+        || purpose.len() > PURPOSE_MAX_LEN
+        || !purpose.bytes().all(|b| b.is_ascii_graphic() || b == b' ')
+    {
+        return Err(ZaffreCoreError::InvalidDerivationPurpose);
+    }
+    Ok(format!(
+        "{WALLET_MESSAGE_HEADER}\nprogram: {}\npurpose: {purpose}\nindex: {index}\n\n\
+         Only sign this in an app you trust with your Zaffre accounts.",
+        bs58::encode(program_id).into_string()
+    )
+    .into_bytes())
+}
+
+/// the secret for `signature`, the wallet's ed25519 signature over
+/// `wallet_derivation_message(program_id, purpose, index)`. the signature is not
+/// verified here; a wrong or randomized one just yields a different secret. the output
+/// is masked below 2^253 like `MasterSeed` secrets, so it is a canonical bn254 element.
+pub fn secret_from_wallet_signature(
+    signature: &[u8; WALLET_SIGNATURE_BYTES],
+    program_id: &[u8; 32],
+    purpose: &str,
+    index: u32,
+) -> Result<Secret, ZaffreCoreError> {
+    let message = wallet_derivation_message(program_id, purpose, index)?;
+    let mut hasher = Sha512::new();
+    hasher.update(WALLET_SECRET_DOMAIN);
+    hasher.update((message.len() as u32).to_le_bytes());
+    hasher.update(&message);
+    hasher.update(signature);
+    let mut output: [u8; 64] = hasher.finalize().into();
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&output[..32]);
+    output.zeroize();
+    Ok(secret_from_uniform_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wallet_derivation_message() {
+        let message = wallet_derivation_message(&[0u8; 32], "ownership", 3).unwrap();
+        assert_eq!(
+            String::from_utf8(message).unwrap(),
+            "Zaffre secret derivation v1\n\
+             program: 11111111111111111111111111111111\n\
+             purpose: ownership\n\
+             index: 3\n\n\
+             Only sign this in an app you trust with your Zaffre accounts."
+        );
+
+        for purpose in ["", "two\nlines", "ünicode", &"x".repeat(65)] {
+            assert_eq!(
+                wallet_derivation_message(&[0u8; 32], purpose, 0),
+                Err(ZaffreCoreError::InvalidDerivationPurpose)
+            );
+        }
+    }
+
+    #[test]
+    fn test_secret_from_wallet_signature() {
+        let signature = [9u8; 64];
+        let secret = secret_from_wallet_signature(&signature, &[1u8; 32], "ownership", 0).unwrap();
+        assert_eq!(
+            secret.as_bytes(),
+            secret_from_wallet_signature(&signature, &[1u8; 32], "ownership", 0)
+                .unwrap()
+                .as_bytes()
+        );
+        // pinned: a changed derivation would silently lose every wallet-derived account
+        assert_eq!(
+            secret.as_bytes(),
+            &[
+                0xbc, 0x70, 0x3c, 0x73, 0xcc, 0xc6, 0x94, 0x9c, 0xb4, 0x8a, 0x12, 0x37, 0xb2, 0xa7,
+                0xf2, 0x3c, 0xdc, 0x24, 0x9d, 0x9c, 0xff, 0xcf, 0xdb, 0x48, 0x7b, 0x19, 0x6b, 0xa5,
+                0x79, 0xe9, 0xa7, 0x0e,
+            ]
+        );
+        assert!(secret.as_bytes()[31] < 0x20);
+
+        let others = [
+            secret_from_wallet_signature(&[8u8; 64], &[1u8; 32], "ownership", 0),
+            secret_from_wallet_signature(&signature, &[2u8; 32], "ownership", 0),
+            secret_from_wallet_signature(&signature, &[1u8; 32], "membership", 0),
+            secret_from_wallet_signature(&signature, &[1u8; 32], "ownership", 1),
+        ];
+        for other in others {
+            assert_ne!(other.unwrap().as_bytes(), secret.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! gnark public witness layout, shared by the programs, the prover and the js sdk
+
+use crate::error::ZaffreCoreError;
+
+pub const PUBLIC_INPUT_BYTES: usize = 160;
+/// each 32-byte value is exposed as two big-endian 128-bit limbs (hi, lo)
+pub const PUBLIC_INPUT_LIMB_BYTES: usize = 16;
+pub const PUBLIC_INPUT_COUNT: usize = PUBLIC_INPUT_BYTES / PUBLIC_INPUT_LIMB_BYTES;
+pub const PUBLIC_WITNESS_HEADER_LEN: usize = 12;
+pub const PUBLIC_WITNESS_ENTRY_LEN: usize = 32;
+pub const PUBLIC_WITNESS_SIZE: usize =
+    PUBLIC_WITNESS_HEADER_LEN + PUBLIC_INPUT_COUNT * PUBLIC_WITNESS_ENTRY_LEN;
+pub const OWNERSHIP_ACTION_PARAMS_MAX_LEN: usize = 32;
+/// membership circuit exposes (root, program_id, action_hash, nullifier)
+pub const MEMBERSHIP_PUBLIC_INPUT_BYTES: usize = 128;
+pub const MEMBERSHIP_PUBLIC_INPUT_COUNT: usize =
+    MEMBERSHIP_PUBLIC_INPUT_BYTES / PUBLIC_INPUT_LIMB_BYTES;
+pub const MEMBERSHIP_PUBLIC_WITNESS_SIZE: usize =
+    PUBLIC_WITNESS_HEADER_LEN + MEMBERSHIP_PUBLIC_INPUT_COUNT * PUBLIC_WITNESS_ENTRY_LEN;
+
+/// ownership circuit public values; `encoding` adds a pubkey constructor for on-chain use
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicInputs {
+    pub commitment: [u8; 32],
+    pub pda: [u8; 32],
+    pub program_id: [u8; 32],
+    pub action_hash: [u8; 32],
+    pub nullifier: [u8; 32],
+}
+
+impl PublicInputs {
+    pub fn from_bytes(bytes: &[u8; PUBLIC_INPUT_BYTES]) -> Self {
+        let field = |i: usize| bytes[i * 32..(i + 1) * 32].try_into().unwrap();
+        Self {
+            commitment: field(0),
+            pda: field(1),
+            program_id: field(2),
+            action_hash: field(3),
+            nullifier: field(4),
+        }
+    }
+
+    /// decodes an ownership public witness, saying which part of it is malformed
+    pub fn from_public_witness(public_witness: &[u8]) -> Result<Self, ZaffreCoreError> {
+        unpack_public_witness(public_witness).map(|raw| Self::from_bytes(&raw))
+    }
+
+    pub fn to_bytes(&self) -> [u8; PUBLIC_INPUT_BYTES] {
+        let mut result = [0u8; PUBLIC_INPUT_BYTES];
+        result[0..32].copy_from_slice(&self.commitment);
+        result[32..64].copy_from_slice(&self.pda);
+        result[64..96].copy_from_slice(&self.program_id);
+        result[96..128].copy_from_slice(&self.action_hash);
+        result[128..PUBLIC_INPUT_BYTES].copy_from_slice(&self.nullifier);
+        result
+    }
+
+    pub fn to_public_witness(&self) -> Vec<u8> {
+        pack_public_witness(&self.to_bytes())
+    }
+}
+
+/// packs concatenated 32-byte public values into a gnark public witness: a 12-byte
+/// header, then one 32-byte entry per 16-byte limb
+pub fn pack_public_witness(raw: &[u8]) -> Vec<u8> {
+    let count = (raw.len() / PUBLIC_INPUT_LIMB_BYTES) as u32;
+    let mut out = vec![0u8; PUBLIC_WITNESS_HEADER_LEN + count as usize * PUBLIC_WITNESS_ENTRY_LEN];
+    out[0..4].copy_from_slice(&count.to_be_bytes());
+    out[4..8].copy_from_slice(&0u32.to_be_bytes());
+    out[8..12].copy_from_slice(&count.to_be_bytes());
+
+    // gnark encodes each field element as 32 big-endian bytes, so a 16-byte limb
+    // occupies the low half of its entry and the high half stays zero
+    let entry_pad = PUBLIC_WITNESS_ENTRY_LEN - PUBLIC_INPUT_LIMB_BYTES;
+    let mut offset = PUBLIC_WITNESS_HEADER_LEN;
+    for limb in raw.chunks_exact(PUBLIC_INPUT_LIMB_BYTES) {
+        out[offset + entry_pad..offset + PUBLIC_WITNESS_ENTRY_LEN].copy_from_slice(limb);
+        offset += PUBLIC_WITNESS_ENTRY_LEN;
+    }
+    out
+}
+
+/// inverse of `pack_public_witness` for `N` bytes of public values
+pub fn unpack_public_witness<const N: usize>(
+    public_witness: &[u8],
+) -> Result<[u8; N], ZaffreCoreError> {
+    let count = N / PUBLIC_INPUT_LIMB_BYTES;
+    let expected = PUBLIC_WITNESS_HEADER_LEN + count * PUBLIC_WITNESS_ENTRY_LEN;
+    if public_witness.len() != expected {
+        return Err(ZaffreCoreError::InvalidPublicWitnessLength {
+            expected,
+            actual: public_witness.len(),
+        });
+    }
+
+    let header_word = |i: usize| {
+        let bytes: [u8; 4] = public_witness[i * 4..i * 4 + 4].try_into().unwrap();
+        u32::from_be_bytes(bytes) as usize
+    };
+    if header_word(0) != count || header_word(1) != 0 || header_word(2) != count {
+        return Err(ZaffreCoreError::InvalidPublicWitnessHeader { expected: count });
+    }
+
+    let entry_pad = PUBLIC_WITNESS_ENTRY_LEN - PUBLIC_INPUT_LIMB_BYTES;
+    let mut raw = [0u8; N];
+    let mut offset = PUBLIC_WITNESS_HEADER_LEN;
+    for (index, limb) in raw.chunks_exact_mut(PUBLIC_INPUT_LIMB_BYTES).enumerate() {
+        let entry = &public_witness[offset..offset + PUBLIC_WITNESS_ENTRY_LEN];
+        if entry[..entry_pad].iter().any(|b| *b != 0) {
+            return Err(ZaffreCoreError::NonCanonicalPublicInput { index });
+        }
+        limb.copy_from_slice(&entry[entry_pad..]);
+        offset += PUBLIC_WITNESS_ENTRY_LEN;
+    }
+    Ok(raw)
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "onchain")]
+
 use solana_program::pubkey::Pubkey;
 use zaffre_core::*;
 
@@ -91,3 +93,22 @@ fn test_domain_separator() {
     let sep3 = compute_domain_separator(&other);
     assert_ne!(sep1, sep3);
 }
+
+#[test]
+fn test_action_hash_params_digest_allows_long_params() {
+    let program_id = Pubkey::new_unique();
+    let pda = Pubkey::new_unique();
+    let discriminator = *b"transfer";
+    let params = [3u8; OWNERSHIP_ACTION_PARAMS_MAX_LEN * 4];
+
+    let hash1 =
+        compute_action_hash_with_params_digest(&program_id, &pda, &discriminator, &params, 1);
+    let hash2 = compute_action_hash(
+        &program_id,
+        &pda,
+        &discriminator,
+        &compute_action_params_digest(&params),
+        1,
+    );
+    assert_eq!(hash1, hash2);
+}
@@ -0,0 +1,15 @@
+//! discriminator parsing shared by zaffre-derive and zaffre-anchor-macros, which includes
+//! this file, so the derive and the attribute pad the same way
+
+use syn::{Error, LitByteStr};
+
+/// shorter discriminators are padded with nul bytes, like `rotate\0\0`
+pub(crate) fn pad_discriminator(lit: &LitByteStr) -> syn::Result<[u8; 8]> {
+    let bytes = lit.value();
+    if bytes.is_empty() || bytes.len() > 8 {
+        return Err(Error::new(lit.span(), "discriminator must be 1 to 8 bytes"));
+    }
+    let mut discriminator = [0u8; 8];
+    discriminator[..bytes.len()].copy_from_slice(&bytes);
+    Ok(discriminator)
+}
@@ -0,0 +1,139 @@
+//! derive macro for `zaffre_core::ZaffreAction`, re-exported by zaffre-core
+
+mod discriminator;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Index, LitByteStr, Path};
+
+use crate::discriminator::pad_discriminator;
+
+/// implements `ZaffreAction` for a struct whose fields are the action params:
+///
+/// ```ignore
+/// #[derive(ZaffreAction)]
+/// #[zaffre(discriminator = b"setvalue")]
+/// pub struct SetValueAction {
+///     pub value: u64,
+/// }
+/// ```
+///
+/// fields are borsh-encoded in declaration order, so the params are the same bytes as
+/// the instruction arguments. `#[zaffre(params_digest)]` hashes them with the params
+/// digest scheme, and `#[zaffre(crate = path)]` names the crate that defines the trait
+/// (default `::zaffre_core`). shorter discriminators are padded with nul bytes.
+#[proc_macro_derive(ZaffreAction, attributes(zaffre))]
+pub fn derive_zaffre_action(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut discriminator = None;
+    let mut params_digest = false;
+    let mut krate: Path = syn::parse_quote!(::zaffre_core);
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("zaffre"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("discriminator") {
+                let lit: LitByteStr = meta.value()?.parse()?;
+                discriminator = Some(pad_discriminator(&lit)?);
+            } else if meta.path.is_ident("params_digest") {
+                params_digest = true;
+            } else if meta.path.is_ident("crate") {
+                krate = meta.value()?.parse()?;
+            } else {
+                return Err(meta.error("unsupported zaffre option"));
+            }
+            Ok(())
+        })?;
+    }
+    let discriminator = discriminator.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "ZaffreAction needs #[zaffre(discriminator = b\"...\")]",
+        )
+    })?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "ZaffreAction can only be derived for structs",
+        ));
+    };
+    let fields: Vec<TokenStream2> = match &data.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let ident = &field.ident;
+                quote!(#ident)
+            })
+            .collect(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|i| {
+                let index = Index::from(i);
+                quote!(#index)
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::ZaffreAction for #ident #ty_generics #where_clause {
+            const DISCRIMINATOR: [u8; 8] = [#(#discriminator),*];
+            const PARAMS_DIGEST: bool = #params_digest;
+
+            fn action_params(&self) -> ::std::vec::Vec<u8> {
+                #[allow(unused_mut)]
+                let mut params = ::std::vec::Vec::new();
+                #(
+                    #krate::borsh::BorshSerialize::serialize(&self.#fields, &mut params)
+                        .expect("writing to a vec does not fail");
+                )*
+                params
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_expand_options() {
+        let input: DeriveInput = parse_quote! {
+            #[zaffre(discriminator = b"memo", params_digest, crate = crate)]
+            struct Memo(u64, String);
+        };
+        let tokens = expand(input).unwrap().to_string();
+        assert!(tokens.contains("impl crate :: ZaffreAction for Memo"));
+        assert!(tokens.contains("const PARAMS_DIGEST : bool = true"));
+        assert!(tokens.contains("self . 1"));
+
+        let missing: DeriveInput = parse_quote!(
+            struct Action;
+        );
+        assert!(expand(missing).is_err());
+        let long: DeriveInput = parse_quote! {
+            #[zaffre(discriminator = b"toolongdisc")]
+            struct Action;
+        };
+        assert!(expand(long).is_err());
+        let enumeration: DeriveInput = parse_quote! {
+            #[zaffre(discriminator = b"enum")]
+            enum Action { A }
+        };
+        assert!(expand(enumeration).is_err());
+    }
+}
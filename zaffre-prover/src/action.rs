@@ -0,0 +1,3 @@
+//! typed zaffre actions and their derive, shared with zaffre-core
+
+pub use zaffre_core::{CloseAction, RotateAction, ZaffreAction};
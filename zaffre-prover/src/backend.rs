@@ -0,0 +1,560 @@
+//! pluggable proving backends
+
+use std::path::{Path, PathBuf};
+#[cfg(feature = "remote")]
+use std::time::Duration;
+
+use ark_bn254::{Fr, G1Affine, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::PrimeField;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zaffre_core::ZaffreAction;
+
+use crate::commitment::{compute_action_hash, compute_commitment, compute_nullifier};
+use crate::gnark::{encode_public_witness, public_inputs_from_values, GnarkProof};
+use crate::proof::{prove_ownership, ProofResult, ProverError, ToolPaths, ACTION_PARAMS_MAX_LEN};
+use crate::types::{Commitment, Nullifier, Secret};
+use crate::verify::PublicInputs;
+
+/// everything the ownership circuit needs for one action. `new` derives the public
+/// values from the secret; the fields stay public so callers can prove precomputed ones.
+#[derive(Clone)]
+pub struct ProofRequest {
+    pub secret: Secret,
+    pub commitment: Commitment,
+    pub pda: [u8; 32],
+    pub program_id: [u8; 32],
+    pub action_hash: [u8; 32],
+    pub nullifier: Nullifier,
+    pub action_discriminator: [u8; 8],
+    pub action_params: Vec<u8>,
+    pub nonce: u64,
+}
+
+impl ProofRequest {
+    pub fn new(
+        secret: Secret,
+        program_id: [u8; 32],
+        pda: [u8; 32],
+        action_discriminator: [u8; 8],
+        action_params: &[u8],
+        nonce: u64,
+    ) -> Result<Self, ProverError> {
+        if action_params.len() > ACTION_PARAMS_MAX_LEN {
+            return Err(ProverError::ActionParamsTooLong(action_params.len()));
+        }
+        let commitment = compute_commitment(&secret);
+        let action_hash = compute_action_hash(
+            &program_id,
+            &pda,
+            &action_discriminator,
+            action_params,
+            nonce,
+        );
+        let nullifier = compute_nullifier(&secret, &program_id, &action_hash);
+        Ok(Self {
+            secret,
+            commitment,
+            pda,
+            program_id,
+            action_hash,
+            nullifier,
+            action_discriminator,
+            action_params: action_params.to_vec(),
+            nonce,
+        })
+    }
+
+    /// `new` with the discriminator and params of a typed action, the same
+    /// `zaffre_core::ZaffreAction` impl the program hashes with. params-digest actions
+    /// prove the digest as their action params.
+    pub fn for_action<A: ZaffreAction>(
+        secret: Secret,
+        program_id: [u8; 32],
+        pda: [u8; 32],
+        action: &A,
+        nonce: u64,
+    ) -> Result<Self, ProverError> {
+        Self::new(
+            secret,
+            program_id,
+            pda,
+            A::DISCRIMINATOR,
+            &action.circuit_params(),
+            nonce,
+        )
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        public_inputs_from_values(&[
+            self.commitment.as_bytes(),
+            &self.pda,
+            &self.program_id,
+            &self.action_hash,
+            self.nullifier.as_bytes(),
+        ])
+    }
+
+    pub fn public_values(&self) -> PublicInputs {
+        PublicInputs {
+            commitment: *self.commitment.as_bytes(),
+            pda: self.pda,
+            program_id: self.program_id,
+            action_hash: self.action_hash,
+            nullifier: *self.nullifier.as_bytes(),
+        }
+    }
+
+    /// the gnark public witness any backend must return for this request
+    pub fn public_witness(&self) -> Vec<u8> {
+        encode_public_witness(&self.public_inputs())
+    }
+
+    /// checks the constraints the circuit enforces, without proving
+    pub fn validate(&self) -> Result<(), ProverError> {
+        if self.action_params.len() > ACTION_PARAMS_MAX_LEN {
+            return Err(ProverError::ActionParamsTooLong(self.action_params.len()));
+        }
+        if compute_commitment(&self.secret) != self.commitment {
+            return Err(ProverError::InvalidRequest("commitment mismatch"));
+        }
+        let action_hash = compute_action_hash(
+            &self.program_id,
+            &self.pda,
+            &self.action_discriminator,
+            &self.action_params,
+            self.nonce,
+        );
+        if action_hash != self.action_hash {
+            return Err(ProverError::InvalidRequest("action hash mismatch"));
+        }
+        if compute_nullifier(&self.secret, &self.program_id, &self.action_hash) != self.nullifier {
+            return Err(ProverError::InvalidRequest("nullifier mismatch"));
+        }
+        Ok(())
+    }
+}
+
+pub trait ProverBackend {
+    fn prove(&self, request: &ProofRequest) -> Result<ProofResult, ProverError>;
+}
+
+/// local nargo + sunspot pipeline over a circuit package
+#[derive(Clone, Debug)]
+pub struct NargoSunspotBackend {
+    circuit_dir: PathBuf,
+    tools: ToolPaths,
+}
+
+impl NargoSunspotBackend {
+    /// uses `ToolPaths::default()`, read once here
+    pub fn new(circuit_dir: impl Into<PathBuf>) -> Self {
+        Self::with_tools(circuit_dir, ToolPaths::default())
+    }
+
+    pub fn with_tools(circuit_dir: impl Into<PathBuf>, tools: ToolPaths) -> Self {
+        Self {
+            circuit_dir: circuit_dir.into(),
+            tools,
+        }
+    }
+
+    pub fn circuit_dir(&self) -> &Path {
+        &self.circuit_dir
+    }
+
+    pub fn tools(&self) -> &ToolPaths {
+        &self.tools
+    }
+}
+
+impl ProverBackend for NargoSunspotBackend {
+    fn prove(&self, request: &ProofRequest) -> Result<ProofResult, ProverError> {
+        prove_ownership(request, &self.circuit_dir, &self.tools)
+    }
+}
+
+/// deterministic backend for tests. it enforces the circuit's constraints natively and
+/// returns the real public witness with a well-formed proof that does not verify.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MockBackend;
+
+impl ProverBackend for MockBackend {
+    fn prove(&self, request: &ProofRequest) -> Result<ProofResult, ProverError> {
+        request.validate()?;
+        let public_witness = request.public_witness();
+        let scalar = |tag: &[u8]| {
+            let mut hasher = Sha256::new();
+            hasher.update(b"zaffre-mock-proof");
+            hasher.update(tag);
+            hasher.update(&public_witness);
+            Fr::from_be_bytes_mod_order(&hasher.finalize())
+        };
+        let g1 = |tag: &[u8]| (G1Affine::generator() * scalar(tag)).into_affine();
+        let proof = GnarkProof {
+            ar: g1(b"ar"),
+            bs: (G2Affine::generator() * scalar(b"bs")).into_affine(),
+            krs: g1(b"krs"),
+            commitments: vec![g1(b"commitment")],
+            commitment_pok: g1(b"pok"),
+        };
+        Ok(ProofResult {
+            proof: proof.to_bytes(),
+            public_witness,
+        })
+    }
+}
+
+/// prover service over http. the request, including the secret, is posted as json to
+/// the endpoint, so only use a service you trust with the secret, over tls.
+#[cfg(feature = "remote")]
+#[derive(Clone, Debug)]
+pub struct RemoteBackend {
+    endpoint: String,
+    agent: ureq::Agent,
+}
+
+#[cfg(feature = "remote")]
+impl RemoteBackend {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self::with_timeout(endpoint, Self::DEFAULT_TIMEOUT)
+    }
+
+    pub fn with_timeout(endpoint: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+#[cfg(feature = "remote")]
+impl ProverBackend for RemoteBackend {
+    fn prove(&self, request: &ProofRequest) -> Result<ProofResult, ProverError> {
+        let response = self
+            .agent
+            .post(&self.endpoint)
+            .send_json(RemoteProofRequest::from(request))
+            .map_err(|e| ProverError::Remote(e.to_string()))?;
+        let response: RemoteProofResponse = response
+            .into_json()
+            .map_err(|e| ProverError::Remote(format!("invalid response: {e}")))?;
+        let decode = |field: &str, value: &str| {
+            hex::decode(value).map_err(|_| ProverError::Remote(format!("{field} must be hex")))
+        };
+        let proof = decode("proof", &response.proof)?;
+        let public_witness = decode("public_witness", &response.public_witness)?;
+
+        // the service is not trusted to pick the statement
+        if public_witness != request.public_witness() {
+            return Err(ProverError::PublicWitnessMismatch);
+        }
+        GnarkProof::from_bytes(&proof)?;
+
+        Ok(ProofResult {
+            proof,
+            public_witness,
+        })
+    }
+}
+
+/// json body posted by `RemoteBackend`, with byte fields hex-encoded
+#[derive(Serialize, Deserialize)]
+pub struct RemoteProofRequest {
+    pub secret: String,
+    pub commitment: String,
+    pub pda: String,
+    pub program_id: String,
+    pub action_hash: String,
+    pub nullifier: String,
+    pub action_discriminator: String,
+    pub action_params: String,
+    pub nonce: u64,
+}
+
+impl From<&ProofRequest> for RemoteProofRequest {
+    fn from(request: &ProofRequest) -> Self {
+        Self {
+            secret: hex::encode(request.secret.as_bytes()),
+            commitment: hex::encode(request.commitment.as_bytes()),
+            pda: hex::encode(request.pda),
+            program_id: hex::encode(request.program_id),
+            action_hash: hex::encode(request.action_hash),
+            nullifier: hex::encode(request.nullifier.as_bytes()),
+            action_discriminator: hex::encode(request.action_discriminator),
+            action_params: hex::encode(&request.action_params),
+            nonce: request.nonce,
+        }
+    }
+}
+
+impl TryFrom<&RemoteProofRequest> for ProofRequest {
+    type Error = ProverError;
+
+    fn try_from(body: &RemoteProofRequest) -> Result<Self, ProverError> {
+        fn fixed<const N: usize>(field: &'static str, value: &str) -> Result<[u8; N], ProverError> {
+            hex::decode(value)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or(ProverError::InvalidRequest(field))
+        }
+        let action_params = hex::decode(&body.action_params)
+            .map_err(|_| ProverError::InvalidRequest("action_params"))?;
+        if action_params.len() > ACTION_PARAMS_MAX_LEN {
+            return Err(ProverError::ActionParamsTooLong(action_params.len()));
+        }
+        Ok(Self {
+            secret: Secret::from_bytes(fixed("secret", &body.secret)?),
+            commitment: Commitment::from_bytes(fixed("commitment", &body.commitment)?),
+            pda: fixed("pda", &body.pda)?,
+            program_id: fixed("program_id", &body.program_id)?,
+            action_hash: fixed("action_hash", &body.action_hash)?,
+            nullifier: Nullifier::from_bytes(fixed("nullifier", &body.nullifier)?),
+            action_discriminator: fixed("action_discriminator", &body.action_discriminator)?,
+            action_params,
+            nonce: body.nonce,
+        })
+    }
+}
+
+/// json body returned by a prover service, with byte fields hex-encoded
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteProofResponse {
+    pub proof: String,
+    pub public_witness: String,
+}
+
+impl From<&ProofResult> for RemoteProofResponse {
+    fn from(result: &ProofResult) -> Self {
+        Self {
+            proof: hex::encode(&result.proof),
+            public_witness: hex::encode(&result.public_witness),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> ProofRequest {
+        ProofRequest::new(
+            Secret::from_bytes([7u8; 32]),
+            [3u8; 32],
+            [4u8; 32],
+            *b"setvalue",
+            &123u64.to_le_bytes(),
+            42,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_request_derives_public_values() {
+        let request = request();
+        assert_eq!(request.commitment, compute_commitment(&request.secret));
+        assert_eq!(
+            request.nullifier,
+            compute_nullifier(&request.secret, &request.program_id, &request.action_hash)
+        );
+        assert_eq!(request.public_inputs().len(), 10);
+        assert!(request.validate().is_ok());
+
+        assert!(matches!(
+            ProofRequest::new(
+                Secret::from_bytes([7u8; 32]),
+                [3u8; 32],
+                [4u8; 32],
+                *b"setvalue",
+                &[0u8; 33],
+                1
+            ),
+            Err(ProverError::ActionParamsTooLong(33))
+        ));
+    }
+
+    #[test]
+    fn test_request_for_typed_action() {
+        let secret = Secret::from_bytes([7u8; 32]);
+        let rotate = crate::RotateAction {
+            new_commitment: [5u8; 32],
+        };
+        let typed =
+            ProofRequest::for_action(secret.clone(), [3u8; 32], [4u8; 32], &rotate, 1).unwrap();
+        let raw =
+            ProofRequest::new(secret, [3u8; 32], [4u8; 32], *b"rotate\0\0", &[5u8; 32], 1).unwrap();
+        assert_eq!(typed.action_hash, raw.action_hash);
+        assert_eq!(
+            typed.action_hash,
+            rotate.action_hash(&[3u8; 32], &[4u8; 32], 1)
+        );
+    }
+
+    #[test]
+    fn test_mock_backend_is_deterministic() {
+        let request = request();
+        let first = MockBackend.prove(&request).unwrap();
+        let second = MockBackend.prove(&request).unwrap();
+        assert_eq!(first.proof, second.proof);
+        assert_eq!(first.public_witness, request.public_witness());
+        assert_eq!(
+            GnarkProof::from_bytes(&first.proof).unwrap().to_bytes(),
+            first.proof
+        );
+
+        let mut other = request.clone();
+        other.nonce += 1;
+        other.action_hash = compute_action_hash(
+            &other.program_id,
+            &other.pda,
+            &other.action_discriminator,
+            &other.action_params,
+            other.nonce,
+        );
+        other.nullifier = compute_nullifier(&other.secret, &other.program_id, &other.action_hash);
+        assert_ne!(MockBackend.prove(&other).unwrap().proof, first.proof);
+    }
+
+    #[test]
+    fn test_mock_backend_rejects_unsatisfiable_request() {
+        let mut request = request();
+        request.nonce += 1;
+        assert!(matches!(
+            MockBackend.prove(&request),
+            Err(ProverError::InvalidRequest("action hash mismatch"))
+        ));
+
+        let mut request = self::request();
+        request.commitment = Commitment::from_bytes([1u8; 32]);
+        assert!(matches!(
+            MockBackend.prove(&request),
+            Err(ProverError::InvalidRequest("commitment mismatch"))
+        ));
+    }
+
+    #[test]
+    fn test_remote_request_roundtrip() {
+        let request = request();
+        let body = RemoteProofRequest::from(&request);
+        let decoded = ProofRequest::try_from(&body).unwrap();
+        assert_eq!(decoded.public_witness(), request.public_witness());
+        assert_eq!(decoded.secret.as_bytes(), request.secret.as_bytes());
+
+        let bad = RemoteProofRequest {
+            pda: "00".into(),
+            ..body
+        };
+        assert!(matches!(
+            ProofRequest::try_from(&bad),
+            Err(ProverError::InvalidRequest("pda"))
+        ));
+    }
+
+    #[cfg(feature = "remote")]
+    mod remote {
+        use super::*;
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+        use std::thread::JoinHandle;
+
+        // minimal single-request http/1.1 stand-in for a prover service
+        fn serve_once<F>(respond: F) -> (String, JoinHandle<()>)
+        where
+            F: FnOnce(RemoteProofRequest) -> (u16, String) + Send + 'static,
+        {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/prove", listener.local_addr().unwrap());
+            let handle = std::thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let (status, response) = respond(serde_json::from_slice(&body).unwrap());
+
+                let mut stream = stream;
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            });
+            (url, handle)
+        }
+
+        fn mock_service(body: RemoteProofRequest) -> (u16, String) {
+            let request = ProofRequest::try_from(&body).unwrap();
+            match MockBackend.prove(&request) {
+                Ok(result) => (
+                    200,
+                    serde_json::to_string(&RemoteProofResponse::from(&result)).unwrap(),
+                ),
+                Err(e) => (422, format!("{{\"error\":\"{e}\"}}")),
+            }
+        }
+
+        #[test]
+        fn test_remote_backend_matches_local_mock() {
+            let request = request();
+            let (url, handle) = serve_once(mock_service);
+            let result = RemoteBackend::new(url).prove(&request).unwrap();
+            handle.join().unwrap();
+
+            let expected = MockBackend.prove(&request).unwrap();
+            assert_eq!(result.proof, expected.proof);
+            assert_eq!(result.public_witness, expected.public_witness);
+        }
+
+        #[test]
+        fn test_remote_backend_surfaces_service_errors() {
+            let mut request = request();
+            request.nonce += 1;
+            let (url, handle) = serve_once(mock_service);
+            let err = RemoteBackend::new(url).prove(&request).unwrap_err();
+            handle.join().unwrap();
+            assert!(matches!(err, ProverError::Remote(_)), "{err:?}");
+        }
+
+        #[test]
+        fn test_remote_backend_rejects_other_statement() {
+            let request = request();
+            let (url, handle) = serve_once(|body| {
+                let request = ProofRequest::try_from(&body).unwrap();
+                let other = ProofRequest::new(
+                    request.secret.clone(),
+                    request.program_id,
+                    [9u8; 32],
+                    request.action_discriminator,
+                    &request.action_params,
+                    request.nonce,
+                )
+                .unwrap();
+                mock_service(RemoteProofRequest::from(&other))
+            });
+            let err = RemoteBackend::new(url).prove(&request).unwrap_err();
+            handle.join().unwrap();
+            assert!(matches!(err, ProverError::PublicWitnessMismatch), "{err:?}");
+        }
+    }
+}
@@ -0,0 +1,821 @@
+//! zaffre command line: secrets, commitments, pdas, action hashes and proofs
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+use zaffre_core::OWNERSHIP_ACTION_PARAMS_MAX_LEN;
+use zeroize::Zeroizing;
+
+#[cfg(feature = "remote")]
+use zaffre_prover::RemoteBackend;
+use zaffre_prover::{
+    compute_action_hash, compute_action_params_digest, compute_commitment, compute_nullifier,
+    decode_public_witness, derive_zaffre_pda, read_circuit_name, values_from_public_inputs,
+    verify_ownership_proof, verify_proof, Commitment, Keystore, KeystoreEntry, NargoSunspotBackend,
+    ProofRequest, ProverBackend, ProverError, PublicInputs, Secret, VerifyingKey,
+};
+
+const KEYSTORE_PASSWORD_ENV: &str = "ZAFFRE_KEYSTORE_PASSWORD";
+
+#[derive(Parser)]
+#[command(name = "zaffre", version, about = "Zaffre unlinkable PDA tooling")]
+struct Cli {
+    /// print the result as a json object
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// manage secrets
+    #[command(subcommand)]
+    Secret(SecretCommand),
+    /// manage an encrypted keystore of secrets
+    #[command(subcommand)]
+    Keystore(KeystoreCommand),
+    /// compute the commitment Poseidon(secret)
+    Commit {
+        #[command(flatten)]
+        secret: SecretArg,
+    },
+    /// derive the zaffre state PDA of a commitment
+    DerivePda {
+        #[arg(long, value_parser = parse_pubkey)]
+        program_id: [u8; 32],
+        /// commitment as hex; derived from the secret if omitted
+        #[arg(long, value_parser = parse_bytes32)]
+        commitment: Option<[u8; 32]>,
+        /// secret as hex
+        #[arg(long, env = "ZAFFRE_SECRET", hide_env_values = true, value_parser = parse_bytes32)]
+        secret: Option<[u8; 32]>,
+    },
+    /// compute the action hash bound by a proof
+    ActionHash {
+        #[arg(long, value_parser = parse_pubkey)]
+        program_id: [u8; 32],
+        #[arg(long, value_parser = parse_pubkey)]
+        pda: [u8; 32],
+        #[command(flatten)]
+        action: ActionArgs,
+    },
+    /// compute the nullifier of a secret for an action
+    Nullifier {
+        #[command(flatten)]
+        secret: SecretArg,
+        #[arg(long, value_parser = parse_pubkey)]
+        program_id: [u8; 32],
+        #[arg(long, value_parser = parse_bytes32)]
+        action_hash: [u8; 32],
+    },
+    /// generate an ownership proof
+    Prove(ProveArgs),
+    /// verify a proof against a gnark verifying key
+    Verify {
+        #[arg(long)]
+        vk: PathBuf,
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long)]
+        public_witness: PathBuf,
+    },
+    /// decode a gnark public witness file
+    InspectWitness { path: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum SecretCommand {
+    /// generate a random secret and its commitment
+    New,
+}
+
+#[derive(Subcommand)]
+enum KeystoreCommand {
+    /// create an empty keystore file
+    Create {
+        #[command(flatten)]
+        keystore: KeystorePath,
+    },
+    /// store a new random secret, or the one in --secret / ZAFFRE_SECRET with --import
+    Add {
+        #[command(flatten)]
+        keystore: KeystorePath,
+        #[arg(long)]
+        label: String,
+        #[arg(long, value_parser = parse_pubkey)]
+        program_id: [u8; 32],
+        /// position in the secret's rotation history
+        #[arg(long, default_value_t = 0)]
+        rotation_index: u32,
+        #[arg(long, requires = "secret")]
+        import: bool,
+        #[arg(long, env = "ZAFFRE_SECRET", hide_env_values = true, value_parser = parse_bytes32)]
+        secret: Option<[u8; 32]>,
+    },
+    /// check the password and count the entries
+    Unlock {
+        #[command(flatten)]
+        keystore: KeystorePath,
+    },
+    /// list labels, program ids, commitments and rotation indices, without secrets
+    List {
+        #[command(flatten)]
+        keystore: KeystorePath,
+    },
+    /// print the secret of one entry
+    Export {
+        #[command(flatten)]
+        keystore: KeystorePath,
+        #[arg(long)]
+        label: String,
+    },
+}
+
+#[derive(Args)]
+struct KeystorePath {
+    /// keystore file; the password is read from ZAFFRE_KEYSTORE_PASSWORD or prompted for
+    #[arg(long, env = "ZAFFRE_KEYSTORE")]
+    keystore: PathBuf,
+}
+
+#[derive(Args)]
+struct SecretArg {
+    /// secret as hex; prefer a keystore entry or the environment variable to keep it out
+    /// of shell history
+    #[arg(long, env = "ZAFFRE_SECRET", hide_env_values = true, value_parser = parse_bytes32)]
+    secret: Option<[u8; 32]>,
+    /// take the secret from this keystore entry instead
+    #[arg(long, requires = "keystore")]
+    label: Option<String>,
+    #[arg(long, env = "ZAFFRE_KEYSTORE")]
+    keystore: Option<PathBuf>,
+}
+
+impl SecretArg {
+    /// an explicit `--label` wins over a secret from the environment
+    fn resolve(&self) -> Result<Secret, CliError> {
+        match (&self.label, &self.keystore, self.secret) {
+            (Some(label), Some(path), _) => {
+                let keystore = Keystore::open(path, read_password(false)?.as_bytes())?;
+                Ok(keystore.get(label)?.secret.clone())
+            }
+            (_, _, Some(secret)) => Ok(Secret::from_bytes(secret)),
+            _ => Err(CliError::Invalid(
+                "pass --secret, set ZAFFRE_SECRET, or use --keystore with --label".into(),
+            )),
+        }
+    }
+}
+
+#[derive(Args)]
+struct ActionArgs {
+    /// 8-byte discriminator as ascii (zero-padded) or 0x-prefixed hex
+    #[arg(long, default_value = "setvalue", value_parser = parse_discriminator)]
+    discriminator: [u8; 8],
+    /// action params as hex
+    #[arg(long, value_parser = parse_hex, conflicts_with = "value")]
+    params: Option<Vec<u8>>,
+    /// u64 value encoded little-endian as the params, as in the example program's set_value
+    #[arg(long)]
+    value: Option<u64>,
+    /// pass SHA256(params) as the params, for the params digest scheme
+    #[arg(long)]
+    digest: bool,
+    #[arg(long)]
+    nonce: u64,
+}
+
+impl ActionArgs {
+    fn params(&self) -> Result<Vec<u8>, CliError> {
+        let params = match (&self.params, self.value) {
+            (Some(params), _) => params.clone(),
+            (None, Some(value)) => value.to_le_bytes().to_vec(),
+            (None, None) => Vec::new(),
+        };
+        let params = if self.digest {
+            compute_action_params_digest(&params).to_vec()
+        } else {
+            params
+        };
+        if params.len() > OWNERSHIP_ACTION_PARAMS_MAX_LEN {
+            return Err(CliError::Invalid(format!(
+                "action params are {} bytes, the limit is {}; use --digest",
+                params.len(),
+                OWNERSHIP_ACTION_PARAMS_MAX_LEN
+            )));
+        }
+        Ok(params)
+    }
+}
+
+#[derive(Args)]
+struct ProveArgs {
+    #[command(flatten)]
+    secret: SecretArg,
+    #[arg(long, value_parser = parse_pubkey)]
+    program_id: [u8; 32],
+    /// target account; defaults to the zaffre PDA of the secret's commitment
+    #[arg(long, value_parser = parse_pubkey)]
+    pda: Option<[u8; 32]>,
+    #[command(flatten)]
+    action: ActionArgs,
+    /// noir package of the ownership circuit
+    #[arg(long, default_value = "circuits/ownership")]
+    circuit: PathBuf,
+    /// directory for the .proof and .pw files; defaults to <circuit>/target
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+    /// verifying key to check the proof with; defaults to <circuit>/target/<name>.vk
+    #[arg(long)]
+    vk: Option<PathBuf>,
+    /// skip the host-side verification
+    #[arg(long)]
+    no_verify: bool,
+    /// prove with a remote prover service instead of local nargo + sunspot
+    #[cfg(feature = "remote")]
+    #[arg(long)]
+    remote: Option<String>,
+}
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+    #[error("failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// ordered result fields, printed as key=value lines or one json object
+#[derive(Default)]
+struct Output(Vec<(&'static str, Value)>);
+
+impl Output {
+    fn field(mut self, key: &'static str, value: impl Into<Value>) -> Self {
+        self.0.push((key, value.into()));
+        self
+    }
+
+    fn print(self, json: bool) {
+        if json {
+            let object: Map<String, Value> = self
+                .0
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect();
+            println!("{}", Value::Object(object));
+            return;
+        }
+        for (key, value) in self.0 {
+            match value {
+                Value::String(s) => println!("{key}={s}"),
+                Value::Array(items) => {
+                    let items: Vec<String> = items
+                        .iter()
+                        .map(|v| v.as_str().map(str::to_string).unwrap_or(v.to_string()))
+                        .collect();
+                    println!("{key}={}", items.join(","));
+                }
+                other => println!("{key}={other}"),
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(output) => {
+            output.print(cli.json);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            if cli.json {
+                eprintln!("{}", serde_json::json!({ "error": e.to_string() }));
+            } else {
+                eprintln!("error: {e}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<Output, CliError> {
+    match command {
+        Command::Secret(SecretCommand::New) => {
+            let mut bytes = [0u8; 32];
+            getrandom::getrandom(&mut bytes)
+                .map_err(|e| CliError::Invalid(format!("failed to generate secret: {e}")))?;
+            let secret = Secret::from_bytes(bytes);
+            Ok(Output::default()
+                .field("secret_hex", hex::encode(secret.as_bytes()))
+                .field("commitment_hex", hex::encode(compute_commitment(&secret).0)))
+        }
+        Command::Keystore(command) => keystore(command),
+        Command::Commit { secret } => {
+            let commitment = compute_commitment(&secret.resolve()?);
+            Ok(Output::default().field("commitment_hex", hex::encode(commitment.0)))
+        }
+        Command::DerivePda {
+            program_id,
+            commitment,
+            secret,
+        } => {
+            let commitment = match (commitment, secret) {
+                (Some(commitment), _) => Commitment::from_bytes(commitment),
+                (None, Some(secret)) => compute_commitment(&Secret::from_bytes(secret)),
+                (None, None) => {
+                    return Err(CliError::Invalid(
+                        "pass --commitment or --secret (or set ZAFFRE_SECRET)".into(),
+                    ))
+                }
+            };
+            let (pda, bump) = derive_zaffre_pda(&program_id, &commitment);
+            Ok(Output::default()
+                .field("commitment_hex", hex::encode(commitment.0))
+                .field("pda", bs58::encode(pda).into_string())
+                .field("pda_hex", hex::encode(pda))
+                .field("bump", bump))
+        }
+        Command::ActionHash {
+            program_id,
+            pda,
+            action,
+        } => {
+            let params = action.params()?;
+            let action_hash = compute_action_hash(
+                &program_id,
+                &pda,
+                &action.discriminator,
+                &params,
+                action.nonce,
+            );
+            Ok(Output::default()
+                .field("action_params_hex", hex::encode(&params))
+                .field("action_hash_hex", hex::encode(action_hash)))
+        }
+        Command::Nullifier {
+            secret,
+            program_id,
+            action_hash,
+        } => {
+            let nullifier = compute_nullifier(&secret.resolve()?, &program_id, &action_hash);
+            Ok(Output::default().field("nullifier_hex", hex::encode(nullifier.0)))
+        }
+        Command::Prove(args) => prove(args),
+        Command::Verify {
+            vk,
+            proof,
+            public_witness,
+        } => {
+            let vk = VerifyingKey::from_bytes(&read(&vk)?)?;
+            let public_witness = read(&public_witness)?;
+            verify_proof(&vk, &read(&proof)?, &public_witness)?;
+            let mut output = Output::default().field("valid", true);
+            if let Ok(inputs) = PublicInputs::from_public_witness(&public_witness) {
+                output = ownership_fields(output, &inputs);
+            }
+            Ok(output)
+        }
+        Command::InspectWitness { path } => inspect_witness(&read(&path)?),
+    }
+}
+
+fn prove(args: ProveArgs) -> Result<Output, CliError> {
+    let circuit_name = read_circuit_name(&args.circuit).map_err(|e| {
+        CliError::Invalid(format!(
+            "{} is not a noir package: {e}",
+            args.circuit.display()
+        ))
+    })?;
+    let secret = args.secret.resolve()?;
+    let (pda, bump) = match args.pda {
+        Some(pda) => (pda, None),
+        None => {
+            let (pda, bump) = derive_zaffre_pda(&args.program_id, &compute_commitment(&secret));
+            (pda, Some(bump))
+        }
+    };
+    let params = args.action.params()?;
+    let request = ProofRequest::new(
+        secret,
+        args.program_id,
+        pda,
+        args.action.discriminator,
+        &params,
+        args.action.nonce,
+    )?;
+
+    #[cfg(feature = "remote")]
+    let result = match &args.remote {
+        Some(url) => RemoteBackend::new(url.as_str()).prove(&request)?,
+        None => NargoSunspotBackend::new(&args.circuit).prove(&request)?,
+    };
+    #[cfg(not(feature = "remote"))]
+    let result = NargoSunspotBackend::new(&args.circuit).prove(&request)?;
+
+    let target_dir = args.circuit.join("target");
+    if !args.no_verify {
+        let vk_path = args
+            .vk
+            .unwrap_or_else(|| target_dir.join(format!("{circuit_name}.vk")));
+        let vk = VerifyingKey::from_bytes(&read(&vk_path)?)?;
+        verify_ownership_proof(&vk, &result, &request.public_values())?;
+    }
+    let out_dir = args.out_dir.unwrap_or(target_dir);
+    let (proof_path, public_witness_path) = result.write_to(&out_dir, &circuit_name)?;
+
+    let mut output = ownership_fields(Output::default(), &request.public_values())
+        .field("pda", bs58::encode(pda).into_string());
+    if let Some(bump) = bump {
+        output = output.field("bump", bump);
+    }
+    Ok(output
+        .field(
+            "action_discriminator_hex",
+            hex::encode(request.action_discriminator),
+        )
+        .field("action_params_hex", hex::encode(&request.action_params))
+        .field("nonce", request.nonce)
+        .field("verified", !args.no_verify)
+        .field("proof_path", proof_path.display().to_string())
+        .field(
+            "public_witness_path",
+            public_witness_path.display().to_string(),
+        ))
+}
+
+fn keystore(command: KeystoreCommand) -> Result<Output, CliError> {
+    match command {
+        KeystoreCommand::Create { keystore } => {
+            if keystore.keystore.exists() {
+                return Err(CliError::Invalid(format!(
+                    "{} already exists",
+                    keystore.keystore.display()
+                )));
+            }
+            Keystore::new().save(&keystore.keystore, read_password(true)?.as_bytes())?;
+            Ok(Output::default().field("keystore", keystore.keystore.display().to_string()))
+        }
+        KeystoreCommand::Add {
+            keystore,
+            label,
+            program_id,
+            rotation_index,
+            import,
+            secret,
+        } => {
+            let secret = new_entry_secret(import, secret)?;
+            let password = read_password(false)?;
+            let mut store = Keystore::open(&keystore.keystore, password.as_bytes())?;
+            let entry = KeystoreEntry::new(label, program_id, secret, rotation_index);
+            let output = entry_fields(Output::default(), &entry);
+            store.insert(entry)?;
+            store.save(&keystore.keystore, password.as_bytes())?;
+            Ok(output)
+        }
+        KeystoreCommand::Unlock { keystore } => {
+            let store = Keystore::open(&keystore.keystore, read_password(false)?.as_bytes())?;
+            Ok(Output::default()
+                .field("unlocked", true)
+                .field("entry_count", store.entries().len()))
+        }
+        KeystoreCommand::List { keystore } => {
+            let store = Keystore::open(&keystore.keystore, read_password(false)?.as_bytes())?;
+            let entries: Vec<Value> = store
+                .entries()
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "label": entry.label,
+                        "program_id": bs58::encode(entry.program_id).into_string(),
+                        "commitment_hex": hex::encode(entry.commitment.0),
+                        "rotation_index": entry.rotation_index,
+                    })
+                })
+                .collect();
+            Ok(Output::default().field("entries", entries))
+        }
+        KeystoreCommand::Export { keystore, label } => {
+            let store = Keystore::open(&keystore.keystore, read_password(false)?.as_bytes())?;
+            let entry = store.get(&label)?;
+            Ok(entry_fields(Output::default(), entry)
+                .field("secret_hex", hex::encode(entry.secret.as_bytes())))
+        }
+    }
+}
+
+fn entry_fields(output: Output, entry: &KeystoreEntry) -> Output {
+    output
+        .field("label", entry.label.clone())
+        .field("program_id", bs58::encode(entry.program_id).into_string())
+        .field("commitment_hex", hex::encode(entry.commitment.0))
+        .field("rotation_index", entry.rotation_index)
+}
+
+/// a secret given without --import is an error rather than silently replaced by a new one
+fn new_entry_secret(import: bool, secret: Option<[u8; 32]>) -> Result<Secret, CliError> {
+    match (import, secret) {
+        (true, Some(secret)) => Ok(Secret::from_bytes(secret)),
+        (false, None) => Ok(Secret::generate()),
+        (true, None) => Err(CliError::Invalid(
+            "--import needs --secret or ZAFFRE_SECRET".into(),
+        )),
+        (false, Some(_)) => Err(CliError::Invalid(
+            "--secret / ZAFFRE_SECRET is set; pass --import to store it, or unset it to \
+             generate a new secret"
+                .into(),
+        )),
+    }
+}
+
+/// `ZAFFRE_KEYSTORE_PASSWORD`, or a prompt on the terminal; `confirm` asks twice
+fn read_password(confirm: bool) -> Result<Zeroizing<String>, CliError> {
+    if let Ok(password) = std::env::var(KEYSTORE_PASSWORD_ENV) {
+        return Ok(Zeroizing::new(password));
+    }
+    let prompt = |text: &str| {
+        rpassword::prompt_password(text)
+            .map(Zeroizing::new)
+            .map_err(|e| CliError::Invalid(format!("failed to read password: {e}")))
+    };
+    let password = prompt("keystore password: ")?;
+    if confirm && *prompt("repeat password: ")? != *password {
+        return Err(CliError::Invalid("passwords do not match".into()));
+    }
+    Ok(password)
+}
+
+fn inspect_witness(bytes: &[u8]) -> Result<Output, CliError> {
+    let inputs = decode_public_witness(bytes)?;
+    let mut output = Output::default().field("input_count", inputs.len());
+    let names: &[&'static str] = match inputs.len() {
+        10 => &[
+            "commitment",
+            "pda",
+            "program_id",
+            "action_hash",
+            "nullifier",
+        ],
+        8 => &["root", "program_id", "action_hash", "nullifier"],
+        _ => &[],
+    };
+    match values_from_public_inputs(&inputs) {
+        Some(values) if values.len() == names.len() => {
+            for (name, value) in names.iter().zip(&values) {
+                output = output.field(name, hex::encode(value));
+            }
+        }
+        _ => {
+            let entries: Vec<Value> = bytes[12..]
+                .chunks_exact(32)
+                .map(|entry| Value::from(hex::encode(entry)))
+                .collect();
+            output = output.field("inputs", entries);
+        }
+    }
+    Ok(output)
+}
+
+fn ownership_fields(output: Output, inputs: &PublicInputs) -> Output {
+    output
+        .field("commitment_hex", hex::encode(inputs.commitment))
+        .field("pda_hex", hex::encode(inputs.pda))
+        .field("program_id_hex", hex::encode(inputs.program_id))
+        .field("action_hash_hex", hex::encode(inputs.action_hash))
+        .field("nullifier_hex", hex::encode(inputs.nullifier))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, CliError> {
+    std::fs::read(path).map_err(|source| CliError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value.strip_prefix("0x").unwrap_or(value)).map_err(|e| format!("invalid hex: {e}"))
+}
+
+fn parse_bytes32(value: &str) -> Result<[u8; 32], String> {
+    let bytes = parse_hex(value)?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("expected 32 bytes, got {}", bytes.len()))
+}
+
+/// base58 (as printed by solana tools) or 64 hex characters
+fn parse_pubkey(value: &str) -> Result<[u8; 32], String> {
+    let hex_str = value.strip_prefix("0x").unwrap_or(value);
+    if hex_str.len() == 64 && hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+        return parse_bytes32(hex_str);
+    }
+    let bytes = bs58::decode(value)
+        .into_vec()
+        .map_err(|e| format!("expected a base58 or hex pubkey: {e}"))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("expected 32 bytes, got {}", bytes.len()))
+}
+
+/// 16 hex chars with a 0x prefix, or up to 8 ascii bytes, zero-padded like anchor-style
+/// string discriminators
+fn parse_discriminator(value: &str) -> Result<[u8; 8], String> {
+    let mut out = [0u8; 8];
+    if let Some(hex_str) = value.strip_prefix("0x") {
+        let bytes = hex::decode(hex_str).map_err(|e| format!("invalid hex: {e}"))?;
+        if bytes.len() != 8 {
+            return Err(format!("expected 8 bytes, got {}", bytes.len()));
+        }
+        out.copy_from_slice(&bytes);
+    } else {
+        if value.len() > 8 {
+            return Err("discriminator must be at most 8 bytes".into());
+        }
+        out[..value.len()].copy_from_slice(value.as_bytes());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_pubkey_accepts_base58_and_hex() {
+        let expected = [3u8; 32];
+        assert_eq!(
+            parse_pubkey("CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8").unwrap(),
+            expected
+        );
+        assert_eq!(parse_pubkey(&hex::encode(expected)).unwrap(), expected);
+        assert_eq!(
+            parse_pubkey(&format!("0x{}", hex::encode(expected))).unwrap(),
+            expected
+        );
+        assert!(parse_pubkey("not a key").is_err());
+        assert!(parse_pubkey("3yZe7d").is_err());
+    }
+
+    #[test]
+    fn test_parse_discriminator() {
+        assert_eq!(parse_discriminator("setvalue").unwrap(), *b"setvalue");
+        assert_eq!(parse_discriminator("rotate").unwrap(), *b"rotate\0\0");
+        assert_eq!(
+            parse_discriminator("0x0102030405060708").unwrap(),
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        assert!(parse_discriminator("toolongname").is_err());
+        assert!(parse_discriminator("0x01").is_err());
+    }
+
+    #[test]
+    fn test_action_hash_command() {
+        let cli = Cli::try_parse_from([
+            "zaffre",
+            "action-hash",
+            "--program-id",
+            "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8",
+            "--pda",
+            &hex::encode([4u8; 32]),
+            "--value",
+            "123",
+            "--nonce",
+            "42",
+        ])
+        .unwrap();
+        let output = run(cli.command).unwrap();
+        let expected = compute_action_hash(
+            &[3u8; 32],
+            &[4u8; 32],
+            b"setvalue",
+            &123u64.to_le_bytes(),
+            42,
+        );
+        assert_eq!(output.0[1].1, Value::from(hex::encode(expected)));
+
+        assert!(Cli::try_parse_from([
+            "zaffre",
+            "action-hash",
+            "--program-id",
+            "x",
+            "--pda",
+            "y",
+            "--nonce",
+            "1"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_secret_sources() {
+        let cli =
+            Cli::try_parse_from(["zaffre", "commit", "--secret", &hex::encode([7u8; 32])]).unwrap();
+        let output = run(cli.command).unwrap();
+        assert_eq!(
+            output.0[0].1,
+            Value::from(hex::encode(
+                compute_commitment(&Secret::from_bytes([7u8; 32])).0
+            ))
+        );
+
+        assert!(Cli::try_parse_from(["zaffre", "commit", "--label", "vault"]).is_err());
+        let cli = Cli::try_parse_from([
+            "zaffre",
+            "keystore",
+            "add",
+            "--keystore",
+            "zaffre.keystore",
+            "--label",
+            "vault",
+            "--program-id",
+            "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8",
+            "--rotation-index",
+            "2",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Keystore(KeystoreCommand::Add {
+                rotation_index: 2,
+                import: false,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_add_secret_needs_import() {
+        let secret = [7u8; 32];
+        let imported = new_entry_secret(true, Some(secret)).unwrap();
+        assert_eq!(imported.as_bytes(), &secret);
+        assert_ne!(new_entry_secret(false, None).unwrap().as_bytes(), &secret);
+        assert!(matches!(
+            new_entry_secret(false, Some(secret)),
+            Err(CliError::Invalid(_))
+        ));
+        assert!(matches!(
+            new_entry_secret(true, None),
+            Err(CliError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_params_limit_is_an_error() {
+        let action = ActionArgs {
+            discriminator: *b"setvalue",
+            params: Some(vec![0u8; 40]),
+            value: None,
+            digest: false,
+            nonce: 1,
+        };
+        assert!(matches!(action.params(), Err(CliError::Invalid(_))));
+        let digest = ActionArgs {
+            digest: true,
+            ..action
+        };
+        assert_eq!(digest.params().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_inspect_witness_names_ownership_inputs() {
+        let request = ProofRequest::new(
+            Secret::from_bytes([7u8; 32]),
+            [3u8; 32],
+            [4u8; 32],
+            *b"setvalue",
+            &[],
+            1,
+        )
+        .unwrap();
+        let output = inspect_witness(&request.public_witness()).unwrap();
+        let keys: Vec<&str> = output.0.iter().map(|(k, _)| *k).collect();
+        assert_eq!(
+            keys,
+            [
+                "input_count",
+                "commitment",
+                "pda",
+                "program_id",
+                "action_hash",
+                "nullifier"
+            ]
+        );
+        assert_eq!(output.0[2].1, Value::from(hex::encode([4u8; 32])));
+        assert!(inspect_witness(&request.public_witness()[..40]).is_err());
+    }
+}
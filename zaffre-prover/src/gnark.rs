@@ -0,0 +1,269 @@
+//! gnark binary encodings of groth16 proofs and public witnesses
+
+use ark_bn254::{Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::proof::ProverError;
+
+pub use zaffre_core::witness::{
+    PUBLIC_INPUT_LIMB_BYTES, PUBLIC_WITNESS_ENTRY_LEN, PUBLIC_WITNESS_HEADER_LEN,
+};
+
+pub(crate) const G1_LEN: usize = 64;
+pub(crate) const G2_LEN: usize = 128;
+
+/// splits 32-byte public values into the circuits' big-endian (hi, lo) limbs
+pub fn public_inputs_from_values(values: &[&[u8; 32]]) -> Vec<Fr> {
+    values
+        .iter()
+        .flat_map(|value| value.chunks_exact(PUBLIC_INPUT_LIMB_BYTES))
+        .map(Fr::from_be_bytes_mod_order)
+        .collect()
+}
+
+/// inverse of `public_inputs_from_values`; `None` if a limb exceeds 128 bits or the
+/// input count is odd
+pub fn values_from_public_inputs(inputs: &[Fr]) -> Option<Vec<[u8; 32]>> {
+    if !inputs.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut values = vec![[0u8; 32]; inputs.len() / 2];
+    for (input, limb) in inputs.iter().zip(
+        values
+            .iter_mut()
+            .flat_map(|v| v.chunks_exact_mut(PUBLIC_INPUT_LIMB_BYTES)),
+    ) {
+        let bytes = input.into_bigint().to_bytes_be();
+        let (high, low) = bytes.split_at(bytes.len() - PUBLIC_INPUT_LIMB_BYTES);
+        if high.iter().any(|b| *b != 0) {
+            return None;
+        }
+        limb.copy_from_slice(low);
+    }
+    Some(values)
+}
+
+/// gnark public witness: (public count, secret count = 0, vector length) as u32 be,
+/// then one 32-byte big-endian entry per input
+pub fn encode_public_witness(inputs: &[Fr]) -> Vec<u8> {
+    let count = inputs.len() as u32;
+    let mut out =
+        Vec::with_capacity(PUBLIC_WITNESS_HEADER_LEN + inputs.len() * PUBLIC_WITNESS_ENTRY_LEN);
+    out.extend_from_slice(&count.to_be_bytes());
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.extend_from_slice(&count.to_be_bytes());
+    for input in inputs {
+        out.extend_from_slice(&input.into_bigint().to_bytes_be());
+    }
+    out
+}
+
+pub fn decode_public_witness(bytes: &[u8]) -> Result<Vec<Fr>, ProverError> {
+    let invalid = || ProverError::InvalidPublicWitness(bytes.len());
+    if bytes.len() < PUBLIC_WITNESS_HEADER_LEN {
+        return Err(invalid());
+    }
+    let count = u32::from_be_bytes(bytes[0..4].try_into().unwrap()) as usize;
+    let secret_count = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+    let vector_len = u32::from_be_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    if secret_count != 0
+        || vector_len != count
+        || bytes.len() != PUBLIC_WITNESS_HEADER_LEN + count * PUBLIC_WITNESS_ENTRY_LEN
+    {
+        return Err(invalid());
+    }
+
+    bytes[PUBLIC_WITNESS_HEADER_LEN..]
+        .chunks_exact(PUBLIC_WITNESS_ENTRY_LEN)
+        .map(|entry| {
+            let value = Fr::from_be_bytes_mod_order(entry);
+            if value.into_bigint().to_bytes_be() == entry {
+                Ok(value)
+            } else {
+                Err(invalid())
+            }
+        })
+        .collect()
+}
+
+/// groth16 proof with the bsb22 commitment extension, as written by gnark (and sunspot):
+/// `ar (g1) | bs (g2) | krs (g1) | u32 be commitment count | commitments (g1) | pok (g1)`.
+/// points are uncompressed big-endian, g2 coordinates as (c1, c0), infinity as zeros.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GnarkProof {
+    pub ar: G1Affine,
+    pub bs: G2Affine,
+    pub krs: G1Affine,
+    pub commitments: Vec<G1Affine>,
+    pub commitment_pok: G1Affine,
+}
+
+impl GnarkProof {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProverError> {
+        let invalid = || ProverError::InvalidProof(bytes.len());
+        let fixed = 2 * G1_LEN + G2_LEN;
+        if bytes.len() < fixed + 4 + G1_LEN {
+            return Err(invalid());
+        }
+        let count = u32::from_be_bytes(bytes[fixed..fixed + 4].try_into().unwrap()) as usize;
+        if bytes.len() != fixed + 4 + (count + 1) * G1_LEN {
+            return Err(invalid());
+        }
+
+        let ar = read_g1(&bytes[0..G1_LEN]).ok_or_else(invalid)?;
+        let bs = read_g2(&bytes[G1_LEN..G1_LEN + G2_LEN]).ok_or_else(invalid)?;
+        let krs = read_g1(&bytes[G1_LEN + G2_LEN..fixed]).ok_or_else(invalid)?;
+        let mut points = bytes[fixed + 4..]
+            .chunks_exact(G1_LEN)
+            .map(read_g1)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let commitment_pok = points.pop().ok_or_else(invalid)?;
+
+        Ok(Self {
+            ar,
+            bs,
+            krs,
+            commitments: points,
+            commitment_pok,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out =
+            Vec::with_capacity(2 * G1_LEN + G2_LEN + 4 + (self.commitments.len() + 1) * G1_LEN);
+        write_g1(&mut out, &self.ar);
+        write_g2(&mut out, &self.bs);
+        write_g1(&mut out, &self.krs);
+        out.extend_from_slice(&(self.commitments.len() as u32).to_be_bytes());
+        for commitment in &self.commitments {
+            write_g1(&mut out, commitment);
+        }
+        write_g1(&mut out, &self.commitment_pok);
+        out
+    }
+}
+
+fn read_fq(bytes: &[u8]) -> Option<Fq> {
+    let value = Fq::from_be_bytes_mod_order(bytes);
+    (value.into_bigint().to_bytes_be() == bytes).then_some(value)
+}
+
+pub(crate) fn read_g1(bytes: &[u8]) -> Option<G1Affine> {
+    if bytes.iter().all(|b| *b == 0) {
+        return Some(G1Affine::zero());
+    }
+    let point = G1Affine::new_unchecked(read_fq(&bytes[0..32])?, read_fq(&bytes[32..64])?);
+    point.is_on_curve().then_some(point)
+}
+
+pub(crate) fn read_g2(bytes: &[u8]) -> Option<G2Affine> {
+    if bytes.iter().all(|b| *b == 0) {
+        return Some(G2Affine::zero());
+    }
+    let x = Fq2::new(read_fq(&bytes[32..64])?, read_fq(&bytes[0..32])?);
+    let y = Fq2::new(read_fq(&bytes[96..128])?, read_fq(&bytes[64..96])?);
+    let point = G2Affine::new_unchecked(x, y);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+fn write_fq(out: &mut Vec<u8>, value: &Fq) {
+    out.extend_from_slice(&value.into_bigint().to_bytes_be());
+}
+
+pub(crate) fn write_g1(out: &mut Vec<u8>, point: &G1Affine) {
+    match point.xy() {
+        Some((x, y)) => {
+            write_fq(out, x);
+            write_fq(out, y);
+        }
+        None => out.extend_from_slice(&[0u8; G1_LEN]),
+    }
+}
+
+pub(crate) fn write_g2(out: &mut Vec<u8>, point: &G2Affine) {
+    match point.xy() {
+        Some((x, y)) => {
+            write_fq(out, &x.c1);
+            write_fq(out, &x.c0);
+            write_fq(out, &y.c1);
+            write_fq(out, &y.c0);
+        }
+        None => out.extend_from_slice(&[0u8; G2_LEN]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // proof with one commitment from vendor/gnark-solana verifier-lib tests
+    const PROOF_HEX: &str = concat!(
+        "0c0db971ccb520d78c176a700d33ba7667167f76ba9d175db7a05032a4d47c5f",
+        "08e9a7306bb0e46ceefe7ce2cf48ac5ffe9ccb9a81947c60a78f59605343119e",
+        "07ad258cc7b288ab97f01cff8e29a46bc8c8c9a50b0ede615482729b8edc7dca",
+        "14b1dfd3a0bb42d35485980253334cedb51d8ba39a8e0a310290d3b605093fc6",
+        "2382f130068002f6683b8117c9089bfbfeb0293555e4794acac99ff02407053b",
+        "041771187776deda76e8851e42893031d96cafafc08978b8f2bc93d4207c4a7d",
+        "2d4f9c6abe5368220519a507ad49848b16b294056f5c571f027ae49003c47505",
+        "11e4f6d3dc3b606f65fbd1a28ede6fc5f766434a685b3f8e790d5cbfe8c35e1e",
+        "000000012e470554902c76a8941e0f04e5919b5eeba702ce714703b3295fa6df",
+        "7c79400b291acb72e3234d489cf0f17b0daaacfd1f2f19e02058dae759e43866",
+        "0ab6309701aeea6eb7d7e04fc74e84d6fd519f1cdde47ec37a52c927c9007059",
+        "146845c908a42409945d4b03983141885496c95f0c5bcdaa1985cf0ffb88ef32",
+        "20ba7971",
+    );
+
+    fn proof_bytes() -> Vec<u8> {
+        hex::decode(PROOF_HEX).unwrap()
+    }
+
+    #[test]
+    fn test_proof_roundtrip() {
+        let bytes = proof_bytes();
+        assert_eq!(bytes.len(), 388);
+        let proof = GnarkProof::from_bytes(&bytes).expect("proof should parse");
+        assert_eq!(proof.commitments.len(), 1);
+        assert_eq!(proof.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_proof_rejects_invalid_point() {
+        let mut bytes = proof_bytes();
+        bytes[63] ^= 1;
+        assert!(GnarkProof::from_bytes(&bytes).is_err());
+        assert!(GnarkProof::from_bytes(&bytes[..387]).is_err());
+    }
+
+    #[test]
+    fn test_public_witness_roundtrip() {
+        let mut value = [0u8; 32];
+        value[15] = 1;
+        value[31] = 2;
+        let inputs = public_inputs_from_values(&[&value, &[0xff; 32]]);
+        assert_eq!(
+            inputs,
+            vec![
+                Fr::from(1u64),
+                Fr::from(2u64),
+                Fr::from(u128::MAX),
+                Fr::from(u128::MAX)
+            ]
+        );
+
+        let encoded = encode_public_witness(&inputs);
+        assert_eq!(
+            encoded.len(),
+            PUBLIC_WITNESS_HEADER_LEN + 4 * PUBLIC_WITNESS_ENTRY_LEN
+        );
+        assert_eq!(&encoded[0..12], &[0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 4]);
+        assert_eq!(decode_public_witness(&encoded).unwrap(), inputs);
+        assert_eq!(
+            values_from_public_inputs(&inputs).unwrap(),
+            vec![value, [0xff; 32]]
+        );
+        assert!(values_from_public_inputs(&inputs[..3]).is_none());
+        assert!(decode_public_witness(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,454 @@
+//! encrypted keystore for secrets, so they never have to be passed on the command line.
+//!
+//! a keystore file is versioned json: argon2id parameters and a salt, a chacha20poly1305
+//! nonce, and one ciphertext holding every entry. labels and program ids are encrypted
+//! too, since a readable list of programs would link the accounts it is meant to keep
+//! apart. the header is authenticated as associated data.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::commitment::compute_commitment;
+use crate::proof::ProverError;
+use crate::types::{Commitment, Secret};
+
+pub const KEYSTORE_VERSION: u32 = 1;
+
+const KDF_NAME: &str = "argon2id";
+const CIPHER_NAME: &str = "chacha20poly1305";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// argon2id cost; the default follows the owasp recommendation of 64 MiB and 3 passes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// the most a keystore may ask for. the params are read from the file before anything
+    /// authenticates them, so an edited file could otherwise demand any amount of memory
+    /// and time
+    pub const MAX: KdfParams = KdfParams {
+        memory_kib: 4 * 1024 * 1024,
+        iterations: 64,
+        parallelism: 16,
+    };
+
+    fn check(&self) -> Result<(), ProverError> {
+        let max = Self::MAX;
+        if self.memory_kib > max.memory_kib
+            || self.iterations > max.iterations
+            || self.parallelism > max.parallelism
+        {
+            return Err(invalid(format!(
+                "kdf parameters {self:?} exceed the limit {max:?}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct KeystoreEntry {
+    pub label: String,
+    pub program_id: [u8; 32],
+    pub secret: Secret,
+    pub commitment: Commitment,
+    /// position in the secret's rotation history, e.g. the `MasterSeed` index
+    pub rotation_index: u32,
+}
+
+impl KeystoreEntry {
+    pub fn new(
+        label: impl Into<String>,
+        program_id: [u8; 32],
+        secret: Secret,
+        rotation_index: u32,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            program_id,
+            commitment: compute_commitment(&secret),
+            secret,
+            rotation_index,
+        }
+    }
+}
+
+/// an unlocked keystore; secrets are zeroized when it is dropped
+#[derive(Clone, Default)]
+pub struct Keystore {
+    entries: Vec<KeystoreEntry>,
+    kdf: KdfParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    kdf: KdfHeader,
+    cipher: CipherHeader,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KdfHeader {
+    name: String,
+    #[serde(flatten)]
+    params: KdfParams,
+    salt: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherHeader {
+    name: String,
+    nonce: String,
+}
+
+/// plaintext form of an entry, only ever held in zeroized buffers
+#[derive(Serialize, Deserialize)]
+struct EntryRecord {
+    label: String,
+    program_id: String,
+    secret: String,
+    commitment: String,
+    rotation_index: u32,
+}
+
+impl Drop for EntryRecord {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl Keystore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_kdf_params(kdf: KdfParams) -> Self {
+        Self {
+            entries: Vec::new(),
+            kdf,
+        }
+    }
+
+    pub fn kdf_params(&self) -> KdfParams {
+        self.kdf
+    }
+
+    pub fn entries(&self) -> &[KeystoreEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, label: &str) -> Result<&KeystoreEntry, ProverError> {
+        self.entries
+            .iter()
+            .find(|entry| entry.label == label)
+            .ok_or_else(|| ProverError::UnknownKeystoreLabel(label.to_string()))
+    }
+
+    pub fn insert(&mut self, entry: KeystoreEntry) -> Result<(), ProverError> {
+        if self.entries.iter().any(|e| e.label == entry.label) {
+            return Err(ProverError::DuplicateKeystoreLabel(entry.label));
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// reads and decrypts a keystore file
+    pub fn open(path: &Path, password: &[u8]) -> Result<Self, ProverError> {
+        Self::decrypt(&fs::read(path)?, password)
+    }
+
+    /// encrypts with a fresh salt and nonce and atomically replaces `path`, readable by
+    /// the owner only
+    pub fn save(&self, path: &Path, password: &[u8]) -> Result<(), ProverError> {
+        let contents = self.encrypt(password)?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut file = NamedTempFile::new_in(dir)?;
+        file.write_all(&contents)?;
+        file.as_file().sync_all()?;
+        file.persist(path).map_err(|e| ProverError::Io(e.error))?;
+        Ok(())
+    }
+
+    pub fn encrypt(&self, password: &[u8]) -> Result<Vec<u8>, ProverError> {
+        let records: Vec<EntryRecord> = self
+            .entries
+            .iter()
+            .map(|entry| EntryRecord {
+                label: entry.label.clone(),
+                program_id: bs58::encode(entry.program_id).into_string(),
+                secret: hex::encode(entry.secret.as_bytes()),
+                commitment: hex::encode(entry.commitment.as_bytes()),
+                rotation_index: entry.rotation_index,
+            })
+            .collect();
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(&records).map_err(|e| invalid(format!("encoding: {e}")))?,
+        );
+
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut salt).map_err(|e| invalid(format!("getrandom: {e}")))?;
+        getrandom::getrandom(&mut nonce).map_err(|e| invalid(format!("getrandom: {e}")))?;
+        let mut file = KeystoreFile {
+            version: KEYSTORE_VERSION,
+            kdf: KdfHeader {
+                name: KDF_NAME.to_string(),
+                params: self.kdf,
+                salt: hex::encode(salt),
+            },
+            cipher: CipherHeader {
+                name: CIPHER_NAME.to_string(),
+                nonce: hex::encode(nonce),
+            },
+            ciphertext: String::new(),
+        };
+
+        let key = derive_key(password, &salt, &self.kdf)?;
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &associated_data(&file)?,
+                },
+            )
+            .map_err(|_| invalid("encryption failed".into()))?;
+        file.ciphertext = hex::encode(ciphertext);
+        serde_json::to_vec_pretty(&file).map_err(|e| invalid(format!("encoding: {e}")))
+    }
+
+    pub fn decrypt(bytes: &[u8], password: &[u8]) -> Result<Self, ProverError> {
+        #[derive(Deserialize)]
+        struct VersionOnly {
+            version: u32,
+        }
+        let VersionOnly { version } =
+            serde_json::from_slice(bytes).map_err(|e| invalid(e.to_string()))?;
+        if version != KEYSTORE_VERSION {
+            return Err(ProverError::UnsupportedKeystoreVersion(version));
+        }
+        let file: KeystoreFile =
+            serde_json::from_slice(bytes).map_err(|e| invalid(e.to_string()))?;
+        if file.kdf.name != KDF_NAME || file.cipher.name != CIPHER_NAME {
+            return Err(invalid(format!(
+                "unsupported algorithms {} / {}",
+                file.kdf.name, file.cipher.name
+            )));
+        }
+        let salt = decode_hex(&file.kdf.salt, "salt")?;
+        let nonce = decode_hex(&file.cipher.nonce, "nonce")?;
+        if nonce.len() != NONCE_LEN {
+            return Err(invalid("nonce must be 12 bytes".into()));
+        }
+        let ciphertext = decode_hex(&file.ciphertext, "ciphertext")?;
+
+        let key = derive_key(password, &salt, &file.kdf.params)?;
+        let plaintext = Zeroizing::new(
+            ChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+                .decrypt(
+                    Nonce::from_slice(&nonce),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: &associated_data(&file)?,
+                    },
+                )
+                .map_err(|_| ProverError::KeystoreDecryption)?,
+        );
+        let records: Vec<EntryRecord> =
+            serde_json::from_slice(&plaintext).map_err(|e| invalid(e.to_string()))?;
+
+        let mut keystore = Self::with_kdf_params(file.kdf.params);
+        for record in records {
+            let entry = record.to_entry()?;
+            keystore.insert(entry)?;
+        }
+        Ok(keystore)
+    }
+}
+
+impl EntryRecord {
+    fn to_entry(&self) -> Result<KeystoreEntry, ProverError> {
+        let program_id = bs58::decode(&self.program_id)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| invalid(format!("entry {:?} has a bad program id", self.label)))?;
+        let secret_bytes = Zeroizing::new(decode_hex(&self.secret, "secret")?);
+        let secret = <[u8; 32]>::try_from(secret_bytes.as_slice())
+            .map(Secret::from_bytes)
+            .map_err(|_| invalid(format!("entry {:?} has a bad secret", self.label)))?;
+
+        let entry = KeystoreEntry::new(self.label.clone(), program_id, secret, self.rotation_index);
+        if hex::encode(entry.commitment.as_bytes()) != self.commitment {
+            return Err(invalid(format!(
+                "entry {:?} does not match its commitment",
+                self.label
+            )));
+        }
+        Ok(entry)
+    }
+}
+
+fn derive_key(
+    password: &[u8],
+    salt: &[u8],
+    kdf: &KdfParams,
+) -> Result<Zeroizing<[u8; 32]>, ProverError> {
+    kdf.check()?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| invalid(format!("kdf parameters: {e}")))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password, salt, key.as_mut())
+        .map_err(|e| invalid(format!("kdf: {e}")))?;
+    Ok(key)
+}
+
+/// the header without the ciphertext, so parameters cannot be swapped undetected
+fn associated_data(file: &KeystoreFile) -> Result<Vec<u8>, ProverError> {
+    serde_json::to_vec(&(file.version, &file.kdf, &file.cipher))
+        .map_err(|e| invalid(format!("encoding: {e}")))
+}
+
+fn decode_hex(value: &str, field: &str) -> Result<Vec<u8>, ProverError> {
+    hex::decode(value).map_err(|e| invalid(format!("{field}: {e}")))
+}
+
+fn invalid(reason: String) -> ProverError {
+    ProverError::InvalidKeystore(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // argon2's minimum cost, to keep the tests fast
+    const TEST_KDF: KdfParams = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn keystore() -> Keystore {
+        let mut keystore = Keystore::with_kdf_params(TEST_KDF);
+        keystore
+            .insert(KeystoreEntry::new(
+                "vault",
+                [3u8; 32],
+                Secret::from_bytes([7u8; 32]),
+                0,
+            ))
+            .unwrap();
+        keystore
+            .insert(KeystoreEntry::new(
+                "vault-rotated",
+                [3u8; 32],
+                Secret::from_bytes([8u8; 32]),
+                1,
+            ))
+            .unwrap();
+        keystore
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zaffre.keystore");
+        keystore().save(&path, b"hunter2").unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("vault"));
+        assert!(!contents.contains(&hex::encode([7u8; 32])));
+
+        let unlocked = Keystore::open(&path, b"hunter2").unwrap();
+        assert_eq!(unlocked.kdf_params(), TEST_KDF);
+        assert_eq!(unlocked.entries().len(), 2);
+        let entry = unlocked.get("vault-rotated").unwrap();
+        assert_eq!(entry.secret.as_bytes(), &[8u8; 32]);
+        assert_eq!(entry.program_id, [3u8; 32]);
+        assert_eq!(entry.rotation_index, 1);
+        assert_eq!(
+            entry.commitment,
+            compute_commitment(&Secret::from_bytes([8u8; 32]))
+        );
+        assert!(matches!(
+            unlocked.get("missing"),
+            Err(ProverError::UnknownKeystoreLabel(_))
+        ));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_rejects_wrong_password_and_tampering() {
+        let bytes = keystore().encrypt(b"hunter2").unwrap();
+        assert!(matches!(
+            Keystore::decrypt(&bytes, b"hunter3"),
+            Err(ProverError::KeystoreDecryption)
+        ));
+
+        let mut file: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        file["kdf"]["iterations"] = 2.into();
+        let tampered = serde_json::to_vec(&file).unwrap();
+        assert!(matches!(
+            Keystore::decrypt(&tampered, b"hunter2"),
+            Err(ProverError::KeystoreDecryption)
+        ));
+
+        file["kdf"]["memory_kib"] = u32::MAX.into();
+        let expensive = serde_json::to_vec(&file).unwrap();
+        assert!(matches!(
+            Keystore::decrypt(&expensive, b"hunter2"),
+            Err(ProverError::InvalidKeystore(reason)) if reason.contains("exceed")
+        ));
+
+        file["version"] = 2.into();
+        let future = serde_json::to_vec(&file).unwrap();
+        assert!(matches!(
+            Keystore::decrypt(&future, b"hunter2"),
+            Err(ProverError::UnsupportedKeystoreVersion(2))
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_label() {
+        let mut keystore = keystore();
+        let duplicate = KeystoreEntry::new("vault", [4u8; 32], Secret::from_bytes([9u8; 32]), 0);
+        assert!(matches!(
+            keystore.insert(duplicate),
+            Err(ProverError::DuplicateKeystoreLabel(label)) if label == "vault"
+        ));
+    }
+}
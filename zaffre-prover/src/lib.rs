@@ -1,14 +1,46 @@
 //! off-chain prover utilities
 
+pub mod action;
+pub mod backend;
 pub mod commitment;
 pub mod encoding;
+pub mod gnark;
+pub mod keystore;
+pub mod merkle;
+pub mod pda;
 pub mod proof;
 pub mod types;
+pub mod verify;
 
-pub use commitment::{compute_commitment, compute_domain, compute_nullifier};
+pub use action::{CloseAction, RotateAction, ZaffreAction};
+#[cfg(feature = "remote")]
+pub use backend::RemoteBackend;
+pub use backend::{MockBackend, NargoSunspotBackend, ProofRequest, ProverBackend};
+pub use commitment::{
+    compute_action_hash, compute_action_params_digest, compute_commitment,
+    compute_domain_separator, compute_nullifier, encode_relayed_action_params,
+};
 pub use encoding::{bytes_to_field, field_to_bytes, is_canonical};
-pub use proof::{generate_proof, ProofResult, ProverError};
+pub use gnark::{
+    decode_public_witness, encode_public_witness, public_inputs_from_values,
+    values_from_public_inputs, GnarkProof,
+};
+pub use keystore::{KdfParams, Keystore, KeystoreEntry, KEYSTORE_VERSION};
+pub use merkle::{MerklePath, MerkleTree, MERKLE_TREE_DEPTH};
+pub use pda::{
+    derive_nullifier_pda, derive_nullifier_shard_pda, derive_zaffre_pda, find_program_address,
+    nullifier_shard_index,
+};
+pub use proof::{
+    generate_membership_proof, generate_proof, prepare_circuit, prepare_circuit_with_tools,
+    read_circuit_name, CircuitArtifacts, ProofResult, ProverError, ToolPaths,
+};
 pub use types::{Commitment, Nullifier, Secret};
+pub use verify::{
+    verify_groth16, verify_ownership_proof, verify_proof, PublicInputs, VerifyingKey,
+};
+
+pub use borsh;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,203 @@
+//! commitment merkle tree mirroring the on-chain incremental tree
+
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonHasher};
+
+use crate::encoding::{bytes_to_field, field_to_bytes, is_canonical};
+use crate::proof::ProverError;
+
+pub const MERKLE_TREE_DEPTH: usize = 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath {
+    pub leaf_index: u32,
+    pub siblings: [[u8; 32]; MERKLE_TREE_DEPTH],
+}
+
+impl MerklePath {
+    pub fn compute_root(&self, leaf: &[u8; 32]) -> [u8; 32] {
+        let mut node = *leaf;
+        let mut index = self.leaf_index;
+        for sibling in &self.siblings {
+            node = if index & 1 == 0 {
+                hash_pair(&node, sibling)
+            } else {
+                hash_pair(sibling, &node)
+            };
+            index >>= 1;
+        }
+        node
+    }
+}
+
+/// append-only tree over commitments (little-endian field bytes), rebuilt off-chain
+/// from the leaves in insertion order
+#[derive(Clone, Debug, Default)]
+pub struct MerkleTree {
+    leaves: Vec<[u8; 32]>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_leaves(leaves: &[[u8; 32]]) -> Result<Self, ProverError> {
+        let mut tree = Self::new();
+        for leaf in leaves {
+            tree.insert(*leaf)?;
+        }
+        Ok(tree)
+    }
+
+    pub fn insert(&mut self, leaf: [u8; 32]) -> Result<u32, ProverError> {
+        if !is_canonical(&leaf) {
+            return Err(ProverError::InvalidMerkleLeaf);
+        }
+        if self.leaves.len() >= 1 << MERKLE_TREE_DEPTH {
+            return Err(ProverError::MerkleTreeFull);
+        }
+        self.leaves.push(leaf);
+        Ok((self.leaves.len() - 1) as u32)
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        &self.leaves
+    }
+
+    pub fn position(&self, leaf: &[u8; 32]) -> Option<u32> {
+        self.leaves.iter().position(|l| l == leaf).map(|i| i as u32)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let zeros = zero_hashes();
+        let mut level = self.leaves.clone();
+        for zero in zeros.iter().take(MERKLE_TREE_DEPTH) {
+            level = next_level(&level, zero);
+        }
+        level.first().copied().unwrap_or(zeros[MERKLE_TREE_DEPTH])
+    }
+
+    pub fn path(&self, leaf_index: u32) -> Result<MerklePath, ProverError> {
+        let index = leaf_index as usize;
+        if index >= self.leaves.len() {
+            return Err(ProverError::MerkleLeafIndexOutOfRange(leaf_index));
+        }
+
+        let zeros = zero_hashes();
+        let mut siblings = [[0u8; 32]; MERKLE_TREE_DEPTH];
+        let mut level = self.leaves.clone();
+        let mut position = index;
+        for (depth, sibling) in siblings.iter_mut().enumerate() {
+            *sibling = level.get(position ^ 1).copied().unwrap_or(zeros[depth]);
+            level = next_level(&level, &zeros[depth]);
+            position >>= 1;
+        }
+
+        Ok(MerklePath {
+            leaf_index,
+            siblings,
+        })
+    }
+}
+
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut poseidon = Poseidon::<Fr>::new_circom(2).expect("Poseidon init failed");
+    let node = poseidon
+        .hash(&[bytes_to_field(left), bytes_to_field(right)])
+        .expect("Poseidon hash failed");
+    field_to_bytes(&node)
+}
+
+/// roots of empty subtrees by height, starting from the zero leaf
+pub fn zero_hashes() -> [[u8; 32]; MERKLE_TREE_DEPTH + 1] {
+    let mut zeros = [[0u8; 32]; MERKLE_TREE_DEPTH + 1];
+    for i in 1..=MERKLE_TREE_DEPTH {
+        zeros[i] = hash_pair(&zeros[i - 1], &zeros[i - 1]);
+    }
+    zeros
+}
+
+fn next_level(level: &[[u8; 32]], zero: &[u8; 32]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(zero)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::compute_commitment;
+    use crate::types::Secret;
+
+    fn leaf(seed: u8) -> [u8; 32] {
+        *compute_commitment(&Secret::from_bytes([seed; 32])).as_bytes()
+    }
+
+    fn small_leaf(value: u8) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[0] = value;
+        bytes
+    }
+
+    // the same vectors are checked against the on-chain tree in zaffre-anchor
+    #[test]
+    fn test_root_vectors() {
+        let zeros = zero_hashes();
+        assert_eq!(MerkleTree::new().root(), zeros[MERKLE_TREE_DEPTH]);
+        assert_eq!(
+            hex::encode(zeros[MERKLE_TREE_DEPTH]),
+            "3e1f1922dfb671d3f912f7ea461e0a88ee848fdde12b6c18ab1ad2c56ae73421"
+        );
+
+        let tree = MerkleTree::from_leaves(&[small_leaf(1), small_leaf(2), small_leaf(3)]).unwrap();
+        assert_eq!(
+            hex::encode(tree.root()),
+            "d099d9dd89d1dd9b31d8056242ae2e1218bd804d149dc949b7e147ce6e318324"
+        );
+    }
+
+    #[test]
+    fn test_paths_recompute_root() {
+        let leaves: Vec<[u8; 32]> = (1..=5).map(leaf).collect();
+        let tree = MerkleTree::from_leaves(&leaves).unwrap();
+        let root = tree.root();
+        for (i, l) in leaves.iter().enumerate() {
+            let path = tree.path(i as u32).unwrap();
+            assert_eq!(path.compute_root(l), root);
+        }
+        assert_ne!(tree.path(0).unwrap().compute_root(&leaf(9)), root);
+    }
+
+    #[test]
+    fn test_root_changes_on_insert() {
+        let mut tree = MerkleTree::new();
+        tree.insert(leaf(1)).unwrap();
+        let before = tree.root();
+        assert_eq!(tree.insert(leaf(2)).unwrap(), 1);
+        assert_ne!(tree.root(), before);
+        assert_eq!(tree.position(&leaf(2)), Some(1));
+    }
+
+    #[test]
+    fn test_rejects_non_canonical_leaf() {
+        let mut tree = MerkleTree::new();
+        assert!(matches!(
+            tree.insert([0xff; 32]),
+            Err(ProverError::InvalidMerkleLeaf)
+        ));
+        assert!(matches!(
+            tree.path(0),
+            Err(ProverError::MerkleLeafIndexOutOfRange(0))
+        ));
+    }
+}
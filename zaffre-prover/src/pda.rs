@@ -0,0 +1,9 @@
+//! program derived addresses over raw 32-byte ids, shared with zaffre-core
+
+pub use zaffre_core::pda::bytes::{
+    create_program_address, derive_nullifier_pda, derive_nullifier_shard_pda, derive_zaffre_pda,
+    find_program_address,
+};
+pub use zaffre_core::pda::{
+    nullifier_shard_index, NULLIFIER_SEED_PREFIX, NULLIFIER_SHARD_SEED_PREFIX, ZAFFRE_SEED_PREFIX,
+};
@@ -1,16 +1,24 @@
 //! proof generation via nargo and sunspot
 
-use std::ffi::OsStr;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Mutex;
 
 use num_bigint::BigUint;
 use serde::Deserialize;
+use tempfile::TempDir;
 use thiserror::Error;
 use ark_ff::{BigInteger, PrimeField};
+use zaffre_core::ZaffreCoreError;
 
+use crate::backend::{NargoSunspotBackend, ProofRequest, ProverBackend};
 use crate::encoding::bytes_to_field;
+use crate::gnark::{
+    encode_public_witness, public_inputs_from_values, GnarkProof, PUBLIC_INPUT_LIMB_BYTES,
+};
+use crate::merkle::MerklePath;
 use crate::types::{Commitment, Nullifier, Secret};
 
 #[derive(Debug, Error)]
@@ -31,20 +39,61 @@ pub enum ProverError {
     MissingOutput(PathBuf),
     #[error("invalid public witness length: {0}")]
     InvalidPublicWitness(usize),
+    #[error("public witness does not match the requested public inputs")]
+    PublicWitnessMismatch,
+    #[error("invalid gnark proof ({0} bytes)")]
+    InvalidProof(usize),
     #[error("invalid Nargo.toml: missing [package] name")]
     MissingCircuitName,
     #[error("action params too long: {0}")]
     ActionParamsTooLong(usize),
+    #[error("merkle leaf is not a canonical field element")]
+    InvalidMerkleLeaf,
+    #[error("merkle tree is full")]
+    MerkleTreeFull,
+    #[error("merkle leaf index out of range: {0}")]
+    MerkleLeafIndexOutOfRange(u32),
+    #[error("invalid gnark verifying key")]
+    InvalidVerifyingKey,
+    #[error("proof verification failed")]
+    ProofVerificationFailed,
+    #[error("invalid proof request: {0}")]
+    InvalidRequest(&'static str),
+    #[error("remote prover error: {0}")]
+    Remote(String),
+    #[error(transparent)]
+    Core(#[from] ZaffreCoreError),
+    #[error("unsupported keystore version {0}")]
+    UnsupportedKeystoreVersion(u32),
+    #[error("invalid keystore: {0}")]
+    InvalidKeystore(String),
+    #[error("wrong keystore password or corrupted keystore")]
+    KeystoreDecryption,
+    #[error("keystore already has an entry labelled {0:?}")]
+    DuplicateKeystoreLabel(String),
+    #[error("keystore has no entry labelled {0:?}")]
+    UnknownKeystoreLabel(String),
 }
 
-const ACTION_PARAMS_MAX_LEN: usize = 32;
-const PUBLIC_WITNESS_SIZE: usize = 5132;
+pub(crate) use zaffre_core::witness::OWNERSHIP_ACTION_PARAMS_MAX_LEN as ACTION_PARAMS_MAX_LEN;
 
+const WITNESS_NAME: &str = "zaffre_witness";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ProofResult {
     pub proof: Vec<u8>,
     pub public_witness: Vec<u8>,
-    pub proof_path: PathBuf,
-    pub public_witness_path: PathBuf,
+}
+
+impl ProofResult {
+    /// writes `<name>.proof` and `<name>.pw` into `dir`
+    pub fn write_to(&self, dir: &Path, name: &str) -> Result<(PathBuf, PathBuf), ProverError> {
+        let proof_path = dir.join(format!("{name}.proof"));
+        let public_witness_path = dir.join(format!("{name}.pw"));
+        fs::write(&proof_path, &self.proof)?;
+        fs::write(&public_witness_path, &self.public_witness)?;
+        Ok((proof_path, public_witness_path))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,6 +106,8 @@ struct NargoPackage {
     name: String,
 }
 
+/// proves with the local nargo/sunspot pipeline; see `ProverBackend` for other backends
+#[allow(clippy::too_many_arguments)]
 pub fn generate_proof(
     secret: &Secret,
     commitment: &Commitment,
@@ -64,84 +115,213 @@ pub fn generate_proof(
     program_id: &[u8; 32],
     action_hash: &[u8; 32],
     nullifier: &Nullifier,
+    action_discriminator: &[u8; 8],
     action_params: &[u8],
     nonce: u64,
     circuit_dir: &Path,
 ) -> Result<ProofResult, ProverError> {
-    let circuit_name = read_circuit_name(circuit_dir)?;
-    let target_dir = circuit_dir.join("target");
-    let witness_name = "zaffre_witness";
-    let prover_toml_path = circuit_dir.join("Prover.toml");
-
-    let _prover_toml_guard = ProverTomlGuard::new(prover_toml_path);
-    write_prover_toml(
-        circuit_dir,
-        secret,
-        commitment,
-        pda,
+    let request = ProofRequest {
+        secret: secret.clone(),
+        commitment: *commitment,
+        pda: *pda,
+        program_id: *program_id,
+        action_hash: *action_hash,
+        nullifier: *nullifier,
+        action_discriminator: *action_discriminator,
+        action_params: action_params.to_vec(),
+        nonce,
+    };
+    NargoSunspotBackend::new(circuit_dir).prove(&request)
+}
+
+pub(crate) fn prove_ownership(
+    request: &ProofRequest,
+    circuit_dir: &Path,
+    tools: &ToolPaths,
+) -> Result<ProofResult, ProverError> {
+    prove_circuit(circuit_dir, tools, &request.public_witness(), |dir| {
+        write_prover_toml(
+            dir,
+            &request.secret,
+            &request.commitment,
+            &request.pda,
+            &request.program_id,
+            &request.action_hash,
+            &request.nullifier,
+            &request.action_discriminator,
+            &request.action_params,
+            request.nonce,
+        )
+    })
+}
+
+/// proves membership of `compute_commitment(secret)` under `root` without revealing
+/// the leaf. `pda` is the account the action targets; it is bound only through the
+/// action hash.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_membership_proof(
+    secret: &Secret,
+    root: &[u8; 32],
+    merkle_path: &MerklePath,
+    pda: &[u8; 32],
+    program_id: &[u8; 32],
+    action_hash: &[u8; 32],
+    nullifier: &Nullifier,
+    action_discriminator: &[u8; 8],
+    action_params: &[u8],
+    nonce: u64,
+    circuit_dir: &Path,
+) -> Result<ProofResult, ProverError> {
+    let expected_public_witness = encode_public_witness(&public_inputs_from_values(&[
+        root,
         program_id,
         action_hash,
-        nullifier,
-        action_params,
-        nonce,
-    )?;
+        nullifier.as_bytes(),
+    ]));
+    let tools = ToolPaths::default();
+    prove_circuit(circuit_dir, &tools, &expected_public_witness, |dir| {
+        write_membership_prover_toml(
+            dir,
+            secret,
+            root,
+            merkle_path,
+            pda,
+            program_id,
+            action_hash,
+            nullifier,
+            action_discriminator,
+            action_params,
+            nonce,
+        )
+    })
+}
 
-    run_cmd(
-        Command::new(tool_path("nargo"))
-            .current_dir(circuit_dir)
-            .arg("execute")
-            .arg(witness_name),
-    )?;
+/// the nargo and sunspot binaries a pipeline runs. `default` reads `NARGO_BIN` and
+/// `SUNSPOT_BIN` and falls back to looking the tools up in PATH.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolPaths {
+    pub nargo: PathBuf,
+    pub sunspot: PathBuf,
+}
 
-    let acir_path = target_dir.join(format!("{circuit_name}.json"));
-    let witness_path = target_dir.join(format!("{witness_name}.gz"));
-    let _witness_guard = TempFileGuard::new(witness_path.clone());
-    if !acir_path.exists() {
-        return Err(ProverError::MissingOutput(acir_path));
+impl Default for ToolPaths {
+    fn default() -> Self {
+        let from_env = |key: &str, name: &str| {
+            std::env::var_os(key)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(name))
+        };
+        Self {
+            nargo: from_env("NARGO_BIN", "nargo"),
+            sunspot: from_env("SUNSPOT_BIN", "sunspot"),
+        }
     }
-    if !witness_path.exists() {
-        return Err(ProverError::MissingOutput(witness_path));
+}
+
+/// compiled circuit artifacts shared read-only by every proof job
+#[derive(Clone, Debug)]
+pub struct CircuitArtifacts {
+    pub name: String,
+    pub acir: PathBuf,
+    pub ccs: PathBuf,
+    pub pk: PathBuf,
+    pub vk: PathBuf,
+}
+
+static SETUP_LOCK: Mutex<()> = Mutex::new(());
+
+/// compiles the circuit and runs the sunspot setup into `<circuit_dir>/target` if the
+/// artifacts are missing. proof jobs only read these files, so run this once before
+/// proving from several processes.
+pub fn prepare_circuit(circuit_dir: &Path) -> Result<CircuitArtifacts, ProverError> {
+    prepare_circuit_with_tools(circuit_dir, &ToolPaths::default())
+}
+
+/// `prepare_circuit` with explicit tool paths
+pub fn prepare_circuit_with_tools(
+    circuit_dir: &Path,
+    tools: &ToolPaths,
+) -> Result<CircuitArtifacts, ProverError> {
+    let _lock = SETUP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let name = read_circuit_name(circuit_dir)?;
+    let target_dir = circuit_dir.join("target");
+    let artifacts = CircuitArtifacts {
+        acir: target_dir.join(format!("{name}.json")),
+        ccs: target_dir.join(format!("{name}.ccs")),
+        pk: target_dir.join(format!("{name}.pk")),
+        vk: target_dir.join(format!("{name}.vk")),
+        name,
+    };
+
+    if !artifacts.acir.exists() {
+        run_cmd(
+            Command::new(&tools.nargo)
+                .current_dir(circuit_dir)
+                .arg("compile"),
+        )?;
     }
-    let acir_rel = PathBuf::from("target").join(format!("{circuit_name}.json"));
+    require_output(&artifacts.acir)?;
 
-    let ccs_path = target_dir.join(format!("{circuit_name}.ccs"));
-    if !ccs_path.exists() {
+    if !artifacts.ccs.exists() {
         run_cmd(
-            Command::new(tool_path("sunspot"))
+            Command::new(&tools.sunspot)
                 .current_dir(circuit_dir)
                 .arg("compile")
-                .arg(&acir_rel),
+                .arg(&artifacts.acir),
         )?;
     }
-    if !ccs_path.exists() {
-        return Err(ProverError::MissingOutput(ccs_path));
-    }
-    let ccs_rel = PathBuf::from("target").join(format!("{circuit_name}.ccs"));
+    require_output(&artifacts.ccs)?;
 
-    let pk_path = target_dir.join(format!("{circuit_name}.pk"));
-    let vk_path = target_dir.join(format!("{circuit_name}.vk"));
-    if !pk_path.exists() || !vk_path.exists() {
+    if !artifacts.pk.exists() || !artifacts.vk.exists() {
         run_cmd(
-            Command::new(tool_path("sunspot"))
+            Command::new(&tools.sunspot)
                 .current_dir(circuit_dir)
                 .arg("setup")
-                .arg(&ccs_rel),
+                .arg(&artifacts.ccs),
         )?;
     }
-    if !pk_path.exists() {
-        return Err(ProverError::MissingOutput(pk_path));
-    }
-    if !vk_path.exists() {
-        return Err(ProverError::MissingOutput(vk_path));
-    }
-    let acir_file = PathBuf::from(format!("{circuit_name}.json"));
-    let witness_file = PathBuf::from(format!("{witness_name}.gz"));
-    let ccs_file = PathBuf::from(format!("{circuit_name}.ccs"));
-    let pk_file = PathBuf::from(format!("{circuit_name}.pk"));
+    require_output(&artifacts.pk)?;
+    require_output(&artifacts.vk)?;
+
+    Ok(artifacts)
+}
+
+fn prove_circuit<F>(
+    circuit_dir: &Path,
+    tools: &ToolPaths,
+    expected_public_witness: &[u8],
+    write_inputs: F,
+) -> Result<ProofResult, ProverError>
+where
+    F: FnOnce(&Path) -> Result<(), ProverError>,
+{
+    let artifacts = prepare_circuit_with_tools(circuit_dir, tools)?;
+    let job = JobWorkspace::new(circuit_dir)?;
+    write_inputs(job.path())?;
+
+    let job_target = job.path().join("target");
+    let acir_file = format!("{}.json", artifacts.name);
+    let witness_file = format!("{WITNESS_NAME}.gz");
+    let ccs_file = format!("{}.ccs", artifacts.name);
+    let pk_file = format!("{}.pk", artifacts.name);
+    // nargo skips acir generation when the target artifact matches the package, so
+    // execute only solves the witness. copied rather than linked: nargo may rewrite the
+    // artifact, and the shared one must stay untouched.
+    fs::copy(&artifacts.acir, job_target.join(&acir_file))?;
+    link_artifact(&artifacts.ccs, &job_target.join(&ccs_file))?;
+    link_artifact(&artifacts.pk, &job_target.join(&pk_file))?;
+
+    run_cmd(
+        Command::new(&tools.nargo)
+            .current_dir(job.path())
+            .arg("execute")
+            .arg(WITNESS_NAME),
+    )?;
+    require_output(&job_target.join(&witness_file))?;
 
     run_cmd(
-        Command::new(tool_path("sunspot"))
-            .current_dir(&target_dir)
+        Command::new(&tools.sunspot)
+            .current_dir(&job_target)
             .arg("prove")
             .arg(&acir_file)
             .arg(&witness_file)
@@ -149,30 +329,50 @@ pub fn generate_proof(
             .arg(&pk_file),
     )?;
 
-    let proof_path = target_dir.join(format!("{circuit_name}.proof"));
-    let public_witness_path = target_dir.join(format!("{circuit_name}.pw"));
-    if !proof_path.exists() {
-        return Err(ProverError::MissingOutput(proof_path));
-    }
-    if !public_witness_path.exists() {
-        return Err(ProverError::MissingOutput(public_witness_path));
-    }
+    let proof_path = job_target.join(format!("{}.proof", artifacts.name));
+    let public_witness_path = job_target.join(format!("{}.pw", artifacts.name));
+    require_output(&proof_path)?;
+    require_output(&public_witness_path)?;
 
     let proof = fs::read(&proof_path)?;
     let public_witness = fs::read(&public_witness_path)?;
-    if public_witness.len() != PUBLIC_WITNESS_SIZE {
+    if public_witness.len() != expected_public_witness.len() {
         return Err(ProverError::InvalidPublicWitness(public_witness.len()));
     }
+    // the public witness is fully determined by the request, so compare it with the
+    // natively encoded one and check the proof decodes to valid curve points
+    if public_witness != expected_public_witness {
+        return Err(ProverError::PublicWitnessMismatch);
+    }
+    GnarkProof::from_bytes(&proof)?;
 
     Ok(ProofResult {
         proof,
         public_witness,
-        proof_path,
-        public_witness_path,
     })
 }
 
-fn read_circuit_name(circuit_dir: &Path) -> Result<String, ProverError> {
+fn require_output(path: &Path) -> Result<(), ProverError> {
+    if path.exists() {
+        Ok(())
+    } else {
+        Err(ProverError::MissingOutput(path.to_path_buf()))
+    }
+}
+
+#[cfg(unix)]
+fn link_artifact(src: &Path, dst: &Path) -> Result<(), ProverError> {
+    std::os::unix::fs::symlink(fs::canonicalize(src)?, dst)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn link_artifact(src: &Path, dst: &Path) -> Result<(), ProverError> {
+    fs::copy(src, dst)?;
+    Ok(())
+}
+
+pub fn read_circuit_name(circuit_dir: &Path) -> Result<String, ProverError> {
     let manifest_path = circuit_dir.join("Nargo.toml");
     let manifest_str = fs::read_to_string(manifest_path)?;
     let manifest: NargoManifest = toml::from_str(&manifest_str)?;
@@ -183,6 +383,7 @@ fn read_circuit_name(circuit_dir: &Path) -> Result<String, ProverError> {
     Ok(name)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn write_prover_toml(
     circuit_dir: &Path,
     secret: &Secret,
@@ -191,6 +392,47 @@ fn write_prover_toml(
     program_id: &[u8; 32],
     action_hash: &[u8; 32],
     nullifier: &Nullifier,
+    action_discriminator: &[u8; 8],
+    action_params: &[u8],
+    nonce: u64,
+) -> Result<(), ProverError> {
+    let prover_path = circuit_dir.join("Prover.toml");
+    let secret_field = bytes_to_field(secret.as_bytes());
+    let secret_dec = field_to_decimal_string(&secret_field);
+    if action_params.len() > ACTION_PARAMS_MAX_LEN {
+        return Err(ProverError::ActionParamsTooLong(action_params.len()));
+    }
+    let mut action_params_padded = [0u8; ACTION_PARAMS_MAX_LEN];
+    action_params_padded[..action_params.len()].copy_from_slice(action_params);
+    let nonce_bytes = nonce.to_le_bytes();
+
+    let content = format!(
+        "secret = \"{}\"\ncommitment = {}\npda = {}\nprogram_id = {}\naction_hash = {}\nnullifier = {}\naction_discriminator = {}\naction_params = {}\naction_params_len = {}\nnonce = {}\n",
+        secret_dec,
+        format_limbs(commitment.as_bytes()),
+        format_limbs(pda),
+        format_limbs(program_id),
+        format_limbs(action_hash),
+        format_limbs(nullifier.as_bytes()),
+        format_byte_array(action_discriminator),
+        format_byte_array(&action_params_padded),
+        action_params.len(),
+        format_byte_array(&nonce_bytes),
+    );
+    write_secret_file(&prover_path, content.as_bytes())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_membership_prover_toml(
+    circuit_dir: &Path,
+    secret: &Secret,
+    root: &[u8; 32],
+    merkle_path: &MerklePath,
+    pda: &[u8; 32],
+    program_id: &[u8; 32],
+    action_hash: &[u8; 32],
+    nullifier: &Nullifier,
+    action_discriminator: &[u8; 8],
     action_params: &[u8],
     nonce: u64,
 ) -> Result<(), ProverError> {
@@ -203,21 +445,28 @@ fn write_prover_toml(
     let mut action_params_padded = [0u8; ACTION_PARAMS_MAX_LEN];
     action_params_padded[..action_params.len()].copy_from_slice(action_params);
     let nonce_bytes = nonce.to_le_bytes();
+    let siblings: Vec<String> = merkle_path
+        .siblings
+        .iter()
+        .map(|node| format!("\"{}\"", field_to_decimal_string(&bytes_to_field(node))))
+        .collect();
 
     let content = format!(
-        "secret = \"{}\"\ncommitment = {}\npda = {}\nprogram_id = {}\naction_hash = {}\nnullifier = {}\naction_params = {}\naction_params_len = {}\nnonce = {}\n",
+        "secret = \"{}\"\nroot = {}\nprogram_id = {}\naction_hash = {}\nnullifier = {}\nmerkle_path = [{}]\nleaf_index = {}\npda = {}\naction_discriminator = {}\naction_params = {}\naction_params_len = {}\nnonce = {}\n",
         secret_dec,
-        format_byte_array(commitment.as_bytes()),
+        format_limbs(root),
+        format_limbs(program_id),
+        format_limbs(action_hash),
+        format_limbs(nullifier.as_bytes()),
+        siblings.join(", "),
+        merkle_path.leaf_index,
         format_byte_array(pda),
-        format_byte_array(program_id),
-        format_byte_array(action_hash),
-        format_byte_array(nullifier.as_bytes()),
+        format_byte_array(action_discriminator),
         format_byte_array(&action_params_padded),
         action_params.len(),
         format_byte_array(&nonce_bytes),
     );
-    fs::write(prover_path, content)?;
-    Ok(())
+    write_secret_file(&prover_path, content.as_bytes())
 }
 
 fn field_to_decimal_string(field: &ark_bn254::Fr) -> String {
@@ -231,17 +480,13 @@ fn format_byte_array(bytes: &[u8]) -> String {
     format!("[{}]", items.join(", "))
 }
 
-fn tool_path<S: AsRef<OsStr>>(name: S) -> PathBuf {
-    let name_ref = name.as_ref();
-    let env_key = format!(
-        "{}_BIN",
-        name_ref.to_string_lossy().to_uppercase()
-    );
-    if let Some(path) = std::env::var_os(env_key) {
-        PathBuf::from(path)
-    } else {
-        PathBuf::from(name_ref)
-    }
+/// splits a 32-byte value into the circuit's big-endian (hi, lo) 128-bit limbs
+fn format_limbs(bytes: &[u8; 32]) -> String {
+    let items: Vec<String> = bytes
+        .chunks_exact(PUBLIC_INPUT_LIMB_BYTES)
+        .map(|limb| format!("\"0x{}\"", hex::encode(limb)))
+        .collect();
+    format!("[{}]", items.join(", "))
 }
 
 fn run_cmd(cmd: &mut Command) -> Result<(), ProverError> {
@@ -264,42 +509,85 @@ fn run_cmd(cmd: &mut Command) -> Result<(), ProverError> {
     Ok(())
 }
 
-struct ProverTomlGuard {
-    path: PathBuf,
-    backup: Option<Vec<u8>>,
+/// private scratch copy of the circuit package for one proof job. the directory is
+/// created 0700, and files that carry the secret (Prover.toml and the solved witness)
+/// are overwritten with zeros before the directory is removed.
+struct JobWorkspace {
+    dir: TempDir,
 }
 
-impl ProverTomlGuard {
-    fn new(path: PathBuf) -> Self {
-        let backup = fs::read(&path).ok();
-        Self { path, backup }
+impl JobWorkspace {
+    fn new(circuit_dir: &Path) -> Result<Self, ProverError> {
+        let dir = tempfile::Builder::new().prefix("zaffre-proof-").tempdir()?;
+        copy_package(circuit_dir, dir.path())?;
+        fs::create_dir(dir.path().join("target"))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
     }
 }
 
-impl Drop for ProverTomlGuard {
+impl Drop for JobWorkspace {
     fn drop(&mut self) {
-        if let Some(contents) = &self.backup {
-            let _ = fs::write(&self.path, contents);
-        } else {
-            let _ = fs::remove_file(&self.path);
-        }
+        let _ = wipe_file(&self.path().join("Prover.toml"));
+        let _ = wipe_file(&self.path().join("target").join(format!("{WITNESS_NAME}.gz")));
     }
 }
 
-struct TempFileGuard {
-    path: PathBuf,
+/// copies the package sources, skipping build output and any checked-in Prover.toml
+fn copy_package(src: &Path, dst: &Path) -> Result<(), ProverError> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == "target" || name == "Prover.toml" {
+            continue;
+        }
+        copy_recursive(&entry.path(), &dst.join(name))?;
+    }
+    Ok(())
 }
 
-impl TempFileGuard {
-    fn new(path: PathBuf) -> Self {
-        Self { path }
+fn copy_recursive(src: &Path, dst: &Path) -> Result<(), ProverError> {
+    if src.is_dir() {
+        fs::create_dir_all(dst)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(src, dst)?;
     }
+    Ok(())
 }
 
-impl Drop for TempFileGuard {
-    fn drop(&mut self) {
-        let _ = fs::remove_file(&self.path);
+fn write_secret_file(path: &Path, contents: &[u8]) -> Result<(), ProverError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
     }
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn wipe_file(path: &Path) -> Result<(), ProverError> {
+    let len = match fs::metadata(path) {
+        Ok(metadata) => metadata.len() as usize,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    file.write_all(&vec![0u8; len])?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -342,6 +630,7 @@ mod tests {
             &program_id,
             &action_hash,
             &nullifier,
+            b"setvalue",
             &params,
             1,
         )
@@ -374,6 +663,7 @@ mod tests {
             &program_id,
             &action_hash,
             &nullifier,
+            b"setvalue",
             &params,
             1,
         )
@@ -381,5 +671,203 @@ mod tests {
 
         let prover_path = dir.join("Prover.toml");
         assert!(prover_path.exists(), "Prover.toml not written");
+        let contents = fs::read_to_string(prover_path).unwrap();
+        assert!(contents
+            .contains("action_discriminator = [115, 101, 116, 118, 97, 108, 117, 101]"));
+    }
+
+    #[test]
+    fn test_membership_prover_toml() {
+        let dir = temp_dir("membership");
+        let secret = Secret::from_bytes([1u8; 32]);
+        let nullifier = Nullifier::from_bytes([3u8; 32]);
+        let mut siblings = [[0u8; 32]; crate::merkle::MERKLE_TREE_DEPTH];
+        siblings[0][0] = 7;
+        let merkle_path = MerklePath {
+            leaf_index: 5,
+            siblings,
+        };
+
+        write_membership_prover_toml(
+            &dir,
+            &secret,
+            &[2u8; 32],
+            &merkle_path,
+            &[4u8; 32],
+            &[5u8; 32],
+            &[6u8; 32],
+            &nullifier,
+            b"setvalue",
+            &[9u8; 8],
+            1,
+        )
+        .expect("expected write_membership_prover_toml to succeed");
+
+        let contents = fs::read_to_string(dir.join("Prover.toml")).unwrap();
+        assert!(contents.contains("leaf_index = 5\n"));
+        assert!(contents.contains("merkle_path = [\"7\", \"0\""));
+        assert!(contents.contains(
+            "root = [\"0x02020202020202020202020202020202\", \"0x02020202020202020202020202020202\"]"
+        ));
+        assert!(!contents.contains("commitment"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prover_toml_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("mode");
+        write_prover_toml(
+            &dir,
+            &Secret::from_bytes([1u8; 32]),
+            &Commitment::from_bytes([2u8; 32]),
+            &[4u8; 32],
+            &[5u8; 32],
+            &[6u8; 32],
+            &Nullifier::from_bytes([3u8; 32]),
+            b"setvalue",
+            &[],
+            1,
+        )
+        .unwrap();
+        let mode = fs::metadata(dir.join("Prover.toml")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_job_workspace_is_isolated_and_wiped() {
+        let circuit_dir = temp_dir("package");
+        fs::create_dir_all(circuit_dir.join("src")).unwrap();
+        fs::create_dir_all(circuit_dir.join("target")).unwrap();
+        fs::write(circuit_dir.join("Nargo.toml"), "[package]\nname = \"stub\"\n").unwrap();
+        fs::write(circuit_dir.join("src").join("main.nr"), "fn main() {}").unwrap();
+        fs::write(circuit_dir.join("Prover.toml"), "secret = \"1\"").unwrap();
+        fs::write(circuit_dir.join("target").join("stub.pk"), "pk").unwrap();
+
+        let first = JobWorkspace::new(&circuit_dir).unwrap();
+        let second = JobWorkspace::new(&circuit_dir).unwrap();
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().join("src").join("main.nr").exists());
+        assert!(!first.path().join("Prover.toml").exists());
+        assert!(!first.path().join("target").join("stub.pk").exists());
+
+        let job_dir = first.path().to_path_buf();
+        write_secret_file(&job_dir.join("Prover.toml"), b"secret = \"2\"").unwrap();
+        drop(first);
+        assert!(!job_dir.exists());
+        assert_eq!(
+            fs::read_to_string(circuit_dir.join("Prover.toml")).unwrap(),
+            "secret = \"1\""
+        );
+    }
+
+    // runs the full pipeline concurrently against stub nargo/sunspot binaries. the stub
+    // nargo only executes against an existing artifact and logs every compile.
+    #[cfg(unix)]
+    #[test]
+    fn test_concurrent_jobs_with_stub_tools() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let circuit_dir = temp_dir("stub_circuit");
+        fs::create_dir_all(circuit_dir.join("src")).unwrap();
+        fs::write(circuit_dir.join("Nargo.toml"), "[package]\nname = \"stub\"\n").unwrap();
+        fs::write(circuit_dir.join("src").join("main.nr"), "fn main() {}").unwrap();
+
+        let secret = Secret::from_bytes([1u8; 32]);
+        let commitment = Commitment::from_bytes([2u8; 32]);
+        let nullifier = Nullifier::from_bytes([3u8; 32]);
+        let (pda, program_id, action_hash) = ([4u8; 32], [5u8; 32], [6u8; 32]);
+        let public_witness = encode_public_witness(&public_inputs_from_values(&[
+            commitment.as_bytes(),
+            &pda,
+            &program_id,
+            &action_hash,
+            nullifier.as_bytes(),
+        ]));
+        let proof = GnarkProof {
+            ar: ark_bn254::G1Affine::default(),
+            bs: ark_bn254::G2Affine::default(),
+            krs: ark_bn254::G1Affine::default(),
+            commitments: vec![ark_bn254::G1Affine::default()],
+            commitment_pok: ark_bn254::G1Affine::default(),
+        }
+        .to_bytes();
+
+        let tools = temp_dir("stub_tools");
+        fs::write(tools.join("stub.proof"), &proof).unwrap();
+        fs::write(tools.join("stub.pw"), &public_witness).unwrap();
+        let nargo = tools.join("nargo");
+        let sunspot = tools.join("sunspot");
+        fs::write(
+            &nargo,
+            format!(
+                "#!/bin/sh\ncase \"$1\" in\n\
+                 compile) echo compile >> {dir}/nargo.log; mkdir -p target; \
+                 echo '{{}}' > target/stub.json ;;\n\
+                 execute) test -f target/stub.json || exit 1; cp Prover.toml \"target/$2.gz\" ;;\n\
+                 esac\n",
+                dir = tools.display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            &sunspot,
+            format!(
+                "#!/bin/sh\ncase \"$1\" in\n\
+                 compile) touch target/stub.ccs ;;\n\
+                 setup) touch target/stub.pk target/stub.vk ;;\n\
+                 prove) cp {dir}/stub.proof stub.proof; cp {dir}/stub.pw stub.pw ;;\n\
+                 esac\n",
+                dir = tools.display()
+            ),
+        )
+        .unwrap();
+        for tool in [&nargo, &sunspot] {
+            fs::set_permissions(tool, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let backend = NargoSunspotBackend::with_tools(&circuit_dir, ToolPaths { nargo, sunspot });
+        let request = ProofRequest {
+            secret,
+            commitment,
+            pda,
+            program_id,
+            action_hash,
+            nullifier,
+            action_discriminator: *b"setvalue",
+            action_params: vec![1u8; 8],
+            nonce: 1,
+        };
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let backend = backend.clone();
+                let request = request.clone();
+                std::thread::spawn(move || backend.prove(&request))
+            })
+            .collect();
+        for handle in handles {
+            let result = handle.join().unwrap().expect("stub proof should succeed");
+            assert_eq!(result.proof, proof);
+            assert_eq!(result.public_witness, public_witness);
+        }
+
+        assert!(!circuit_dir.join("Prover.toml").exists());
+        assert!(!circuit_dir
+            .join("target")
+            .join(format!("{WITNESS_NAME}.gz"))
+            .exists());
+        assert_eq!(fs::read_to_string(tools.join("nargo.log")).unwrap(), "compile\n");
+    }
+
+    #[test]
+    fn test_format_limbs() {
+        let mut bytes = [0u8; 32];
+        bytes[15] = 1;
+        bytes[31] = 0xff;
+        assert_eq!(
+            format_limbs(&bytes),
+            "[\"0x00000000000000000000000000000001\", \"0x000000000000000000000000000000ff\"]"
+        );
     }
 }
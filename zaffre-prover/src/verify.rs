@@ -0,0 +1,519 @@
+//! host-side groth16 verification of gnark proofs, to catch bad proofs before they
+//! reach the on-chain verifier
+
+use std::fs;
+use std::path::Path;
+
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, One, PrimeField, Zero};
+use sha2::{Digest, Sha256};
+
+pub use zaffre_core::witness::PublicInputs;
+
+use crate::gnark::{
+    decode_public_witness, read_g1, read_g2, write_g1, write_g2, GnarkProof, G1_LEN, G2_LEN,
+};
+use crate::proof::{ProofResult, ProverError};
+
+const COMMITMENT_DST: &[u8] = b"bsb22-commitment";
+const CHALLENGE_DST: &[u8] = b"G16-BSB22";
+
+/// gnark groth16 verifying key, as written by `WriteRawTo` (the sunspot `.vk` file)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKey {
+    pub alpha_g1: G1Affine,
+    pub beta_g1: G1Affine,
+    pub beta_g2: G2Affine,
+    pub gamma_g2: G2Affine,
+    pub delta_g1: G1Affine,
+    pub delta_g2: G2Affine,
+    /// one point for the constant, one per public input and one per commitment wire
+    pub k: Vec<G1Affine>,
+    /// for each commitment, the 1-based public witness indices it commits to
+    pub public_and_commitment_committed: Vec<Vec<u64>>,
+    /// pedersen keys per commitment as (g, g_sigma_neg)
+    pub commitment_keys: Vec<(G2Affine, G2Affine)>,
+}
+
+impl VerifyingKey {
+    pub fn read(path: &Path) -> Result<Self, ProverError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// trailing bytes after the commitment keys are ignored, as in the on-chain parser
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProverError> {
+        let mut reader = VkReader { bytes, offset: 0 };
+        let alpha_g1 = reader.g1()?;
+        let beta_g1 = reader.g1()?;
+        let beta_g2 = reader.g2()?;
+        let gamma_g2 = reader.g2()?;
+        let delta_g1 = reader.g1()?;
+        let delta_g2 = reader.g2()?;
+
+        let k_len = reader.u32()?;
+        let k = (0..k_len)
+            .map(|_| reader.g1())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let committed_len = reader.u32()?;
+        let public_and_commitment_committed = (0..committed_len)
+            .map(|_| {
+                let len = reader.u32()?;
+                (0..len).map(|_| reader.u64()).collect()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let keys_len = reader.u32()?;
+        let commitment_keys = (0..keys_len)
+            .map(|_| Ok((reader.g2()?, reader.g2()?)))
+            .collect::<Result<Vec<_>, ProverError>>()?;
+
+        if commitment_keys.len() != public_and_commitment_committed.len()
+            || k.len() < 1 + commitment_keys.len()
+        {
+            return Err(ProverError::InvalidVerifyingKey);
+        }
+
+        Ok(Self {
+            alpha_g1,
+            beta_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g1,
+            delta_g2,
+            k,
+            public_and_commitment_committed,
+            commitment_keys,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_g1(&mut out, &self.alpha_g1);
+        write_g1(&mut out, &self.beta_g1);
+        write_g2(&mut out, &self.beta_g2);
+        write_g2(&mut out, &self.gamma_g2);
+        write_g1(&mut out, &self.delta_g1);
+        write_g2(&mut out, &self.delta_g2);
+        out.extend_from_slice(&(self.k.len() as u32).to_be_bytes());
+        for point in &self.k {
+            write_g1(&mut out, point);
+        }
+        out.extend_from_slice(&(self.public_and_commitment_committed.len() as u32).to_be_bytes());
+        for indices in &self.public_and_commitment_committed {
+            out.extend_from_slice(&(indices.len() as u32).to_be_bytes());
+            for index in indices {
+                out.extend_from_slice(&index.to_be_bytes());
+            }
+        }
+        out.extend_from_slice(&(self.commitment_keys.len() as u32).to_be_bytes());
+        for (g, g_sigma_neg) in &self.commitment_keys {
+            write_g2(&mut out, g);
+            write_g2(&mut out, g_sigma_neg);
+        }
+        out
+    }
+
+    /// number of public inputs the circuit exposes, excluding commitment wires
+    pub fn public_input_count(&self) -> usize {
+        self.k.len() - 1 - self.commitment_keys.len()
+    }
+}
+
+struct VkReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> VkReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProverError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(ProverError::InvalidVerifyingKey)?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, ProverError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ProverError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn g1(&mut self) -> Result<G1Affine, ProverError> {
+        read_g1(self.take(G1_LEN)?).ok_or(ProverError::InvalidVerifyingKey)
+    }
+
+    fn g2(&mut self) -> Result<G2Affine, ProverError> {
+        read_g2(self.take(G2_LEN)?).ok_or(ProverError::InvalidVerifyingKey)
+    }
+}
+
+/// checks the groth16 pairing equation, including gnark's bsb22 commitment extension
+pub fn verify_groth16(
+    vk: &VerifyingKey,
+    proof: &GnarkProof,
+    public_inputs: &[Fr],
+) -> Result<(), ProverError> {
+    if public_inputs.len() != vk.public_input_count()
+        || proof.commitments.len() != vk.commitment_keys.len()
+    {
+        return Err(ProverError::ProofVerificationFailed);
+    }
+
+    let mut inputs = public_inputs.to_vec();
+    if !vk.commitment_keys.is_empty() {
+        let mut wires = Vec::with_capacity(proof.commitments.len() * 32);
+        for (commitment, indices) in proof
+            .commitments
+            .iter()
+            .zip(&vk.public_and_commitment_committed)
+        {
+            let mut prehash = Vec::new();
+            write_g1(&mut prehash, commitment);
+            for index in indices {
+                let input = (*index as usize)
+                    .checked_sub(1)
+                    .and_then(|i| inputs.get(i))
+                    .ok_or(ProverError::InvalidVerifyingKey)?;
+                prehash.extend_from_slice(&input.into_bigint().to_bytes_be());
+            }
+            let wire = hash_to_field(&prehash, COMMITMENT_DST);
+            wires.extend_from_slice(&wire.into_bigint().to_bytes_be());
+            inputs.push(wire);
+        }
+        let challenge = hash_to_field(&wires, CHALLENGE_DST);
+        verify_pedersen(vk, proof, challenge)?;
+    }
+
+    let mut prepared = vk.k[0].into_group();
+    for (input, k) in inputs.iter().zip(&vk.k[1..]) {
+        prepared += *k * input;
+    }
+    for commitment in &proof.commitments {
+        prepared += commitment;
+    }
+
+    // e(A, B) * e(-alpha, beta) * e(K_x, -gamma) * e(C, -delta) == 1
+    let check = Bn254::multi_pairing(
+        [proof.ar, -vk.alpha_g1, prepared.into_affine(), proof.krs],
+        [proof.bs, vk.beta_g2, -vk.gamma_g2, -vk.delta_g2],
+    );
+    if check.is_zero() {
+        Ok(())
+    } else {
+        Err(ProverError::ProofVerificationFailed)
+    }
+}
+
+/// batched proof of knowledge for the pedersen commitments:
+/// prod e(C_i * r^i, g_sigma_neg_i) * e(pok, g) == 1
+fn verify_pedersen(
+    vk: &VerifyingKey,
+    proof: &GnarkProof,
+    challenge: Fr,
+) -> Result<(), ProverError> {
+    let g = vk.commitment_keys[0].0;
+    if vk.commitment_keys.iter().any(|(base, _)| *base != g) {
+        return Err(ProverError::InvalidVerifyingKey);
+    }
+
+    let mut g1 = Vec::with_capacity(proof.commitments.len() + 1);
+    let mut g2 = Vec::with_capacity(proof.commitments.len() + 1);
+    let mut r = Fr::one();
+    for (commitment, (_, g_sigma_neg)) in proof.commitments.iter().zip(&vk.commitment_keys) {
+        g1.push((*commitment * r).into_affine());
+        g2.push(*g_sigma_neg);
+        r *= challenge;
+    }
+    g1.push(proof.commitment_pok);
+    g2.push(g);
+
+    if Bn254::multi_pairing(g1, g2).is_zero() {
+        Ok(())
+    } else {
+        Err(ProverError::ProofVerificationFailed)
+    }
+}
+
+/// gnark's hash-to-field: 48 bytes of expand_message_xmd, reduced modulo r
+fn hash_to_field(msg: &[u8], dst: &[u8]) -> Fr {
+    Fr::from_be_bytes_mod_order(&expand_message_xmd(msg, dst, 48))
+}
+
+/// expand_message_xmd with sha256 (rfc 9380 section 5.3.1), for `len` up to 255 * 32
+fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+
+    let mut hasher = Sha256::new();
+    hasher.update([0u8; 64]);
+    hasher.update(msg);
+    hasher.update((len as u16).to_be_bytes());
+    hasher.update([0u8]);
+    hasher.update(&dst_prime);
+    let b0: [u8; 32] = hasher.finalize().into();
+
+    let mut out = Vec::with_capacity(len.div_ceil(32) * 32);
+    let mut previous = [0u8; 32];
+    for i in 1..=len.div_ceil(32) {
+        let mut hasher = Sha256::new();
+        if i == 1 {
+            hasher.update(b0);
+        } else {
+            let mixed: Vec<u8> = b0.iter().zip(&previous).map(|(a, b)| a ^ b).collect();
+            hasher.update(mixed);
+        }
+        hasher.update([i as u8]);
+        hasher.update(&dst_prime);
+        previous = hasher.finalize().into();
+        out.extend_from_slice(&previous);
+    }
+    out.truncate(len);
+    out
+}
+
+/// verifies gnark `.proof` and `.pw` bytes against a verifying key
+pub fn verify_proof(
+    vk: &VerifyingKey,
+    proof: &[u8],
+    public_witness: &[u8],
+) -> Result<(), ProverError> {
+    let proof = GnarkProof::from_bytes(proof)?;
+    let inputs = decode_public_witness(public_witness)?;
+    verify_groth16(vk, &proof, &inputs)
+}
+
+/// checks that an ownership proof carries `expected` as its public inputs and that it
+/// verifies, so a bad proof is caught before it costs a transaction
+pub fn verify_ownership_proof(
+    vk: &VerifyingKey,
+    result: &ProofResult,
+    expected: &PublicInputs,
+) -> Result<(), ProverError> {
+    if PublicInputs::from_public_witness(&result.public_witness)? != *expected {
+        return Err(ProverError::PublicWitnessMismatch);
+    }
+    verify_proof(vk, &result.proof, &result.public_witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{MockBackend, ProofRequest, ProverBackend};
+    use crate::gnark::{encode_public_witness, public_inputs_from_values};
+    use crate::types::Secret;
+    use ark_ff::{Field, UniformRand};
+
+    fn g1(scalar: Fr) -> G1Affine {
+        (G1Affine::generator() * scalar).into_affine()
+    }
+
+    fn g2(scalar: Fr) -> G2Affine {
+        (G2Affine::generator() * scalar).into_affine()
+    }
+
+    // setup and prover for a circuit with no constraints: the toxic waste is known, so
+    // a proof for any public inputs can be solved from the pairing equation
+    struct TestSetup {
+        vk: VerifyingKey,
+        alpha: Fr,
+        beta: Fr,
+        gamma: Fr,
+        delta: Fr,
+        k: Vec<Fr>,
+        sigma: Fr,
+    }
+
+    impl TestSetup {
+        fn new(public_inputs: usize, committed: Vec<u64>) -> Self {
+            let mut rng = ark_std::test_rng();
+            let mut rand = || Fr::rand(&mut rng);
+            let (alpha, beta, gamma, delta, sigma, base) =
+                (rand(), rand(), rand(), rand(), rand(), rand());
+            let k: Vec<Fr> = (0..public_inputs + 2).map(|_| rand()).collect();
+            let vk = VerifyingKey {
+                alpha_g1: g1(alpha),
+                beta_g1: g1(beta),
+                beta_g2: g2(beta),
+                gamma_g2: g2(gamma),
+                delta_g1: g1(delta),
+                delta_g2: g2(delta),
+                k: k.iter().map(|s| g1(*s)).collect(),
+                public_and_commitment_committed: vec![committed],
+                commitment_keys: vec![(g2(base), g2(-sigma * base))],
+            };
+            Self {
+                vk,
+                alpha,
+                beta,
+                gamma,
+                delta,
+                k,
+                sigma,
+            }
+        }
+
+        fn prove(&self, public_inputs: &[Fr]) -> GnarkProof {
+            let mut rng = ark_std::test_rng();
+            let (a, b, t) = (Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng));
+            let commitment = g1(t);
+
+            let mut prehash = Vec::new();
+            write_g1(&mut prehash, &commitment);
+            for index in &self.vk.public_and_commitment_committed[0] {
+                let input = public_inputs[*index as usize - 1];
+                prehash.extend_from_slice(&input.into_bigint().to_bytes_be());
+            }
+            let wire = hash_to_field(&prehash, COMMITMENT_DST);
+
+            let mut kx = self.k[0] + t;
+            for (input, k) in public_inputs.iter().chain([&wire]).zip(&self.k[1..]) {
+                kx += *input * k;
+            }
+            let c =
+                (a * b - self.alpha * self.beta - kx * self.gamma) * self.delta.inverse().unwrap();
+
+            GnarkProof {
+                ar: g1(a),
+                bs: g2(b),
+                krs: g1(c),
+                commitments: vec![commitment],
+                commitment_pok: g1(self.sigma * t),
+            }
+        }
+    }
+
+    fn ownership_request() -> ProofRequest {
+        ProofRequest::new(
+            Secret::from_bytes([7u8; 32]),
+            [3u8; 32],
+            [4u8; 32],
+            *b"setvalue",
+            &123u64.to_le_bytes(),
+            42,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_expand_message_xmd_vectors() {
+        // rfc 9380 appendix k.1
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        assert_eq!(
+            hex::encode(expand_message_xmd(b"", dst, 0x20)),
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"
+        );
+        assert_eq!(
+            hex::encode(expand_message_xmd(b"abc", dst, 0x20)),
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"
+        );
+        assert_eq!(
+            hex::encode(expand_message_xmd(b"", dst, 0x80)),
+            concat!(
+                "af84c27ccfd45d41914fdff5df25293e221afc53d8ad2ac06d5e3e29485dadbe",
+                "e0d121587713a3e0dd4d5e69e93eb7cd4f5df4cd103e188cf60cb02edc3edf18",
+                "eda8576c412b18ffb658e3dd6ec849469b979d444cf7b26911a08e63cf31f9dc",
+                "c541708d3491184472c2c29bb749d4286b004ceb5ee6b9a7fa5b646c993f0ced",
+            )
+        );
+    }
+
+    #[test]
+    fn test_verifying_key_roundtrip() {
+        let setup = TestSetup::new(10, vec![1, 3]);
+        let bytes = setup.vk.to_bytes();
+        let vk = VerifyingKey::from_bytes(&bytes).unwrap();
+        assert_eq!(vk, setup.vk);
+        assert_eq!(vk.public_input_count(), 10);
+        assert!(matches!(
+            VerifyingKey::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ProverError::InvalidVerifyingKey)
+        ));
+    }
+
+    #[test]
+    fn test_verify_accepts_valid_proof() {
+        let setup = TestSetup::new(10, vec![2]);
+        let request = ownership_request();
+        let inputs = request.public_inputs();
+        let proof = setup.prove(&inputs);
+
+        assert!(verify_groth16(&setup.vk, &proof, &inputs).is_ok());
+        assert!(verify_proof(&setup.vk, &proof.to_bytes(), &request.public_witness()).is_ok());
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering() {
+        let setup = TestSetup::new(10, vec![2]);
+        let inputs = ownership_request().public_inputs();
+        let proof = setup.prove(&inputs);
+
+        let mut other_inputs = inputs.clone();
+        other_inputs[9] += Fr::one();
+        assert!(matches!(
+            verify_groth16(&setup.vk, &proof, &other_inputs),
+            Err(ProverError::ProofVerificationFailed)
+        ));
+
+        // the committed input feeds the commitment wire, so it is checked twice
+        let mut committed = inputs.clone();
+        committed[1] += Fr::one();
+        assert!(verify_groth16(&setup.vk, &proof, &committed).is_err());
+
+        let mut bad_proof = proof.clone();
+        bad_proof.krs = (bad_proof.krs + G1Affine::generator()).into_affine();
+        assert!(verify_groth16(&setup.vk, &bad_proof, &inputs).is_err());
+
+        let mut bad_pok = proof.clone();
+        bad_pok.commitment_pok = G1Affine::generator();
+        assert!(verify_groth16(&setup.vk, &bad_pok, &inputs).is_err());
+
+        assert!(verify_groth16(&setup.vk, &proof, &inputs[..9]).is_err());
+    }
+
+    #[test]
+    fn test_verify_ownership_proof_checks_public_inputs() {
+        let setup = TestSetup::new(10, vec![]);
+        let request = ownership_request();
+        let result = ProofResult {
+            proof: setup.prove(&request.public_inputs()).to_bytes(),
+            public_witness: request.public_witness(),
+        };
+        let expected = request.public_values();
+        assert_eq!(
+            PublicInputs::from_public_witness(&result.public_witness).unwrap(),
+            expected
+        );
+        assert_eq!(expected.to_public_witness(), result.public_witness);
+        assert!(verify_ownership_proof(&setup.vk, &result, &expected).is_ok());
+
+        let mut wrong = expected;
+        wrong.pda = [9u8; 32];
+        assert!(matches!(
+            verify_ownership_proof(&setup.vk, &result, &wrong),
+            Err(ProverError::PublicWitnessMismatch)
+        ));
+
+        // mock proofs decode but never verify
+        let mock = MockBackend.prove(&request).unwrap();
+        assert!(matches!(
+            verify_ownership_proof(&setup.vk, &mock, &expected),
+            Err(ProverError::ProofVerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_public_inputs_reject_wide_limbs() {
+        let mut inputs = public_inputs_from_values(&[&[1u8; 32]; 5]);
+        inputs[0] = -Fr::one();
+        assert!(PublicInputs::from_public_witness(&encode_public_witness(&inputs)).is_err());
+        assert!(PublicInputs::from_public_witness(&encode_public_witness(&inputs[..8])).is_err());
+    }
+}
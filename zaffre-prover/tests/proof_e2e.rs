@@ -1,46 +1,10 @@
-use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
 
-use sha2::{Digest, Sha256};
-
-use zaffre_prover::{compute_commitment, compute_nullifier, generate_proof, Secret};
-
-struct FileBackup {
-    original: PathBuf,
-    backup: Option<PathBuf>,
-}
-
-impl FileBackup {
-    fn new(original: PathBuf) -> Self {
-        if original.exists() {
-            let backup = original.with_extension(format!(
-                "bak.{}",
-                std::process::id()
-            ));
-            let _ = fs::copy(&original, &backup);
-            return Self {
-                original,
-                backup: Some(backup),
-            };
-        }
-        Self {
-            original,
-            backup: None,
-        }
-    }
-}
-
-impl Drop for FileBackup {
-    fn drop(&mut self) {
-        if let Some(backup) = self.backup.take() {
-            let _ = fs::copy(&backup, &self.original);
-            let _ = fs::remove_file(&backup);
-        } else {
-            let _ = fs::remove_file(&self.original);
-        }
-    }
-}
+use zaffre_prover::{
+    verify_ownership_proof, NargoSunspotBackend, ProofRequest, ProverBackend, Secret, ToolPaths,
+    VerifyingKey,
+};
 
 #[test]
 #[ignore]
@@ -55,9 +19,6 @@ fn e2e_generate_and_verify_proof() {
         .join("circuits")
         .join("ownership");
 
-    let secret = Secret::from_bytes([7u8; 32]);
-    let commitment = compute_commitment(&secret);
-
     let program_id = [3u8; 32];
     let pda = [4u8; 32];
 
@@ -66,50 +27,40 @@ fn e2e_generate_and_verify_proof() {
     let params = value.to_le_bytes();
     let nonce = 42u64;
 
-    let mut hasher = Sha256::new();
-    hasher.update(program_id);
-    hasher.update(pda);
-    hasher.update(action_discriminator);
-    hasher.update(params);
-    hasher.update(nonce.to_le_bytes());
-    let action_hash: [u8; 32] = hasher.finalize().into();
-    let nullifier = compute_nullifier(&secret, &program_id, &action_hash);
-
-    let circuit_name = "ownership";
-    let proof_path = circuit_dir
-        .join("target")
-        .join(format!("{circuit_name}.proof"));
-    let public_witness_path = circuit_dir
-        .join("target")
-        .join(format!("{circuit_name}.pw"));
-    let _proof_backup = FileBackup::new(proof_path);
-    let _public_witness_backup = FileBackup::new(public_witness_path);
-
-    let result = generate_proof(
-        &secret,
-        &commitment,
-        &pda,
-        &program_id,
-        &action_hash,
-        &nullifier,
+    let request = ProofRequest::new(
+        Secret::from_bytes([7u8; 32]),
+        program_id,
+        pda,
+        action_discriminator,
         &params,
         nonce,
-        &circuit_dir,
     )
-    .expect("proof generation failed");
+    .expect("invalid proof request");
 
+    let circuit_name = "ownership";
+
+    let result = NargoSunspotBackend::new(&circuit_dir)
+        .prove(&request)
+        .expect("proof generation failed");
+
+    let out_dir = tempfile::tempdir().expect("failed to create output dir");
+    let (proof_path, public_witness_path) = result
+        .write_to(out_dir.path(), circuit_name)
+        .expect("failed to write proof");
     let vk_path = circuit_dir
         .join("target")
         .join(format!("{circuit_name}.vk"));
 
-    let status = Command::new(
-        std::env::var_os("SUNSPOT_BIN").unwrap_or_else(|| "sunspot".into()),
-    )
+    let vk = VerifyingKey::read(&vk_path).expect("failed to read verifying key");
+    verify_ownership_proof(&vk, &result, &request.public_values())
+        .expect("host-side verification failed");
+
+    let status = Command::new(ToolPaths::default().sunspot)
         .current_dir(circuit_dir.join("target"))
         .arg("verify")
         .arg(&vk_path)
-        .arg(&result.proof_path)
-        .arg(&result.public_witness_path)
+        .arg(&proof_path)
+        .arg(&public_witness_path)
         .status()
         .expect("failed to run sunspot verify");
 
@@ -0,0 +1,66 @@
+//! zaffre-relayer: serves the reference relayer over http
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use clap::Parser;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+use zaffre_relayer::{server, RelayPolicy, Relayer, RpcSubmitter};
+
+#[derive(Parser)]
+#[command(
+    name = "zaffre-relayer",
+    version,
+    about = "Zaffre proof bundle relayer"
+)]
+struct Cli {
+    /// fee payer keypair (solana cli json format)
+    #[arg(long, env = "ZAFFRE_RELAYER_KEYPAIR")]
+    keypair: PathBuf,
+    #[arg(long, env = "ZAFFRE_RPC_URL", default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+    /// comma-separated program ids to relay for
+    #[arg(
+        long,
+        env = "ZAFFRE_RELAYER_PROGRAMS",
+        value_delimiter = ',',
+        required = true,
+        value_parser = Pubkey::from_str
+    )]
+    programs: Vec<Pubkey>,
+    /// lamports each relayed transaction must earn after fees
+    #[arg(long, env = "ZAFFRE_RELAYER_MIN_PROFIT", default_value_t = 0)]
+    min_profit: u64,
+    #[arg(long)]
+    compute_unit_limit: Option<u32>,
+    #[arg(long, env = "ZAFFRE_RELAYER_BIND", default_value = "127.0.0.1:8080")]
+    bind: String,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let keypair = match read_keypair_file(&cli.keypair) {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            eprintln!("error: reading {}: {e}", cli.keypair.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let policy = RelayPolicy {
+        allowed_programs: HashSet::from_iter(cli.programs),
+        min_profit: cli.min_profit,
+        compute_unit_limit: cli.compute_unit_limit,
+    };
+    let relayer = Relayer::new(keypair, RpcSubmitter::new(cli.rpc_url), policy);
+    eprintln!("relaying as {} on {}", relayer.pubkey(), cli.bind);
+    match server::serve(&relayer, &cli.bind) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::RelayerError;
+
+/// a zaffre action for the relayer to submit: the program instruction with the proof in
+/// its data, built by the client with the relayer's pubkey as the signing fee payer.
+/// pubkeys are base58 and `data` is base64.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofBundle {
+    pub program_id: String,
+    pub accounts: Vec<BundleAccount>,
+    pub data: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleAccount {
+    pub pubkey: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl ProofBundle {
+    pub fn from_instruction(instruction: &Instruction) -> Self {
+        Self {
+            program_id: instruction.program_id.to_string(),
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| BundleAccount {
+                    pubkey: meta.pubkey.to_string(),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: BASE64.encode(&instruction.data),
+        }
+    }
+
+    pub fn to_instruction(&self) -> Result<Instruction, RelayerError> {
+        let pubkey = |field: &'static str, value: &str| {
+            Pubkey::from_str(value).map_err(|_| RelayerError::InvalidBundle(field))
+        };
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|account| {
+                Ok(AccountMeta {
+                    pubkey: pubkey("accounts", &account.pubkey)?,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+            })
+            .collect::<Result<_, RelayerError>>()?;
+        Ok(Instruction {
+            program_id: pubkey("program_id", &self.program_id)?,
+            accounts,
+            data: BASE64
+                .decode(&self.data)
+                .map_err(|_| RelayerError::InvalidBundle("data"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let instruction = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![
+                AccountMeta::new(Pubkey::new_unique(), true),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            ],
+            data: vec![1, 2, 3],
+        };
+        let bundle = ProofBundle::from_instruction(&instruction);
+        let json = serde_json::to_string(&bundle).unwrap();
+        let parsed: ProofBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.to_instruction().unwrap(), instruction);
+
+        let mut bad = bundle;
+        bad.data = "not base64!".to_string();
+        assert!(matches!(
+            bad.to_instruction(),
+            Err(RelayerError::InvalidBundle("data"))
+        ));
+    }
+}
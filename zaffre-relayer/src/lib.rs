@@ -0,0 +1,33 @@
+//! reference relayer: submits zaffre proof bundles as the fee payer, so the user's wallet
+//! never signs (or pays for) their actions
+
+pub mod bundle;
+pub mod relayer;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "server")]
+pub mod server;
+
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+pub use bundle::{BundleAccount, ProofBundle};
+pub use relayer::{RelayPolicy, Relayer, Submitter};
+#[cfg(feature = "rpc")]
+pub use rpc::RpcSubmitter;
+
+#[derive(Debug, Error)]
+pub enum RelayerError {
+    #[error("invalid proof bundle: bad {0}")]
+    InvalidBundle(&'static str),
+    #[error("program {0} is not relayed")]
+    ProgramNotAllowed(Pubkey),
+    #[error("bundle requires a signature from {0}")]
+    UnexpectedSigner(Pubkey),
+    #[error("simulation failed: {0}")]
+    SimulationFailed(String),
+    #[error("relaying would earn {profit} lamports, below the minimum {min_profit}")]
+    Unprofitable { profit: i128, min_profit: u64 },
+    #[error("rpc error: {0}")]
+    Rpc(String),
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::{ProofBundle, RelayerError};
+
+/// the cluster a relayer submits to; `RpcSubmitter` talks json-rpc, tests use a stand-in
+pub trait Submitter {
+    fn latest_blockhash(&self) -> Result<Hash, RelayerError>;
+    fn balance(&self, pubkey: &Pubkey) -> Result<u64, RelayerError>;
+    /// simulates `transaction` and returns the lamports `watch` holds afterwards, with the
+    /// transaction fee already deducted
+    fn simulate(&self, transaction: &Transaction, watch: &Pubkey) -> Result<u64, RelayerError>;
+    fn send(&self, transaction: &Transaction) -> Result<Signature, RelayerError>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RelayPolicy {
+    /// programs whose instructions are relayed; anything else is rejected
+    pub allowed_programs: HashSet<Pubkey>,
+    /// lamports the relayer must come out ahead after fees (`pay_relayer` minus the
+    /// transaction fee)
+    pub min_profit: u64,
+    /// prepended as a compute budget instruction when set
+    pub compute_unit_limit: Option<u32>,
+}
+
+pub struct Relayer<S> {
+    keypair: Keypair,
+    submitter: S,
+    policy: RelayPolicy,
+}
+
+impl<S: Submitter> Relayer<S> {
+    pub fn new(keypair: Keypair, submitter: S, policy: RelayPolicy) -> Self {
+        Self {
+            keypair,
+            submitter,
+            policy,
+        }
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    pub fn policy(&self) -> &RelayPolicy {
+        &self.policy
+    }
+
+    pub fn submitter(&self) -> &S {
+        &self.submitter
+    }
+
+    /// checks, signs, simulates and submits `bundle`
+    pub fn relay(&self, bundle: &ProofBundle) -> Result<Signature, RelayerError> {
+        let transaction = self.prepare(bundle)?;
+        self.submitter.send(&transaction)
+    }
+
+    /// builds the signed transaction for `bundle` with the relayer as fee payer and
+    /// checks by simulation that it pays the relayer at least `min_profit`
+    pub fn prepare(&self, bundle: &ProofBundle) -> Result<Transaction, RelayerError> {
+        let instruction = bundle.to_instruction()?;
+        if !self
+            .policy
+            .allowed_programs
+            .contains(&instruction.program_id)
+        {
+            return Err(RelayerError::ProgramNotAllowed(instruction.program_id));
+        }
+        let relayer = self.pubkey();
+        // the relayer is the only signer; anything else would link a user wallet again
+        if let Some(meta) = instruction
+            .accounts
+            .iter()
+            .find(|meta| meta.is_signer && meta.pubkey != relayer)
+        {
+            return Err(RelayerError::UnexpectedSigner(meta.pubkey));
+        }
+
+        let mut instructions = Vec::with_capacity(2);
+        if let Some(units) = self.policy.compute_unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        instructions.push(instruction);
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&relayer),
+            &[&self.keypair],
+            self.submitter.latest_blockhash()?,
+        );
+
+        let before = self.submitter.balance(&relayer)?;
+        let after = self.submitter.simulate(&transaction, &relayer)?;
+        let profit = after as i128 - before as i128;
+        if profit < self.policy.min_profit as i128 {
+            return Err(RelayerError::Unprofitable {
+                profit,
+                min_profit: self.policy.min_profit,
+            });
+        }
+        Ok(transaction)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::RefCell;
+
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+
+    use super::*;
+
+    /// local validator stand-in: simulation credits the relayer `payout` and charges
+    /// `fee`, and sent transactions are recorded
+    pub(crate) struct MockSubmitter {
+        pub balance: u64,
+        pub payout: u64,
+        pub fee: u64,
+        pub sent: RefCell<Vec<Transaction>>,
+    }
+
+    impl MockSubmitter {
+        pub(crate) fn new(payout: u64) -> Self {
+            Self {
+                balance: 1_000_000,
+                payout,
+                fee: 5_000,
+                sent: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Submitter for MockSubmitter {
+        fn latest_blockhash(&self) -> Result<Hash, RelayerError> {
+            Ok(Hash::new_from_array([9u8; 32]))
+        }
+
+        fn balance(&self, _pubkey: &Pubkey) -> Result<u64, RelayerError> {
+            Ok(self.balance)
+        }
+
+        fn simulate(
+            &self,
+            transaction: &Transaction,
+            _watch: &Pubkey,
+        ) -> Result<u64, RelayerError> {
+            transaction
+                .verify()
+                .map_err(|e| RelayerError::SimulationFailed(e.to_string()))?;
+            Ok(self.balance + self.payout - self.fee)
+        }
+
+        fn send(&self, transaction: &Transaction) -> Result<Signature, RelayerError> {
+            self.sent.borrow_mut().push(transaction.clone());
+            Ok(transaction.signatures[0])
+        }
+    }
+
+    pub(crate) fn relayer(payout: u64, program_id: Pubkey) -> Relayer<MockSubmitter> {
+        let policy = RelayPolicy {
+            allowed_programs: HashSet::from([program_id]),
+            min_profit: 1_000,
+            compute_unit_limit: Some(1_400_000),
+        };
+        Relayer::new(Keypair::new(), MockSubmitter::new(payout), policy)
+    }
+
+    pub(crate) fn bundle(program_id: Pubkey, signer: Pubkey) -> ProofBundle {
+        ProofBundle::from_instruction(&Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new(signer, true),
+            ],
+            data: vec![7u8; 64],
+        })
+    }
+
+    #[test]
+    fn test_relay_signs_as_fee_payer() {
+        let program_id = Pubkey::new_unique();
+        let relayer = relayer(10_000, program_id);
+        let signature = relayer
+            .relay(&bundle(program_id, relayer.pubkey()))
+            .unwrap();
+
+        let sent = relayer.submitter().sent.borrow();
+        assert_eq!(sent.len(), 1);
+        let message = &sent[0].message;
+        assert_eq!(message.account_keys[0], relayer.pubkey());
+        assert_eq!(message.header.num_required_signatures, 1);
+        assert_eq!(message.instructions.len(), 2);
+        assert_eq!(sent[0].signatures[0], signature);
+    }
+
+    #[test]
+    fn test_relay_rejects_bad_bundles() {
+        let program_id = Pubkey::new_unique();
+        let relayer = relayer(10_000, program_id);
+
+        let other = Pubkey::new_unique();
+        let err = relayer.relay(&bundle(other, relayer.pubkey())).unwrap_err();
+        assert!(matches!(err, RelayerError::ProgramNotAllowed(key) if key == other));
+
+        let user = Pubkey::new_unique();
+        let err = relayer.relay(&bundle(program_id, user)).unwrap_err();
+        assert!(matches!(err, RelayerError::UnexpectedSigner(key) if key == user));
+        assert!(relayer.submitter().sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_relay_requires_profit_after_fees() {
+        let program_id = Pubkey::new_unique();
+        // the payout covers the transaction fee but not the minimum profit
+        let relayer = relayer(5_500, program_id);
+        let err = relayer
+            .relay(&bundle(program_id, relayer.pubkey()))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Unprofitable {
+                profit: 500,
+                min_profit: 1_000
+            }
+        ));
+        assert!(relayer.submitter().sent.borrow().is_empty());
+    }
+}
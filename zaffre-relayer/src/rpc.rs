@@ -0,0 +1,151 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+use crate::{RelayerError, Submitter};
+
+/// `Submitter` over plain solana json-rpc
+pub struct RpcSubmitter {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl RpcSubmitter {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Self::DEFAULT_TIMEOUT)
+                .build(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, RelayerError> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(body)
+            .map_err(|e| RelayerError::Rpc(e.to_string()))?
+            .into_json()
+            .map_err(|e| RelayerError::Rpc(format!("invalid response: {e}")))?;
+        if let Some(error) = response.get("error") {
+            return Err(RelayerError::Rpc(format!("{method}: {error}")));
+        }
+        response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| RelayerError::Rpc(format!("{method}: missing result")))
+    }
+}
+
+impl Submitter for RpcSubmitter {
+    fn latest_blockhash(&self) -> Result<Hash, RelayerError> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        result["value"]["blockhash"]
+            .as_str()
+            .and_then(|hash| Hash::from_str(hash).ok())
+            .ok_or_else(|| RelayerError::Rpc("getLatestBlockhash: bad blockhash".to_string()))
+    }
+
+    fn balance(&self, pubkey: &Pubkey) -> Result<u64, RelayerError> {
+        let result = self.call(
+            "getBalance",
+            json!([pubkey.to_string(), { "commitment": "confirmed" }]),
+        )?;
+        result["value"]
+            .as_u64()
+            .ok_or_else(|| RelayerError::Rpc("getBalance: bad balance".to_string()))
+    }
+
+    fn simulate(&self, transaction: &Transaction, watch: &Pubkey) -> Result<u64, RelayerError> {
+        let config = json!({
+            "encoding": "base64",
+            "sigVerify": true,
+            "commitment": "confirmed",
+            "accounts": { "encoding": "base64", "addresses": [watch.to_string()] },
+        });
+        let result = self.call(
+            "simulateTransaction",
+            json!([encode_transaction(transaction)?, config]),
+        )?;
+        parse_simulation(&result)
+    }
+
+    fn send(&self, transaction: &Transaction) -> Result<Signature, RelayerError> {
+        let config = json!({ "encoding": "base64", "preflightCommitment": "confirmed" });
+        let result = self.call(
+            "sendTransaction",
+            json!([encode_transaction(transaction)?, config]),
+        )?;
+        result
+            .as_str()
+            .and_then(|signature| Signature::from_str(signature).ok())
+            .ok_or_else(|| RelayerError::Rpc("sendTransaction: bad signature".to_string()))
+    }
+}
+
+fn encode_transaction(transaction: &Transaction) -> Result<String, RelayerError> {
+    let bytes = bincode::serialize(transaction)
+        .map_err(|e| RelayerError::Rpc(format!("serialize transaction: {e}")))?;
+    Ok(BASE64.encode(bytes))
+}
+
+/// post-simulation lamports of the single watched account; a missing account holds none
+fn parse_simulation(result: &Value) -> Result<u64, RelayerError> {
+    let value = &result["value"];
+    if !value["err"].is_null() {
+        let logs = value["logs"]
+            .as_array()
+            .map(|logs| {
+                logs.iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        return Err(RelayerError::SimulationFailed(format!(
+            "{}\n{logs}",
+            value["err"]
+        )));
+    }
+    match &value["accounts"][0] {
+        Value::Null => Ok(0),
+        account => account["lamports"]
+            .as_u64()
+            .ok_or_else(|| RelayerError::Rpc("simulateTransaction: bad accounts".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simulation() {
+        let ok = json!({ "value": { "err": null, "accounts": [{ "lamports": 42 }] } });
+        assert_eq!(parse_simulation(&ok).unwrap(), 42);
+
+        let closed = json!({ "value": { "err": null, "accounts": [null] } });
+        assert_eq!(parse_simulation(&closed).unwrap(), 0);
+
+        let failed = json!({
+            "value": { "err": { "InstructionError": [1, { "Custom": 6003 }] }, "logs": ["log"] }
+        });
+        let err = parse_simulation(&failed).unwrap_err();
+        assert!(matches!(err, RelayerError::SimulationFailed(msg) if msg.ends_with("log")));
+    }
+}
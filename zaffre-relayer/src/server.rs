@@ -0,0 +1,112 @@
+//! http front end: `GET /relayer` tells clients which pubkey to bind as relayer and
+//! `POST /relay` takes a json `ProofBundle` and returns the transaction signature
+
+use std::io::Read;
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+use crate::{ProofBundle, Relayer, RelayerError, Submitter};
+
+/// largest request body `serve` reads. a bundle has to fit in one transaction, so real ones
+/// are a few kilobytes of json
+pub const MAX_BUNDLE_BYTES: u64 = 64 * 1024;
+
+/// handles one request, returning the status code and json body
+pub fn handle<S: Submitter>(
+    relayer: &Relayer<S>,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> (u16, Value) {
+    match (method, path) {
+        ("GET", "/relayer") => (
+            200,
+            json!({
+                "pubkey": relayer.pubkey().to_string(),
+                "min_profit": relayer.policy().min_profit,
+            }),
+        ),
+        ("POST", "/relay") => {
+            let result = serde_json::from_str::<ProofBundle>(body)
+                .map_err(|_| RelayerError::InvalidBundle("json"))
+                .and_then(|bundle| relayer.relay(&bundle));
+            match result {
+                Ok(signature) => (200, json!({ "signature": signature.to_string() })),
+                Err(err @ RelayerError::Rpc(_)) => (502, json!({ "error": err.to_string() })),
+                Err(err) => (400, json!({ "error": err.to_string() })),
+            }
+        }
+        _ => (404, json!({ "error": "not found" })),
+    }
+}
+
+/// serves `relayer` on `addr` until the process exits; requests are handled one at a time
+pub fn serve<S: Submitter>(relayer: &Relayer<S>, addr: &str) -> Result<(), RelayerError> {
+    let server = Server::http(addr).map_err(|e| RelayerError::Rpc(e.to_string()))?;
+    for mut request in server.incoming_requests() {
+        let (status, value) = match read_body(request.as_reader()) {
+            Ok(body) => handle(relayer, request.method().as_str(), request.url(), &body),
+            Err(error) => error,
+        };
+        let content_type =
+            Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(content_type);
+        // a client hanging up early is not the relayer's problem
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+/// reads at most `MAX_BUNDLE_BYTES`, so an oversized body is rejected without buffering it
+fn read_body(reader: impl Read) -> Result<String, (u16, Value)> {
+    let mut body = Vec::new();
+    reader
+        .take(MAX_BUNDLE_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|_| (400, json!({ "error": "failed to read body" })))?;
+    if body.len() as u64 > MAX_BUNDLE_BYTES {
+        return Err((413, json!({ "error": "body too large" })));
+    }
+    String::from_utf8(body).map_err(|_| (400, json!({ "error": "body must be utf-8" })))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::relayer::tests::{bundle, relayer};
+
+    #[test]
+    fn test_handle_routes() {
+        let program_id = Pubkey::new_unique();
+        let relayer = relayer(10_000, program_id);
+
+        let (status, body) = handle(&relayer, "GET", "/relayer", "");
+        assert_eq!(status, 200);
+        assert_eq!(body["pubkey"], relayer.pubkey().to_string());
+        assert_eq!(body["min_profit"], 1_000);
+
+        let request = serde_json::to_string(&bundle(program_id, relayer.pubkey())).unwrap();
+        let (status, body) = handle(&relayer, "POST", "/relay", &request);
+        assert_eq!(status, 200);
+        assert!(body["signature"].is_string());
+
+        let (status, _) = handle(&relayer, "POST", "/relay", "{}");
+        assert_eq!(status, 400);
+        let (status, _) = handle(&relayer, "GET", "/", "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn test_read_body_limits() {
+        let max = MAX_BUNDLE_BYTES as usize;
+        assert_eq!(read_body(&b"{}"[..]).unwrap(), "{}");
+        assert_eq!(read_body(&vec![b' '; max][..]).unwrap().len(), max);
+        assert_eq!(read_body(&vec![b' '; max + 1][..]).unwrap_err().0, 413);
+        assert_eq!(read_body(&[0xff][..]).unwrap_err().0, 400);
+    }
+}
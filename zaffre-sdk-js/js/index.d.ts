@@ -4,3 +4,10 @@ export function deriveZaffrePda(
   programIdBytes: Uint8Array,
   commitmentBytes: Uint8Array,
 ): { address: Uint8Array; bump: number };
+
+export function deriveSecretFromWallet(
+  signMessage: (message: Uint8Array) => Promise<Uint8Array>,
+  programIdBytes: Uint8Array,
+  purpose: string,
+  index: number,
+): Promise<Uint8Array>;
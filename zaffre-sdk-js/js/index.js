@@ -21,7 +21,22 @@ function deriveZaffrePda(programIdBytes, commitmentBytes) {
   return { address: new Uint8Array(address.toBytes()), bump };
 }
 
+// signs the derivation message twice and refuses wallets whose signatures differ,
+// since their secrets could never be derived again
+async function deriveSecretFromWallet(signMessage, programIdBytes, purpose, index) {
+  const prover = wasm.ZaffreProver.init();
+  const message = prover.walletDerivationMessage(programIdBytes, purpose, index);
+  const first = new Uint8Array(await signMessage(message));
+  const second = new Uint8Array(await signMessage(message));
+  assertLen("signature", first, 64);
+  if (!Buffer.from(first).equals(Buffer.from(second))) {
+    throw new Error("wallet signatures are not deterministic; use a master seed instead");
+  }
+  return prover.secretFromWalletSignature(first, programIdBytes, purpose, index);
+}
+
 module.exports = {
   ...wasm,
   deriveZaffrePda,
+  deriveSecretFromWallet,
 };
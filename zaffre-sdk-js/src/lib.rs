@@ -1,15 +1,13 @@
 use js_sys::Uint8Array;
-use sha2::{Digest, Sha256};
 use wasm_bindgen::prelude::*;
 
+use zaffre_core::{
+    compute_action_hash, compute_action_params_digest, encode_relayed_action_params,
+    secret_from_wallet_signature, wallet_derivation_message, CloseAction, PublicInputs,
+    RotateAction, ZaffreAction, OWNERSHIP_ACTION_PARAMS_MAX_LEN,
+};
 use zaffre_prover::{compute_commitment, compute_nullifier, Secret};
 
-const PUBLIC_INPUT_BYTES: usize = 160;
-const PUBLIC_INPUT_COUNT: usize = PUBLIC_INPUT_BYTES;
-const PUBLIC_WITNESS_HEADER_LEN: usize = 12;
-const PUBLIC_WITNESS_SIZE: usize = PUBLIC_WITNESS_HEADER_LEN + PUBLIC_INPUT_COUNT * 32;
-const ACTION_PARAMS_MAX_LEN: u32 = 32;
-
 #[wasm_bindgen]
 pub struct ZaffreProver;
 
@@ -65,22 +63,125 @@ impl ZaffreProver {
         let program_bytes = read_32(&program_id, "programId")?;
         let pda_bytes = read_32(&pda, "pda")?;
         let discriminator = read_8(&action_discriminator, "actionDiscriminator")?;
-        if action_params.length() > ACTION_PARAMS_MAX_LEN {
+        if action_params.length() as usize > OWNERSHIP_ACTION_PARAMS_MAX_LEN {
             return Err(JsValue::from_str(
                 "actionParams must be <= 32 bytes for the ownership circuit",
             ));
         }
         let params = action_params.to_vec();
 
-        let mut hasher = Sha256::new();
-        hasher.update(program_bytes);
-        hasher.update(pda_bytes);
-        hasher.update(discriminator);
-        hasher.update(params);
-        hasher.update(nonce.to_le_bytes());
-        let digest = hasher.finalize();
+        let hash = compute_action_hash(&program_bytes, &pda_bytes, &discriminator, &params, nonce);
+        Ok(Uint8Array::from(hash.as_slice()))
+    }
+
+    #[wasm_bindgen(js_name = computeActionParamsDigest)]
+    pub fn compute_action_params_digest(&self, action_params: Uint8Array) -> Uint8Array {
+        let digest = compute_action_params_digest(&action_params.to_vec());
+        Uint8Array::from(digest.as_slice())
+    }
+
+    #[wasm_bindgen(js_name = computeActionHashWithParamsDigest)]
+    pub fn compute_action_hash_with_params_digest(
+        &self,
+        program_id: Uint8Array,
+        pda: Uint8Array,
+        action_discriminator: Uint8Array,
+        action_params: Uint8Array,
+        nonce: u64,
+    ) -> Result<Uint8Array, JsValue> {
+        let digest = self.compute_action_params_digest(action_params);
+        self.compute_action_hash(program_id, pda, action_discriminator, digest, nonce)
+    }
+
+    /// action hash binding `relayer` and `fee`; the proof's action params are
+    /// `computeActionParamsDigest(actionParams || relayer || fee_le)`
+    #[wasm_bindgen(js_name = computeRelayedActionHash)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_relayed_action_hash(
+        &self,
+        program_id: Uint8Array,
+        pda: Uint8Array,
+        action_discriminator: Uint8Array,
+        action_params: Uint8Array,
+        relayer: Uint8Array,
+        fee: u64,
+        nonce: u64,
+    ) -> Result<Uint8Array, JsValue> {
+        let relayer_bytes = read_32(&relayer, "relayer")?;
+        let encoded = encode_relayed_action_params(&action_params.to_vec(), &relayer_bytes, fee);
+        self.compute_action_hash_with_params_digest(
+            program_id,
+            pda,
+            action_discriminator,
+            Uint8Array::from(encoded.as_slice()),
+            nonce,
+        )
+    }
+
+    /// action hash of a rotation to `newCommitment`, with the program's `zaffre_core::RotateAction`
+    #[wasm_bindgen(js_name = computeRotateActionHash)]
+    pub fn compute_rotate_action_hash(
+        &self,
+        program_id: Uint8Array,
+        pda: Uint8Array,
+        new_commitment: Uint8Array,
+        nonce: u64,
+    ) -> Result<Uint8Array, JsValue> {
+        let action = RotateAction {
+            new_commitment: read_32(&new_commitment, "newCommitment")?,
+        };
+        let program_bytes = read_32(&program_id, "programId")?;
+        let pda_bytes = read_32(&pda, "pda")?;
+        let hash = action.action_hash(&program_bytes, &pda_bytes, nonce);
+        Ok(Uint8Array::from(hash.as_slice()))
+    }
+
+    /// action hash of closing `pda` to `destination`, with the program's `zaffre_core::CloseAction`
+    #[wasm_bindgen(js_name = computeCloseActionHash)]
+    pub fn compute_close_action_hash(
+        &self,
+        program_id: Uint8Array,
+        pda: Uint8Array,
+        destination: Uint8Array,
+        nonce: u64,
+    ) -> Result<Uint8Array, JsValue> {
+        let action = CloseAction {
+            destination: read_32(&destination, "destination")?,
+        };
+        let program_bytes = read_32(&program_id, "programId")?;
+        let pda_bytes = read_32(&pda, "pda")?;
+        let hash = action.action_hash(&program_bytes, &pda_bytes, nonce);
+        Ok(Uint8Array::from(hash.as_slice()))
+    }
 
-        Ok(Uint8Array::from(digest.as_slice()))
+    /// the message to pass to the wallet's `signMessage` for `secretFromWalletSignature`
+    #[wasm_bindgen(js_name = walletDerivationMessage)]
+    pub fn wallet_derivation_message(
+        &self,
+        program_id: Uint8Array,
+        purpose: &str,
+        index: u32,
+    ) -> Result<Uint8Array, JsValue> {
+        let program_bytes = read_32(&program_id, "programId")?;
+        let message = wallet_derivation_message(&program_bytes, purpose, index)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(Uint8Array::from(message.as_slice()))
+    }
+
+    /// only safe with wallets that sign deterministically; see `deriveSecretFromWallet`
+    #[wasm_bindgen(js_name = secretFromWalletSignature)]
+    pub fn secret_from_wallet_signature(
+        &self,
+        signature: Uint8Array,
+        program_id: Uint8Array,
+        purpose: &str,
+        index: u32,
+    ) -> Result<Uint8Array, JsValue> {
+        let signature_bytes = read_64(&signature, "signature")?;
+        let program_bytes = read_32(&program_id, "programId")?;
+        let secret = secret_from_wallet_signature(&signature_bytes, &program_bytes, purpose, index)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(Uint8Array::from(secret.as_bytes().as_slice()))
     }
 
     #[wasm_bindgen(js_name = serializePublicInputs)]
@@ -92,29 +193,14 @@ impl ZaffreProver {
         action_hash: Uint8Array,
         nullifier: Uint8Array,
     ) -> Result<Uint8Array, JsValue> {
-        let commitment_bytes = read_32(&commitment, "commitment")?;
-        let pda_bytes = read_32(&pda, "pda")?;
-        let program_bytes = read_32(&program_id, "programId")?;
-        let action_bytes = read_32(&action_hash, "actionHash")?;
-        let nullifier_bytes = read_32(&nullifier, "nullifier")?;
-
-        let mut raw = [0u8; PUBLIC_INPUT_BYTES];
-        raw[0..32].copy_from_slice(&commitment_bytes);
-        raw[32..64].copy_from_slice(&pda_bytes);
-        raw[64..96].copy_from_slice(&program_bytes);
-        raw[96..128].copy_from_slice(&action_bytes);
-        raw[128..PUBLIC_INPUT_BYTES].copy_from_slice(&nullifier_bytes);
-
-        let mut witness = Vec::with_capacity(PUBLIC_WITNESS_SIZE);
-        witness.extend_from_slice(&(PUBLIC_INPUT_COUNT as u32).to_be_bytes());
-        witness.extend_from_slice(&0u32.to_be_bytes());
-        witness.extend_from_slice(&(PUBLIC_INPUT_COUNT as u32).to_be_bytes());
-        for b in raw {
-            witness.extend_from_slice(&[0u8; 31]);
-            witness.push(b);
-        }
-
-        Ok(Uint8Array::from(witness.as_slice()))
+        let inputs = PublicInputs {
+            commitment: read_32(&commitment, "commitment")?,
+            pda: read_32(&pda, "pda")?,
+            program_id: read_32(&program_id, "programId")?,
+            action_hash: read_32(&action_hash, "actionHash")?,
+            nullifier: read_32(&nullifier, "nullifier")?,
+        };
+        Ok(Uint8Array::from(inputs.to_public_witness().as_slice()))
     }
 }
 
@@ -130,6 +216,18 @@ fn read_32(arr: &Uint8Array, name: &str) -> Result<[u8; 32], JsValue> {
     Ok(out)
 }
 
+fn read_64(arr: &Uint8Array, name: &str) -> Result<[u8; 64], JsValue> {
+    if arr.length() != 64 {
+        return Err(JsValue::from_str(&format!(
+            "{name} must be 64 bytes (got {})",
+            arr.length()
+        )));
+    }
+    let mut out = [0u8; 64];
+    arr.copy_to(&mut out);
+    Ok(out)
+}
+
 fn read_8(arr: &Uint8Array, name: &str) -> Result<[u8; 8], JsValue> {
     if arr.length() != 8 {
         return Err(JsValue::from_str(&format!(